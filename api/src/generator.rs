@@ -0,0 +1,42 @@
+use models::GeneratorInvocation;
+
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+#[error("Line {line}: {message}")]
+pub struct ScriptError {
+    pub line: usize,
+    pub message: String,
+}
+
+/// Whether `name` can name a generator: letters, digits, `_` and `-`.
+pub fn valid_generator_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+/// Parses a generator script, one invocation per line: a generator name followed
+/// by the arguments it is run with, separated by whitespace. Blank lines and lines
+/// starting with `#` are skipped.
+pub fn parse_generator_script(script: &str) -> Result<Vec<GeneratorInvocation>, ScriptError> {
+    let mut invocations = Vec::new();
+    for (index, line) in script.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let mut words = line.split_whitespace();
+        let generator = words.next().unwrap_or_default();
+        if !valid_generator_name(generator) {
+            return Err(ScriptError {
+                line: index + 1,
+                message: format!("Invalid generator name {}", generator),
+            });
+        }
+        invocations.push(GeneratorInvocation {
+            generator: generator.to_string(),
+            args: words.map(str::to_string).collect(),
+        });
+    }
+    Ok(invocations)
+}
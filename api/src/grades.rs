@@ -0,0 +1,207 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use models::ExecStatus;
+use serde::{Deserialize, Serialize};
+
+pub struct Student {
+    pub user_id: i64,
+    pub username: String,
+}
+
+/// A submission to an assignment problem, in the order it was made.
+pub struct GradedSubmission {
+    pub user_id: i64,
+    pub problem_id: i64,
+    pub status: String,
+    pub score: i32,
+    /// Made after the assignment was due.
+    pub late: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ProblemGrade {
+    pub problem_id: i64,
+    /// Best score after the late penalty.
+    pub best_score: i32,
+    /// Judged submissions, late ones included.
+    pub attempts: i64,
+    pub solved: bool,
+    /// The best score came from a late submission.
+    pub late: bool,
+    pub first_submitted_at: Option<DateTime<Utc>>,
+    /// When the best score was first reached.
+    pub best_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct StudentGrades {
+    pub user_id: i64,
+    pub username: String,
+    pub total: i64,
+    pub problems: Vec<ProblemGrade>,
+}
+
+/// A gradebook column: a problem and the label it is exported under.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct GradebookProblem {
+    pub problem_id: i64,
+    pub label: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Gradebook {
+    pub problems: Vec<GradebookProblem>,
+    pub students: Vec<StudentGrades>,
+}
+
+impl Gradebook {
+    /// One row per student, with the score, attempts, solved and late flags and
+    /// timestamps of each problem in its own columns.
+    pub fn to_csv(&self) -> String {
+        let mut header = vec!["user_id".to_string(), "username".into(), "total".into()];
+        for problem in &self.problems {
+            for column in [
+                "score",
+                "attempts",
+                "solved",
+                "late",
+                "first_submitted_at",
+                "best_at",
+            ] {
+                header.push(format!("{} {}", problem.label, column));
+            }
+        }
+        let mut csv = csv_line(&header);
+        for student in &self.students {
+            let mut fields = vec![
+                student.user_id.to_string(),
+                student.username.clone(),
+                student.total.to_string(),
+            ];
+            for grade in &student.problems {
+                fields.push(grade.best_score.to_string());
+                fields.push(grade.attempts.to_string());
+                fields.push(grade.solved.to_string());
+                fields.push(grade.late.to_string());
+                for at in [grade.first_submitted_at, grade.best_at] {
+                    fields.push(at.map(|at| at.to_rfc3339()).unwrap_or_default());
+                }
+            }
+            csv.push_str(&csv_line(&fields));
+        }
+        csv
+    }
+}
+
+/// Joins fields into a CRLF-terminated RFC 4180 line, quoting where needed. Fields
+/// a spreadsheet would run as a formula, such as usernames and labels starting
+/// with `=`, get a leading `'` so they open as text.
+fn csv_line(fields: &[String]) -> String {
+    let mut line = fields
+        .iter()
+        .map(|field| {
+            let field = if field.starts_with(['=', '+', '-', '@']) {
+                format!("'{}", field)
+            } else {
+                field.clone()
+            };
+            if field.contains([',', '"', '\r', '\n']) {
+                format!("\"{}\"", field.replace('"', "\"\""))
+            } else {
+                field
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(",");
+    line.push_str("\r\n");
+    line
+}
+
+/// Score a late submission keeps under a penalty of `penalty_percent`.
+pub fn late_score(score: i32, penalty_percent: i32) -> i32 {
+    score * (100 - penalty_percent.clamp(0, 100)) / 100
+}
+
+/// Grades every student on every problem from their best submission. Late scores
+/// lose `late_penalty_percent`; pending submissions and judge errors don't count,
+/// and submissions by non-students or to other problems are skipped.
+pub fn grade_students(
+    late_penalty_percent: i32,
+    problem_ids: &[i64],
+    students: &[Student],
+    submissions: &[GradedSubmission],
+) -> Vec<StudentGrades> {
+    let columns: HashMap<i64, usize> = problem_ids
+        .iter()
+        .enumerate()
+        .map(|(column, problem_id)| (*problem_id, column))
+        .collect();
+    let mut rows: Vec<StudentGrades> = students
+        .iter()
+        .map(|student| StudentGrades {
+            user_id: student.user_id,
+            username: student.username.clone(),
+            total: 0,
+            problems: problem_ids
+                .iter()
+                .map(|problem_id| ProblemGrade {
+                    problem_id: *problem_id,
+                    best_score: 0,
+                    attempts: 0,
+                    solved: false,
+                    late: false,
+                    first_submitted_at: None,
+                    best_at: None,
+                })
+                .collect(),
+        })
+        .collect();
+    let row_of: HashMap<i64, usize> = rows
+        .iter()
+        .enumerate()
+        .map(|(index, row)| (row.user_id, index))
+        .collect();
+
+    let passed: &str = ExecStatus::Passed.into();
+    let pending: &str = ExecStatus::Pending.into();
+    let judge_error: &str = ExecStatus::JudgeError.into();
+    for submission in submissions {
+        let (Some(&row), Some(&column)) = (
+            row_of.get(&submission.user_id),
+            columns.get(&submission.problem_id),
+        ) else {
+            continue;
+        };
+        let status = submission.status.as_str();
+        if status == pending || status == judge_error {
+            continue;
+        }
+        let grade = &mut rows[row].problems[column];
+        grade.attempts += 1;
+        grade
+            .first_submitted_at
+            .get_or_insert(submission.created_at);
+        grade.solved |= status == passed;
+        let score = if submission.late {
+            late_score(submission.score, late_penalty_percent)
+        } else {
+            submission.score
+        };
+        if score > grade.best_score || grade.best_at.is_none() {
+            grade.best_score = grade.best_score.max(score);
+            grade.best_at = Some(submission.created_at);
+            grade.late = submission.late;
+        }
+    }
+
+    for row in &mut rows {
+        row.total = row
+            .problems
+            .iter()
+            .map(|grade| grade.best_score as i64)
+            .sum();
+    }
+    rows
+}
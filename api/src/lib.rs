@@ -1,5 +1,11 @@
 pub mod configuration;
+pub mod generator;
+pub mod grades;
+pub mod outbox;
+pub mod package;
+pub mod pool;
 pub mod routes;
+pub mod scoreboard;
 pub mod settings;
 pub mod startup;
 pub use settings::*;
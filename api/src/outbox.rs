@@ -0,0 +1,231 @@
+use std::time::Duration;
+
+use actix_web::HttpResponse;
+use lapin::{
+    BasicProperties, Channel, ExchangeKind,
+    options::{BasicPublishOptions, ExchangeDeclareOptions},
+    types::FieldTable,
+};
+use models::{ExecStatus, OutboxConfig};
+use serde_json::json;
+use sqlx::PgPool;
+use tracing::{error, info, instrument, warn};
+
+use crate::pool::ChannelPool;
+
+pub use models::outbox::enqueue;
+
+pub const CODE_EXCHANGE: &str = "code";
+
+#[derive(thiserror::Error, Debug)]
+pub enum OutboxError {
+    #[error("{0}")]
+    QueueError(#[from] lapin::Error),
+
+    #[error("{0}")]
+    DatabaseError(#[from] sqlx::Error),
+
+    #[error("{0}")]
+    PoolError(#[from] deadpool::managed::PoolError<lapin::Error>),
+
+    #[error("Broker refused message for submission {0}")]
+    Nacked(i64),
+}
+
+pub async fn declare_exchange(channel: &Channel) -> lapin::Result<()> {
+    channel
+        .exchange_declare(
+            CODE_EXCHANGE,
+            ExchangeKind::Direct,
+            ExchangeDeclareOptions::default(),
+            FieldTable::default(),
+        )
+        .await
+}
+
+/// Marks a task published by the request handler so the relay skips it.
+pub async fn mark_published(pgpool: &PgPool, submission_id: i64) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        "UPDATE submission_outbox SET published_at = NOW() WHERE submission_id = $1 AND published_at IS NULL",
+        submission_id
+    )
+    .execute(pgpool)
+    .await?;
+    Ok(())
+}
+
+/// Publishes to the `code` exchange on a confirm-mode channel and waits for the broker ack.
+pub async fn publish(
+    channel: &Channel,
+    submission_id: i64,
+    routing_key: &str,
+    payload: &[u8],
+) -> Result<(), OutboxError> {
+    let confirmation = channel
+        .basic_publish(
+            CODE_EXCHANGE,
+            routing_key,
+            BasicPublishOptions::default(),
+            payload,
+            BasicProperties::default(),
+        )
+        .await?
+        .await?;
+
+    if confirmation.is_nack() {
+        return Err(OutboxError::Nacked(submission_id));
+    }
+    Ok(())
+}
+
+/// Publishes a task already recorded with [`enqueue`] and marks it sent. On failure
+/// the task is still committed in the outbox, and the relay retries it.
+pub async fn dispatch(
+    pgpool: &PgPool,
+    channel_pool: &ChannelPool,
+    submission_id: i64,
+    routing_key: &str,
+    payload: &[u8],
+) -> Result<(), OutboxError> {
+    let channel = channel_pool.get().await?;
+    publish(&channel, submission_id, routing_key, payload).await?;
+
+    // the broker already has the task, at worst the relay publishes it a second time
+    if let Err(e) = mark_published(pgpool, submission_id).await {
+        warn!("Failed to mark outbox row published: {}", e);
+    }
+    Ok(())
+}
+
+/// Answers a request that queued `submission_id`: `200` once the broker confirmed
+/// the task, `202` while it waits in the outbox for the relay.
+pub fn queued_response(submission_id: i64, dispatched: Result<(), OutboxError>) -> HttpResponse {
+    let mut response = match dispatched {
+        Ok(()) => HttpResponse::Ok(),
+        Err(e) => {
+            warn!(
+                "Task for submission {} left to the outbox relay: {}",
+                submission_id, e
+            );
+            HttpResponse::Accepted()
+        }
+    };
+    response.json(json!({ "submission_id": submission_id }))
+}
+
+struct OutboxRow {
+    id: i64,
+    submission_id: i64,
+    routing_key: String,
+    payload: Vec<u8>,
+}
+
+async fn relay_batch(
+    pgpool: &PgPool,
+    channel_pool: &ChannelPool,
+    config: &OutboxConfig,
+) -> Result<usize, OutboxError> {
+    let mut transaction = pgpool.begin().await?;
+
+    // rows younger than relay_after_ms are still being published by the submit handler
+    let rows = sqlx::query_as!(
+        OutboxRow,
+        "SELECT id, submission_id, routing_key, payload FROM submission_outbox WHERE published_at IS NULL AND created_at < NOW() - make_interval(secs => $2) ORDER BY id LIMIT $1 FOR UPDATE SKIP LOCKED",
+        config.batch_size,
+        config.relay_after_ms as f64 / 1000.0
+    )
+    .fetch_all(transaction.as_mut())
+    .await?;
+
+    if rows.is_empty() {
+        return Ok(0);
+    }
+    let channel = channel_pool.get().await?;
+
+    let mut published = 0;
+    for row in rows {
+        // stop at the first failure, the remaining rows stay locked until commit
+        // and are picked up again on the next poll
+        if let Err(e) = publish(&channel, row.submission_id, &row.routing_key, &row.payload).await {
+            error!("Failed to relay submission_id={}: {}", row.submission_id, e);
+            break;
+        }
+        sqlx::query!(
+            "UPDATE submission_outbox SET published_at = NOW() WHERE id = $1",
+            row.id
+        )
+        .execute(transaction.as_mut())
+        .await?;
+        published += 1;
+    }
+
+    transaction.commit().await?;
+    Ok(published)
+}
+
+#[instrument(skip_all)]
+pub async fn relay(pgpool: PgPool, channel_pool: ChannelPool, config: OutboxConfig) {
+    info!("Outbox relay started");
+    let poll_interval = Duration::from_millis(config.poll_interval_ms);
+    loop {
+        match relay_batch(&pgpool, &channel_pool, &config).await {
+            Ok(0) => tokio::time::sleep(poll_interval).await,
+            Ok(published) => info!("Relayed {} submissions from outbox", published),
+            Err(e) => {
+                error!("Outbox relay failed: {}", e);
+                tokio::time::sleep(poll_interval).await;
+            }
+        }
+    }
+}
+
+async fn sweep_once(pgpool: &PgPool, config: &OutboxConfig) -> Result<(), sqlx::Error> {
+    let pending: &str = ExecStatus::Pending.into();
+    let stuck_after = config.stuck_after_secs as f64;
+
+    let rearmed = sqlx::query!(
+        "UPDATE submission_outbox o SET published_at = NULL, republish_count = o.republish_count + 1 FROM submit_status s WHERE o.submission_id = s.submission_id AND s.status = $1 AND o.published_at < NOW() - make_interval(secs => $2) AND o.republish_count < $3 RETURNING o.submission_id",
+        pending,
+        stuck_after,
+        config.max_republish
+    )
+    .fetch_all(pgpool)
+    .await?;
+
+    for row in rearmed {
+        warn!(
+            "Submission {} stuck in PENDING, scheduled for republish",
+            row.submission_id
+        );
+    }
+
+    // bump the counter past the limit so each abandoned submission is reported once
+    let abandoned = sqlx::query!(
+        "UPDATE submission_outbox o SET republish_count = o.republish_count + 1 FROM submit_status s WHERE o.submission_id = s.submission_id AND s.status = $1 AND o.published_at < NOW() - make_interval(secs => $2) AND o.republish_count = $3 RETURNING o.submission_id",
+        pending,
+        stuck_after,
+        config.max_republish
+    )
+    .fetch_all(pgpool)
+    .await?;
+
+    for row in abandoned {
+        error!(
+            "Submission {} still PENDING after {} republishes, giving up",
+            row.submission_id, config.max_republish
+        );
+    }
+    Ok(())
+}
+
+#[instrument(skip_all)]
+pub async fn sweep(pgpool: PgPool, config: OutboxConfig) {
+    info!("Pending submission sweeper started");
+    let mut interval = tokio::time::interval(Duration::from_secs(config.sweep_interval_secs));
+    loop {
+        interval.tick().await;
+        if let Err(e) = sweep_once(&pgpool, &config).await {
+            error!("Pending submission sweep failed: {}", e);
+        }
+    }
+}
@@ -0,0 +1,556 @@
+use std::collections::BTreeMap;
+use std::io::{Cursor, Read, Write};
+
+use models::{Checker, TestcaseLimits};
+use roxmltree::Node;
+use serde::{Deserialize, Serialize};
+use zip::{ZipArchive, ZipWriter, write::SimpleFileOptions};
+
+pub const MANIFEST_FORMAT: &str = "crabjudge-problem";
+pub const MANIFEST_VERSION: u32 = 1;
+const MANIFEST_FILE: &str = "problem.json";
+const KATTIS_FILE: &str = "problem.yaml";
+const POLYGON_FILE: &str = "problem.xml";
+/// Files a package may hold besides its testcases.
+const PACKAGE_EXTRA_FILES: usize = 256;
+
+#[derive(thiserror::Error, Debug)]
+pub enum PackageError {
+    #[error("Invalid problem package: {0}")]
+    Invalid(String),
+
+    #[error("Unsupported problem package: {0}")]
+    Unsupported(String),
+
+    #[error("{0}")]
+    TooLarge(String),
+}
+
+fn invalid(message: impl ToString) -> PackageError {
+    PackageError::Invalid(message.to_string())
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct PackageLimits {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub time_limit_ms: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memory_limit_mb: Option<i32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PackageFormat {
+    Crabjudge,
+    Kattis,
+    Polygon,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct PackageTestcase {
+    pub input: String,
+    pub output: String,
+    pub sample: bool,
+}
+
+/// A problem as read from or written to a package, independent of the layout.
+#[derive(Debug, PartialEq, Eq)]
+pub struct ProblemPackage {
+    pub title: String,
+    /// Kattis and Polygon packages carry no difficulty.
+    pub difficulty: Option<String>,
+    pub statement: String,
+    pub limits: PackageLimits,
+    pub checker: Checker,
+    pub testcases: Vec<PackageTestcase>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ManifestTestcase {
+    input: String,
+    output: String,
+    #[serde(default)]
+    sample: bool,
+}
+
+/// `problem.json`, the manifest of the native package format.
+#[derive(Serialize, Deserialize)]
+struct Manifest {
+    format: String,
+    version: u32,
+    title: String,
+    difficulty: String,
+    statement: String,
+    #[serde(default)]
+    limits: PackageLimits,
+    #[serde(default)]
+    checker: Checker,
+    testcases: Vec<ManifestTestcase>,
+}
+
+/// Files of an archive keyed by their path relative to the package root,
+/// `None` for the binary ones.
+struct PackageFiles(BTreeMap<String, Option<String>>);
+
+impl PackageFiles {
+    fn read(archive: &[u8], limits: &TestcaseLimits) -> Result<PackageFiles, PackageError> {
+        if archive.len() > limits.max_archive_bytes {
+            return Err(PackageError::TooLarge(format!(
+                "Package exceeds {} bytes",
+                limits.max_archive_bytes
+            )));
+        }
+        let mut zip = ZipArchive::new(Cursor::new(archive)).map_err(invalid)?;
+        // two files per testcase, plus statements, checkers and sources
+        let max_files = 2 * limits.max_testcases + PACKAGE_EXTRA_FILES;
+        if zip.len() > max_files {
+            return Err(PackageError::TooLarge(format!(
+                "Package has more than {} files",
+                max_files
+            )));
+        }
+
+        let max_bytes = limits.max_testcase_bytes as u64;
+        let mut unpacked = 0;
+        let mut files = BTreeMap::new();
+        for i in 0..zip.len() {
+            let file = zip.by_index(i).map_err(invalid)?;
+            if file.is_dir() {
+                continue;
+            }
+            let path = file
+                .enclosed_name()
+                .ok_or_else(|| invalid(format!("unsafe path {}", file.name())))?
+                .to_string_lossy()
+                .replace('\\', "/");
+            if file.size() > max_bytes {
+                return Err(PackageError::TooLarge(format!(
+                    "{} exceeds {} bytes",
+                    path, limits.max_testcase_bytes
+                )));
+            }
+            // the declared size can lie, so cap the bytes actually read as well
+            let budget = limits.max_unpacked_bytes - unpacked;
+            let mut content = Vec::new();
+            file.take(max_bytes.min(budget as u64) + 1)
+                .read_to_end(&mut content)
+                .map_err(invalid)?;
+            if content.len() as u64 > max_bytes {
+                return Err(PackageError::TooLarge(format!(
+                    "{} exceeds {} bytes",
+                    path, limits.max_testcase_bytes
+                )));
+            }
+            if content.len() > budget {
+                return Err(PackageError::TooLarge(format!(
+                    "Package unpacks to more than {} bytes",
+                    limits.max_unpacked_bytes
+                )));
+            }
+            unpacked += content.len();
+            // packages carry images we never read, binaries only fail once a reader needs them
+            files.insert(path, String::from_utf8(content).ok());
+        }
+        Ok(PackageFiles(files))
+    }
+
+    /// Re-roots the files at the directory holding the shallowest of `markers`,
+    /// so archives of a whole package directory are accepted as well.
+    fn rooted(self, markers: &[&str]) -> Option<(PackageFiles, String)> {
+        let depth = |dir: &str| {
+            if dir.is_empty() {
+                0
+            } else {
+                dir.matches('/').count() + 1
+            }
+        };
+        let (root, marker) = self
+            .0
+            .keys()
+            .filter_map(|path| {
+                let (dir, name) = path.rsplit_once('/').unwrap_or(("", path));
+                markers.contains(&name).then_some((dir, name))
+            })
+            .min_by_key(|(dir, _)| (depth(dir), *dir))
+            .map(|(dir, name)| (dir.to_string(), name.to_string()))?;
+
+        if root.is_empty() {
+            return Some((self, marker));
+        }
+        let prefix = format!("{}/", root);
+        let files = self
+            .0
+            .into_iter()
+            .filter_map(|(path, content)| {
+                path.strip_prefix(&prefix)
+                    .map(|path| (path.to_string(), content))
+            })
+            .collect();
+        Some((PackageFiles(files), marker))
+    }
+
+    fn get(&self, path: &str) -> Result<Option<&str>, PackageError> {
+        self.0
+            .get(path)
+            .map(|content| text(path, content))
+            .transpose()
+    }
+
+    fn require(&self, path: &str) -> Result<&str, PackageError> {
+        self.get(path)?
+            .ok_or_else(|| invalid(format!("missing {}", path)))
+    }
+}
+
+fn text<'a>(path: &str, content: &'a Option<String>) -> Result<&'a str, PackageError> {
+    content
+        .as_deref()
+        .ok_or_else(|| invalid(format!("{} is not a text file", path)))
+}
+
+/// Reads a problem package, detecting the native, Kattis and Polygon layouts.
+pub fn read_package(
+    archive: &[u8],
+    limits: &TestcaseLimits,
+) -> Result<(PackageFormat, ProblemPackage), PackageError> {
+    let files = PackageFiles::read(archive, limits)?;
+    let (files, marker) = files
+        .rooted(&[MANIFEST_FILE, KATTIS_FILE, POLYGON_FILE])
+        .ok_or_else(|| {
+            invalid(format!(
+                "expected {}, {} or {}",
+                MANIFEST_FILE, KATTIS_FILE, POLYGON_FILE
+            ))
+        })?;
+
+    let (format, package) = match marker.as_str() {
+        MANIFEST_FILE => (PackageFormat::Crabjudge, read_native(&files)?),
+        KATTIS_FILE => (PackageFormat::Kattis, read_kattis(&files)?),
+        _ => (PackageFormat::Polygon, read_polygon(&files)?),
+    };
+
+    if package.testcases.is_empty() {
+        return Err(invalid("package has no testcases"));
+    }
+    if package.testcases.len() > limits.max_testcases {
+        return Err(PackageError::TooLarge(format!(
+            "Package has more than {} testcases",
+            limits.max_testcases
+        )));
+    }
+    Ok((format, package))
+}
+
+fn read_native(files: &PackageFiles) -> Result<ProblemPackage, PackageError> {
+    let manifest: Manifest =
+        serde_json::from_str(files.require(MANIFEST_FILE)?).map_err(invalid)?;
+    if manifest.format != MANIFEST_FORMAT {
+        return Err(invalid(format!("unknown format {}", manifest.format)));
+    }
+    if manifest.version != MANIFEST_VERSION {
+        return Err(PackageError::Unsupported(format!(
+            "manifest version {}, expected {}",
+            manifest.version, MANIFEST_VERSION
+        )));
+    }
+
+    let testcases = manifest
+        .testcases
+        .iter()
+        .map(|testcase| {
+            Ok(PackageTestcase {
+                input: files.require(&testcase.input)?.to_string(),
+                output: files.require(&testcase.output)?.to_string(),
+                sample: testcase.sample,
+            })
+        })
+        .collect::<Result<_, PackageError>>()?;
+
+    Ok(ProblemPackage {
+        title: manifest.title,
+        difficulty: Some(manifest.difficulty),
+        statement: manifest.statement,
+        limits: manifest.limits,
+        checker: manifest.checker,
+        testcases,
+    })
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum KattisName {
+    Plain(String),
+    Localized(BTreeMap<String, String>),
+}
+
+#[derive(Deserialize, Default)]
+struct KattisLimits {
+    /// Seconds, only present in the 2023-07 format.
+    time_limit: Option<f64>,
+    /// MiB.
+    memory: Option<i32>,
+}
+
+#[derive(Deserialize)]
+struct KattisProblem {
+    name: Option<KattisName>,
+    #[serde(default)]
+    limits: KattisLimits,
+    validation: Option<String>,
+    validator_flags: Option<String>,
+}
+
+fn read_kattis(files: &PackageFiles) -> Result<ProblemPackage, PackageError> {
+    let problem: KattisProblem =
+        serde_yaml_ng::from_str(files.require(KATTIS_FILE)?).map_err(invalid)?;
+
+    if problem
+        .validation
+        .as_deref()
+        .is_some_and(|validation| validation != "default")
+    {
+        return Err(PackageError::Unsupported("custom output validators".into()));
+    }
+    let flags: Vec<&str> = problem
+        .validator_flags
+        .as_deref()
+        .unwrap_or_default()
+        .split_whitespace()
+        .collect();
+    if flags.iter().any(|flag| flag.starts_with("float")) {
+        return Err(PackageError::Unsupported("float tolerance".into()));
+    }
+    let checker = if flags.contains(&"space_change_sensitive") {
+        Checker::Exact
+    } else {
+        Checker::Tokens
+    };
+
+    let title = match problem.name {
+        Some(KattisName::Plain(name)) => name,
+        Some(KattisName::Localized(names)) => names
+            .get("en")
+            .or_else(|| names.values().next())
+            .cloned()
+            .unwrap_or_default(),
+        None => String::new(),
+    };
+
+    let statement = ["problem_statement", "statement"]
+        .iter()
+        .flat_map(|dir| {
+            [
+                "problem.en.md",
+                "problem.md",
+                "problem.en.tex",
+                "problem.tex",
+            ]
+            .map(|name| format!("{}/{}", dir, name))
+        })
+        .find_map(|path| files.get(&path).transpose())
+        .transpose()?
+        .ok_or_else(|| invalid("missing problem statement"))?
+        .to_string();
+
+    // samples first, then secret data, each in path order as Kattis judges them
+    let mut testcases = Vec::new();
+    for (group, sample) in [("data/sample/", true), ("data/secret/", false)] {
+        for (path, input) in files.0.range(group.to_string()..) {
+            if !path.starts_with(group) {
+                break;
+            }
+            let Some(stem) = path.strip_suffix(".in") else {
+                continue;
+            };
+            let output = files.require(&format!("{}.ans", stem))?;
+            testcases.push(PackageTestcase {
+                input: text(path, input)?.to_string(),
+                output: output.to_string(),
+                sample,
+            });
+        }
+    }
+
+    Ok(ProblemPackage {
+        title,
+        difficulty: None,
+        statement,
+        limits: PackageLimits {
+            time_limit_ms: problem
+                .limits
+                .time_limit
+                .map(|secs| (secs * 1000.0).ceil() as i32),
+            memory_limit_mb: problem.limits.memory,
+        },
+        checker,
+        testcases,
+    })
+}
+
+/// Expands a Polygon path pattern such as `tests/%02d` for a 1-based test index.
+fn polygon_path(pattern: &str, index: usize) -> Result<String, PackageError> {
+    let start = pattern
+        .find('%')
+        .ok_or_else(|| invalid(format!("bad path pattern {}", pattern)))?;
+    let end = pattern[start..]
+        .find('d')
+        .map(|end| start + end)
+        .ok_or_else(|| invalid(format!("bad path pattern {}", pattern)))?;
+    let width: usize = pattern[start + 1..end]
+        .trim_start_matches('0')
+        .parse()
+        .unwrap_or(0);
+    Ok(format!(
+        "{}{:0width$}{}",
+        &pattern[..start],
+        index,
+        &pattern[end + 1..]
+    ))
+}
+
+fn polygon_checker(name: &str) -> Result<Checker, PackageError> {
+    match name.trim_end_matches(".cpp") {
+        "std::wcmp" | "std::ncmp" | "std::hcmp" => Ok(Checker::Tokens),
+        "std::lcmp" => Ok(Checker::Lines),
+        "std::fcmp" => Ok(Checker::Exact),
+        other => Err(PackageError::Unsupported(format!("checker {}", other))),
+    }
+}
+
+fn child<'a, 'input>(node: Node<'a, 'input>, name: &str) -> Option<Node<'a, 'input>> {
+    node.children().find(|n| n.has_tag_name(name))
+}
+
+fn child_number(node: Node<'_, '_>, name: &str) -> Option<i64> {
+    child(node, name)?.text()?.trim().parse().ok()
+}
+
+fn read_polygon(files: &PackageFiles) -> Result<ProblemPackage, PackageError> {
+    let xml = roxmltree::Document::parse(files.require(POLYGON_FILE)?).map_err(invalid)?;
+    let root = xml.root_element();
+
+    let names: Vec<_> = child(root, "names")
+        .map(|names| {
+            names
+                .children()
+                .filter(|n| n.has_tag_name("name"))
+                .collect()
+        })
+        .unwrap_or_default();
+    let title = names
+        .iter()
+        .find(|n| n.attribute("language") == Some("english"))
+        .or_else(|| names.first())
+        .and_then(|n| n.attribute("value"))
+        .unwrap_or_default()
+        .to_string();
+
+    let testset = child(root, "judging")
+        .and_then(|judging| {
+            judging
+                .children()
+                .find(|n| n.has_tag_name("testset") && n.attribute("name") == Some("tests"))
+        })
+        .ok_or_else(|| invalid("missing tests testset"))?;
+    let input_pattern = child(testset, "input-path-pattern")
+        .and_then(|n| n.text())
+        .ok_or_else(|| invalid("missing input-path-pattern"))?;
+    let answer_pattern = child(testset, "answer-path-pattern")
+        .and_then(|n| n.text())
+        .ok_or_else(|| invalid("missing answer-path-pattern"))?;
+
+    let tests: Vec<_> = child(testset, "tests")
+        .map(|tests| {
+            tests
+                .children()
+                .filter(|n| n.has_tag_name("test"))
+                .collect()
+        })
+        .unwrap_or_default();
+    let testcases = tests
+        .iter()
+        .enumerate()
+        .map(|(i, test)| {
+            Ok(PackageTestcase {
+                input: files
+                    .require(&polygon_path(input_pattern, i + 1)?)?
+                    .to_string(),
+                output: files
+                    .require(&polygon_path(answer_pattern, i + 1)?)?
+                    .to_string(),
+                sample: test.attribute("sample") == Some("true"),
+            })
+        })
+        .collect::<Result<_, PackageError>>()?;
+
+    let checker = child(root, "assets")
+        .and_then(|assets| child(assets, "checker"))
+        .and_then(|checker| checker.attribute("name"))
+        .map(polygon_checker)
+        .transpose()?
+        .unwrap_or_default();
+
+    // prefer the plain sections over the full statement, which relies on olymp.sty
+    let sections: Vec<&str> = ["legend", "input", "output", "notes"]
+        .iter()
+        .filter_map(|section| {
+            files
+                .get(&format!("statement-sections/english/{}.tex", section))
+                .transpose()
+        })
+        .collect::<Result<_, PackageError>>()?;
+    let statement = if sections.is_empty() {
+        files.require("statements/english/problem.tex")?.to_string()
+    } else {
+        sections.join("\n\n")
+    };
+
+    Ok(ProblemPackage {
+        title,
+        difficulty: None,
+        statement,
+        limits: PackageLimits {
+            time_limit_ms: child_number(testset, "time-limit").map(|ms| ms as i32),
+            memory_limit_mb: child_number(testset, "memory-limit")
+                .map(|bytes| (bytes / (1024 * 1024)) as i32),
+        },
+        checker,
+        testcases,
+    })
+}
+
+/// Writes a package in the native format, testcases stored as `tests/N.in`/`tests/N.ans`.
+pub fn write_package(package: &ProblemPackage) -> Result<Vec<u8>, zip::result::ZipError> {
+    let testcases = (1..=package.testcases.len())
+        .zip(&package.testcases)
+        .map(|(n, testcase)| ManifestTestcase {
+            input: format!("tests/{}.in", n),
+            output: format!("tests/{}.ans", n),
+            sample: testcase.sample,
+        })
+        .collect::<Vec<_>>();
+
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    let options = SimpleFileOptions::default();
+    for (entry, testcase) in testcases.iter().zip(&package.testcases) {
+        zip.start_file(entry.input.as_str(), options)?;
+        zip.write_all(testcase.input.as_bytes())?;
+        zip.start_file(entry.output.as_str(), options)?;
+        zip.write_all(testcase.output.as_bytes())?;
+    }
+
+    let manifest = Manifest {
+        format: MANIFEST_FORMAT.into(),
+        version: MANIFEST_VERSION,
+        title: package.title.clone(),
+        difficulty: package.difficulty.clone().unwrap_or_default(),
+        statement: package.statement.clone(),
+        limits: package.limits,
+        checker: package.checker,
+        testcases,
+    };
+    zip.start_file(MANIFEST_FILE, options)?;
+    serde_json::to_writer_pretty(&mut zip, &manifest).map_err(std::io::Error::from)?;
+
+    Ok(zip.finish()?.into_inner())
+}
@@ -0,0 +1,39 @@
+use deadpool::managed::{self, Manager};
+use lapin::{Channel, options::ConfirmSelectOptions};
+
+pub type ChannelPool = managed::Pool<ChannelManager>;
+
+/// Hands out channels already switched to publisher confirm mode.
+pub struct ChannelManager {
+    conn: lapin::Connection,
+}
+
+impl ChannelManager {
+    pub fn new(conn: lapin::Connection) -> ChannelManager {
+        ChannelManager { conn }
+    }
+}
+
+impl Manager for ChannelManager {
+    type Type = Channel;
+    type Error = lapin::Error;
+
+    async fn create(&self) -> Result<Self::Type, Self::Error> {
+        let channel = self.conn.create_channel().await?;
+        channel
+            .confirm_select(ConfirmSelectOptions::default())
+            .await?;
+        Ok(channel)
+    }
+    async fn recycle(
+        &self,
+        channel: &mut Self::Type,
+        _: &managed::Metrics,
+    ) -> managed::RecycleResult<Self::Error> {
+        if channel.status().connected() {
+            Ok(())
+        } else {
+            Err(managed::RecycleError::Message("Channel is closed".into()))
+        }
+    }
+}
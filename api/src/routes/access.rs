@@ -0,0 +1,157 @@
+use actix_web::{HttpResponse, ResponseError};
+use serde::{Deserialize, Serialize};
+use sqlx::PgConnection;
+
+use crate::routes::{role::Role, session::SessionAuth};
+
+/// Part a user plays on a problem, from no part at all up to Admin.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum ProblemAccess {
+    None,
+    /// Can open and submit to the problem before it is published.
+    Tester,
+    /// Can edit the problem, its testcases and see all of its submissions.
+    CoAuthor,
+    /// Can also delete the problem and manage its collaborators.
+    Owner,
+    Admin,
+}
+
+impl ProblemAccess {
+    fn rank(&self) -> u8 {
+        match self {
+            Self::None => 0,
+            Self::Tester => 1,
+            Self::CoAuthor => 2,
+            Self::Owner => 3,
+            Self::Admin => 4,
+        }
+    }
+
+    pub fn can_test(&self) -> bool {
+        *self >= Self::Tester
+    }
+
+    pub fn can_edit(&self) -> bool {
+        *self >= Self::CoAuthor
+    }
+
+    pub fn can_manage(&self) -> bool {
+        *self >= Self::Owner
+    }
+}
+
+impl PartialOrd for ProblemAccess {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        self.rank().partial_cmp(&other.rank())
+    }
+}
+
+impl From<ProblemAccess> for &str {
+    fn from(value: ProblemAccess) -> Self {
+        match value {
+            ProblemAccess::None => "none",
+            ProblemAccess::Tester => "tester",
+            ProblemAccess::CoAuthor => "coauthor",
+            ProblemAccess::Owner => "owner",
+            ProblemAccess::Admin => "admin",
+        }
+    }
+}
+
+/// Looks up what `auth` may do on a problem. Returns `None` if the problem does not exist.
+pub async fn problem_access<'e, E: sqlx::PgExecutor<'e>>(
+    executor: E,
+    problem_id: i64,
+    auth: &SessionAuth,
+) -> Result<Option<ProblemAccess>, sqlx::Error> {
+    let row = sqlx::query!(
+        r#"SELECT p.owner_id, c.role AS "collaborator_role?" FROM problems p LEFT JOIN problem_collaborators c ON c.problem_id = p.problem_id AND c.user_id = $2 WHERE p.problem_id = $1 AND p.deleted_at IS NULL"#,
+        problem_id,
+        auth.user_id
+    )
+    .fetch_optional(executor)
+    .await?;
+
+    Ok(row.map(|row| {
+        if auth.role >= Role::Admin {
+            ProblemAccess::Admin
+        } else if row.owner_id == Some(auth.user_id) {
+            ProblemAccess::Owner
+        } else {
+            match row.collaborator_role.as_deref() {
+                // co-authors keep only tester rights if they lose the problem setter role
+                Some("coauthor") if auth.role >= Role::ProblemSetter => ProblemAccess::CoAuthor,
+                Some("coauthor" | "tester") => ProblemAccess::Tester,
+                _ => ProblemAccess::None,
+            }
+        }
+    }))
+}
+
+/// Error types of handlers that reject users through [`ensure_editor`] and [`ensure_manager`].
+pub trait AccessError: From<sqlx::Error> {
+    const NOT_FOUND: Self;
+    const FORBIDDEN: Self;
+}
+
+/// Rejection for handlers answering with `impl Responder`, which turn it into
+/// a response through [`ResponseError::error_response`].
+#[derive(thiserror::Error, Debug)]
+pub enum AccessDenied {
+    #[error("{0}")]
+    DatabaseError(#[from] sqlx::Error),
+
+    #[error("No such problem")]
+    NotFound,
+
+    #[error("Forbidden")]
+    Forbidden,
+}
+
+impl ResponseError for AccessDenied {
+    fn error_response(&self) -> HttpResponse<actix_web::body::BoxBody> {
+        match self {
+            Self::DatabaseError(e) => HttpResponse::InternalServerError().body(e.to_string()),
+            Self::NotFound => HttpResponse::NotFound().finish(),
+            Self::Forbidden => HttpResponse::Forbidden().finish(),
+        }
+    }
+}
+
+impl AccessError for AccessDenied {
+    const NOT_FOUND: Self = Self::NotFound;
+    const FORBIDDEN: Self = Self::Forbidden;
+}
+
+async fn ensure_access<E: AccessError>(
+    conn: &mut PgConnection,
+    problem_id: i64,
+    auth: &SessionAuth,
+    allowed: fn(&ProblemAccess) -> bool,
+) -> Result<(), E> {
+    match problem_access(conn, problem_id, auth).await? {
+        None => Err(E::NOT_FOUND),
+        Some(access) if !allowed(&access) => Err(E::FORBIDDEN),
+        Some(_) => Ok(()),
+    }
+}
+
+/// Checks the problem exists and `auth` may edit it.
+pub async fn ensure_editor<E: AccessError>(
+    conn: &mut PgConnection,
+    problem_id: i64,
+    auth: &SessionAuth,
+) -> Result<(), E> {
+    ensure_access(conn, problem_id, auth, ProblemAccess::can_edit).await
+}
+
+/// Checks the problem exists and `auth` may delete it and manage its collaborators.
+pub async fn ensure_manager<E: AccessError>(
+    conn: &mut PgConnection,
+    problem_id: i64,
+    auth: &SessionAuth,
+) -> Result<(), E> {
+    ensure_access(conn, problem_id, auth, ProblemAccess::can_manage).await
+}
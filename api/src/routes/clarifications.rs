@@ -0,0 +1,337 @@
+use actix_session::Session;
+use actix_web::{
+    HttpResponse,
+    web::{self, Data},
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::{PgConnection, PgPool};
+use tracing::{info, instrument};
+use validator::Validate;
+
+use crate::routes::{
+    contests::{ContestError, contest_window},
+    role::Role,
+    session::SessionAuth,
+    teams::contest_team,
+};
+
+#[derive(Deserialize, Validate)]
+pub struct ClarificationJson {
+    /// Label of the problem the question is about, if any.
+    #[serde(default)]
+    label: Option<String>,
+    #[validate(length(min = 1, max = 2000, message = "Question must be 1 to 2000 characters"))]
+    question: String,
+}
+
+#[derive(Deserialize, Validate)]
+pub struct AnswerJson {
+    #[validate(length(min = 1, max = 2000, message = "Answer must be 1 to 2000 characters"))]
+    answer: String,
+    /// Shows the question and answer to every contestant as an announcement.
+    #[serde(default)]
+    broadcast: bool,
+}
+
+#[derive(Deserialize, Validate)]
+pub struct AnnouncementJson {
+    #[serde(default)]
+    label: Option<String>,
+    #[validate(length(
+        min = 1,
+        max = 2000,
+        message = "Announcement must be 1 to 2000 characters"
+    ))]
+    body: String,
+}
+
+#[derive(Deserialize)]
+pub struct AnnouncementQuery {
+    /// Only announcements made after the one with this id.
+    #[serde(default)]
+    after: i64,
+}
+
+/// Resolves a contest problem label to its problem.
+async fn labelled_problem(
+    conn: &mut PgConnection,
+    contest_id: i64,
+    label: Option<&str>,
+) -> Result<Option<i64>, ContestError> {
+    let Some(label) = label else {
+        return Ok(None);
+    };
+    let problem_id = sqlx::query_scalar!(
+        "SELECT problem_id FROM contest_problems WHERE contest_id = $1 AND label = $2",
+        contest_id,
+        label.trim()
+    )
+    .fetch_optional(conn)
+    .await?
+    .ok_or_else(|| ContestError::Invalid(format!("No problem {} in the contest", label)))?;
+    Ok(Some(problem_id))
+}
+
+/// Asks the contest's judges a question. Only registered users and members of
+/// registered teams can ask, while the contest runs.
+#[instrument(skip(session, pg_pool, body))]
+pub async fn ask_clarification(
+    session: Session,
+    pg_pool: Data<PgPool>,
+    path: web::Path<(i64,)>,
+    body: web::Json<ClarificationJson>,
+) -> Result<HttpResponse, ContestError> {
+    let auth = SessionAuth::require(&session, Role::User).ok_or(ContestError::Unauthorized)?;
+    body.validate()?;
+    let contest_id = path.into_inner().0;
+
+    let mut conn = pg_pool.acquire().await?;
+    let window = contest_window(conn.as_mut(), contest_id)
+        .await?
+        .ok_or(ContestError::NotFound)?;
+    if !window.running() {
+        return Err(ContestError::Conflict("Contest is not running".into()));
+    }
+    let registered = sqlx::query!(
+        r#"SELECT EXISTS (SELECT 1 FROM contest_registrations WHERE contest_id = $1 AND user_id = $2) AS "registered!""#,
+        contest_id,
+        auth.user_id
+    )
+    .fetch_one(conn.as_mut())
+    .await?
+    .registered;
+    if !registered
+        && contest_team(conn.as_mut(), contest_id, auth.user_id)
+            .await?
+            .is_none()
+    {
+        return Err(ContestError::Forbidden);
+    }
+    let problem_id = labelled_problem(&mut conn, contest_id, body.label.as_deref()).await?;
+
+    let clarification_id = sqlx::query_scalar!(
+        "INSERT INTO contest_clarifications (contest_id, user_id, problem_id, question) VALUES ($1, $2, $3, $4) RETURNING clarification_id",
+        contest_id,
+        auth.user_id,
+        problem_id,
+        body.question
+    )
+    .fetch_one(conn.as_mut())
+    .await?;
+
+    info!(
+        "Clarification {} asked in contest {} by user_id: {}",
+        clarification_id, contest_id, auth.user_id
+    );
+    Ok(HttpResponse::Ok().json(json!({ "clarification_id": clarification_id })))
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Clarification {
+    pub clarification_id: i64,
+    /// Who asked, shown to judges and to the asker only.
+    pub user_id: Option<i64>,
+    pub username: Option<String>,
+    pub label: Option<String>,
+    pub question: String,
+    pub answer: Option<String>,
+    pub broadcast: bool,
+    pub created_at: DateTime<Utc>,
+    pub answered_at: Option<DateTime<Utc>>,
+}
+
+/// The clarifications `user_id` may read: all of them for a judge, otherwise their
+/// own questions and the broadcast ones, the latter without who asked them.
+pub fn visible_clarifications(
+    clarifications: Vec<Clarification>,
+    user_id: i64,
+    judge: bool,
+) -> Vec<Clarification> {
+    if judge {
+        return clarifications;
+    }
+    clarifications
+        .into_iter()
+        .filter(|clarification| clarification.user_id == Some(user_id) || clarification.broadcast)
+        .map(|mut clarification| {
+            if clarification.user_id != Some(user_id) {
+                clarification.user_id = None;
+                clarification.username = None;
+            }
+            clarification
+        })
+        .collect()
+}
+
+/// Lists a contest's clarifications: all of them to its judges, and to everyone else
+/// their own questions and the broadcast ones.
+pub async fn list_clarifications(
+    session: Session,
+    pg_pool: Data<PgPool>,
+    path: web::Path<(i64,)>,
+) -> Result<HttpResponse, ContestError> {
+    let auth = SessionAuth::require(&session, Role::User).ok_or(ContestError::Unauthorized)?;
+    let contest_id = path.into_inner().0;
+
+    let mut conn = pg_pool.acquire().await?;
+    let window = contest_window(conn.as_mut(), contest_id)
+        .await?
+        .ok_or(ContestError::NotFound)?;
+    let judge = window.can_manage(&auth);
+    let clarifications = sqlx::query_as!(
+        Clarification,
+        r#"SELECT q.clarification_id, q.user_id AS "user_id?", u.username AS "username?", cp.label AS "label?", q.question, q.answer, q.broadcast, q.created_at, q.answered_at FROM contest_clarifications q JOIN users u ON u.user_id = q.user_id LEFT JOIN contest_problems cp ON cp.contest_id = q.contest_id AND cp.problem_id = q.problem_id WHERE q.contest_id = $1 ORDER BY q.clarification_id"#,
+        contest_id
+    )
+    .fetch_all(conn.as_mut())
+    .await?;
+
+    Ok(HttpResponse::Ok().json(visible_clarifications(clarifications, auth.user_id, judge)))
+}
+
+/// Answers a clarification, privately to the asker or broadcast to everyone.
+/// A clarification is answered once.
+#[instrument(skip(session, pg_pool, body))]
+pub async fn answer_clarification(
+    session: Session,
+    pg_pool: Data<PgPool>,
+    path: web::Path<(i64, i64)>,
+    body: web::Json<AnswerJson>,
+) -> Result<HttpResponse, ContestError> {
+    let auth = SessionAuth::require(&session, Role::User).ok_or(ContestError::Unauthorized)?;
+    body.validate()?;
+    let (contest_id, clarification_id) = path.into_inner();
+
+    let mut transaction = pg_pool.begin().await?;
+    let window = contest_window(transaction.as_mut(), contest_id)
+        .await?
+        .ok_or(ContestError::NotFound)?;
+    if !window.can_manage(&auth) {
+        return Err(ContestError::Forbidden);
+    }
+    let answered = sqlx::query_scalar!(
+        r#"SELECT answer IS NOT NULL AS "answered!" FROM contest_clarifications WHERE contest_id = $1 AND clarification_id = $2 FOR UPDATE"#,
+        contest_id,
+        clarification_id
+    )
+    .fetch_optional(transaction.as_mut())
+    .await?
+    .ok_or(ContestError::NotFound)?;
+    if answered {
+        return Err(ContestError::Conflict(
+            "Clarification already answered".into(),
+        ));
+    }
+    sqlx::query!(
+        "UPDATE contest_clarifications SET answer = $2, answered_by = $3, answered_at = NOW(), broadcast = $4 WHERE clarification_id = $1",
+        clarification_id,
+        body.answer,
+        auth.user_id,
+        body.broadcast
+    )
+    .execute(transaction.as_mut())
+    .await?;
+    if body.broadcast {
+        sqlx::query!(
+            "INSERT INTO contest_announcements (contest_id, author_id, problem_id, body, clarification_id) SELECT contest_id, $2, problem_id, answer, clarification_id FROM contest_clarifications WHERE clarification_id = $1",
+            clarification_id,
+            auth.user_id
+        )
+        .execute(transaction.as_mut())
+        .await?;
+    }
+    transaction.commit().await?;
+
+    info!(
+        "Clarification {} of contest {} answered by user_id: {}, broadcast: {}",
+        clarification_id, contest_id, auth.user_id, body.broadcast
+    );
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Announces something to every contestant.
+#[instrument(skip(session, pg_pool, body))]
+pub async fn make_announcement(
+    session: Session,
+    pg_pool: Data<PgPool>,
+    path: web::Path<(i64,)>,
+    body: web::Json<AnnouncementJson>,
+) -> Result<HttpResponse, ContestError> {
+    let auth = SessionAuth::require(&session, Role::User).ok_or(ContestError::Unauthorized)?;
+    body.validate()?;
+    let contest_id = path.into_inner().0;
+
+    let mut conn = pg_pool.acquire().await?;
+    let window = contest_window(conn.as_mut(), contest_id)
+        .await?
+        .ok_or(ContestError::NotFound)?;
+    if !window.can_manage(&auth) {
+        return Err(ContestError::Forbidden);
+    }
+    let problem_id = labelled_problem(&mut conn, contest_id, body.label.as_deref()).await?;
+
+    let announcement_id = sqlx::query_scalar!(
+        "INSERT INTO contest_announcements (contest_id, author_id, problem_id, body) VALUES ($1, $2, $3, $4) RETURNING announcement_id",
+        contest_id,
+        auth.user_id,
+        problem_id,
+        body.body
+    )
+    .fetch_one(conn.as_mut())
+    .await?;
+
+    info!(
+        "Announcement {} made in contest {} by user_id: {}",
+        announcement_id, contest_id, auth.user_id
+    );
+    Ok(HttpResponse::Ok().json(json!({ "announcement_id": announcement_id })))
+}
+
+#[derive(Serialize)]
+struct Announcement {
+    announcement_id: i64,
+    label: Option<String>,
+    body: String,
+    /// The question, when the announcement broadcasts a clarification.
+    question: Option<String>,
+    created_at: DateTime<Utc>,
+}
+
+/// Lists a contest's announcements in order. Clients poll it with `after` set to
+/// the last announcement they have seen. Hidden until the contest starts, except
+/// to its managers.
+pub async fn list_announcements(
+    session: Session,
+    pg_pool: Data<PgPool>,
+    path: web::Path<(i64,)>,
+    query: web::Query<AnnouncementQuery>,
+) -> Result<HttpResponse, ContestError> {
+    let contest_id = path.into_inner().0;
+
+    let mut conn = pg_pool.acquire().await?;
+    let window = contest_window(conn.as_mut(), contest_id)
+        .await?
+        .ok_or(ContestError::NotFound)?;
+    if !window.started {
+        let manager = session
+            .get::<SessionAuth>("auth")
+            .ok()
+            .flatten()
+            .is_some_and(|auth| window.can_manage(&auth));
+        if !manager {
+            return Err(ContestError::Conflict("Contest has not started".into()));
+        }
+    }
+    let announcements = sqlx::query_as!(
+        Announcement,
+        r#"SELECT a.announcement_id, cp.label AS "label?", a.body, q.question AS "question?", a.created_at FROM contest_announcements a LEFT JOIN contest_problems cp ON cp.contest_id = a.contest_id AND cp.problem_id = a.problem_id LEFT JOIN contest_clarifications q ON q.clarification_id = a.clarification_id WHERE a.contest_id = $1 AND a.announcement_id > $2 ORDER BY a.announcement_id"#,
+        contest_id,
+        query.after
+    )
+    .fetch_all(conn.as_mut())
+    .await?;
+
+    Ok(HttpResponse::Ok().json(announcements))
+}
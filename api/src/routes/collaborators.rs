@@ -0,0 +1,162 @@
+use actix_session::Session;
+use actix_web::{
+    HttpResponse, Responder, ResponseError,
+    web::{self, Data},
+};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use tracing::info;
+
+use crate::routes::{
+    access::{AccessDenied, ProblemAccess, ensure_editor, ensure_manager},
+    role::Role,
+    session::SessionAuth,
+};
+
+#[derive(Serialize)]
+struct Collaborator {
+    user_id: i64,
+    username: String,
+    role: String,
+}
+
+/// Lists the owner's co-authors and testers of a problem.
+pub async fn list_collaborators(
+    pg_pool: Data<PgPool>,
+    session: Session,
+    path: web::Path<(i64,)>,
+) -> impl Responder {
+    let Ok(Some(auth)) = session.get::<SessionAuth>("auth") else {
+        return HttpResponse::Unauthorized().finish();
+    };
+    let problem_id = path.into_inner().0;
+    let mut conn = match pg_pool.acquire().await {
+        Ok(conn) => conn,
+        Err(e) => return HttpResponse::InternalServerError().body(e.to_string()),
+    };
+    if let Err(e) = ensure_editor::<AccessDenied>(&mut conn, problem_id, &auth).await {
+        return e.error_response();
+    }
+
+    let rows: Result<_, sqlx::Error> = sqlx::query_as!(
+        Collaborator,
+        "SELECT c.user_id, u.username, c.role FROM problem_collaborators c JOIN users u ON u.user_id = c.user_id WHERE c.problem_id = $1 ORDER BY c.added_at",
+        problem_id
+    )
+    .fetch_all(&mut *conn)
+    .await;
+
+    match rows {
+        Ok(rows) => HttpResponse::Ok().json(rows),
+        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
+
+#[derive(Deserialize)]
+pub struct CollaboratorRole {
+    role: ProblemAccess,
+}
+
+/// Adds a co-author or tester to a problem, or changes their part.
+pub async fn set_collaborator(
+    pg_pool: Data<PgPool>,
+    session: Session,
+    path: web::Path<(i64, i64)>,
+    body: web::Json<CollaboratorRole>,
+) -> impl Responder {
+    let Ok(Some(auth)) = session.get::<SessionAuth>("auth") else {
+        return HttpResponse::Unauthorized().finish();
+    };
+    let (problem_id, user_id) = path.into_inner();
+    if !matches!(body.role, ProblemAccess::CoAuthor | ProblemAccess::Tester) {
+        return HttpResponse::BadRequest().body("role must be coauthor or tester");
+    }
+    let mut conn = match pg_pool.acquire().await {
+        Ok(conn) => conn,
+        Err(e) => return HttpResponse::InternalServerError().body(e.to_string()),
+    };
+    if let Err(e) = ensure_manager::<AccessDenied>(&mut conn, problem_id, &auth).await {
+        return e.error_response();
+    }
+
+    let user = sqlx::query!(
+        "SELECT u.role, p.owner_id FROM users u CROSS JOIN problems p WHERE u.user_id = $1 AND p.problem_id = $2",
+        user_id,
+        problem_id
+    )
+    .fetch_optional(&mut *conn)
+    .await;
+    let user = match user {
+        Ok(Some(user)) => user,
+        Ok(None) => return HttpResponse::NotFound().body("No such user"),
+        Err(e) => return HttpResponse::InternalServerError().body(e.to_string()),
+    };
+    if user.owner_id == Some(user_id) {
+        return HttpResponse::BadRequest().body("The owner cannot be a collaborator");
+    }
+    // co-authors edit the problem, so they must be problem setters themselves
+    let user_role = Role::try_from(user.role.as_str()).unwrap_or(Role::User);
+    if body.role == ProblemAccess::CoAuthor && user_role < Role::ProblemSetter {
+        return HttpResponse::BadRequest().body("Co-authors must be problem setters");
+    }
+
+    let role: &str = body.role.into();
+    let res = sqlx::query!(
+        "INSERT INTO problem_collaborators (problem_id, user_id, role) VALUES ($1, $2, $3) ON CONFLICT (problem_id, user_id) DO UPDATE SET role = EXCLUDED.role",
+        problem_id,
+        user_id,
+        role
+    )
+    .execute(&mut *conn)
+    .await;
+
+    match res {
+        Ok(_) => {
+            info!(
+                "User {} made {} of problem {} by user_id: {}",
+                user_id, role, problem_id, auth.user_id
+            );
+            HttpResponse::Ok().finish()
+        }
+        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
+
+/// Removes a co-author or tester from a problem.
+pub async fn remove_collaborator(
+    pg_pool: Data<PgPool>,
+    session: Session,
+    path: web::Path<(i64, i64)>,
+) -> impl Responder {
+    let Ok(Some(auth)) = session.get::<SessionAuth>("auth") else {
+        return HttpResponse::Unauthorized().finish();
+    };
+    let (problem_id, user_id) = path.into_inner();
+    let mut conn = match pg_pool.acquire().await {
+        Ok(conn) => conn,
+        Err(e) => return HttpResponse::InternalServerError().body(e.to_string()),
+    };
+    if let Err(e) = ensure_manager::<AccessDenied>(&mut conn, problem_id, &auth).await {
+        return e.error_response();
+    }
+
+    let res = sqlx::query!(
+        "DELETE FROM problem_collaborators WHERE problem_id = $1 AND user_id = $2",
+        problem_id,
+        user_id
+    )
+    .execute(&mut *conn)
+    .await;
+
+    match res {
+        Ok(res) if res.rows_affected() == 0 => HttpResponse::NotFound().finish(),
+        Ok(_) => {
+            info!(
+                "User {} removed from problem {} by user_id: {}",
+                user_id, problem_id, auth.user_id
+            );
+            HttpResponse::Ok().finish()
+        }
+        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
@@ -0,0 +1,770 @@
+use std::collections::HashSet;
+
+use actix_session::Session;
+use actix_web::{
+    HttpResponse, ResponseError,
+    web::{self, Data},
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::PgPool;
+use tracing::{info, instrument};
+use validator::Validate;
+
+use crate::routes::{
+    access::problem_access, role::Role, session::SessionAuth, teams::contest_team,
+};
+use crate::scoreboard::ContestRules;
+
+#[derive(Deserialize, Validate)]
+pub struct ContestJson {
+    #[validate(length(min = 1, max = 200, message = "Title must be 1 to 200 characters"))]
+    title: String,
+    #[serde(default)]
+    description: String,
+    starts_at: DateTime<Utc>,
+    ends_at: DateTime<Utc>,
+    #[serde(default)]
+    rules: ContestRules,
+    /// Minutes added per wrong attempt on a solved problem under ICPC rules.
+    #[serde(default = "default_penalty_minutes")]
+    #[validate(range(min = 0, max = 1000, message = "Penalty must be 0 to 1000 minutes"))]
+    penalty_minutes: i32,
+    /// Minutes before the end from which the public ICPC scoreboard stops changing.
+    #[validate(range(min = 0, message = "Freeze cannot be negative"))]
+    freeze_minutes: Option<i32>,
+}
+
+fn default_penalty_minutes() -> i32 {
+    20
+}
+
+/// ICPC contests freeze their last hour unless told otherwise, as at the ICPC itself.
+const DEFAULT_ICPC_FREEZE_MINUTES: i32 = 60;
+
+impl ContestJson {
+    fn freeze_minutes(&self) -> i32 {
+        match (self.freeze_minutes, self.rules) {
+            (Some(minutes), _) => minutes,
+            // a contest no longer than the freeze would be frozen from the start
+            (None, ContestRules::Icpc)
+                if (self.ends_at - self.starts_at).num_minutes()
+                    > DEFAULT_ICPC_FREEZE_MINUTES as i64 =>
+            {
+                DEFAULT_ICPC_FREEZE_MINUTES
+            }
+            (None, _) => 0,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ContestProblemJson {
+    problem_id: i64,
+    label: String,
+}
+
+#[derive(Deserialize)]
+pub struct ContestProblemsJson {
+    problems: Vec<ContestProblemJson>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct RegistrationQuery {
+    /// Registers this team, captained by the user, instead of the user alone.
+    #[serde(default)]
+    team_id: Option<i64>,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum ContestError {
+    #[error("{0}")]
+    DatabaseError(#[from] sqlx::Error),
+
+    #[error("Validation error: {0}")]
+    Validation(#[from] validator::ValidationErrors),
+
+    #[error("{0}")]
+    Invalid(String),
+
+    #[error("{0}")]
+    Conflict(String),
+
+    #[error("No such contest")]
+    NotFound,
+
+    #[error("Unauthorized")]
+    Unauthorized,
+
+    #[error("Forbidden")]
+    Forbidden,
+}
+
+impl ResponseError for ContestError {
+    fn error_response(&self) -> HttpResponse<actix_web::body::BoxBody> {
+        match self {
+            Self::Validation(_) | Self::Invalid(_) => {
+                HttpResponse::BadRequest().body(self.to_string())
+            }
+            Self::Conflict(e) => HttpResponse::Conflict().body(e.clone()),
+            Self::NotFound => HttpResponse::NotFound().finish(),
+            Self::Unauthorized => HttpResponse::Unauthorized().finish(),
+            Self::Forbidden => HttpResponse::Forbidden().finish(),
+            Self::DatabaseError(e) => HttpResponse::InternalServerError().body(e.to_string()),
+        }
+    }
+}
+
+/// Where a contest stands relative to the database clock.
+pub struct ContestWindow {
+    pub owner_id: Option<i64>,
+    pub started: bool,
+    pub ended: bool,
+}
+
+impl ContestWindow {
+    pub fn running(&self) -> bool {
+        self.started && !self.ended
+    }
+
+    /// The contest's creator and Admins manage it.
+    pub fn can_manage(&self, auth: &SessionAuth) -> bool {
+        auth.role >= Role::Admin || self.owner_id == Some(auth.user_id)
+    }
+
+    /// Whether a submission to the contest is taken, and if so whether it is virtual.
+    /// Until the contest ends it must come from a registered user, alone or in a team,
+    /// while the contest runs; after that only from a running virtual participation.
+    pub fn admits(&self, registered: bool, virtual_running: bool) -> Result<bool, EntryRefused> {
+        if self.ended {
+            return if virtual_running {
+                Ok(true)
+            } else {
+                Err(EntryRefused::NotRunning)
+            };
+        }
+        if !registered {
+            return Err(EntryRefused::NotRegistered);
+        }
+        if !self.running() {
+            return Err(EntryRefused::NotRunning);
+        }
+        Ok(false)
+    }
+
+    /// Whether a user may start a virtual participation: only once the contest has
+    /// ended, and only if they did not register for it or submit during it.
+    pub fn can_start_virtual(&self, competed: bool) -> Result<(), ContestError> {
+        if !self.ended {
+            return Err(ContestError::Conflict("Contest has not ended".into()));
+        }
+        if competed {
+            return Err(ContestError::Conflict(
+                "Already took part in the contest".into(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Why a contest refuses a submission.
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+pub enum EntryRefused {
+    #[error("Contest is not running")]
+    NotRunning,
+
+    #[error("Not registered for the contest")]
+    NotRegistered,
+}
+
+pub async fn contest_window<'e, E: sqlx::PgExecutor<'e>>(
+    executor: E,
+    contest_id: i64,
+) -> Result<Option<ContestWindow>, sqlx::Error> {
+    sqlx::query_as!(
+        ContestWindow,
+        r#"SELECT owner_id, NOW() >= starts_at AS "started!", NOW() >= ends_at AS "ended!" FROM contests WHERE contest_id = $1"#,
+        contest_id
+    )
+    .fetch_optional(executor)
+    .await
+}
+
+/// Whether the problem belongs to a contest that has not started yet. Such problems
+/// are hidden from practice to everyone but their testers and authors.
+pub async fn hidden_by_contest<'e, E: sqlx::PgExecutor<'e>>(
+    executor: E,
+    problem_id: i64,
+) -> Result<bool, sqlx::Error> {
+    let row = sqlx::query!(
+        r#"SELECT EXISTS (SELECT 1 FROM contest_problems cp JOIN contests c ON c.contest_id = cp.contest_id WHERE cp.problem_id = $1 AND c.starts_at > NOW()) AS "hidden!""#,
+        problem_id
+    )
+    .fetch_one(executor)
+    .await?;
+    Ok(row.hidden)
+}
+
+/// A user's run of a finished contest on their own clock, as long as the contest lasted.
+#[derive(Serialize)]
+pub struct VirtualParticipation {
+    pub started_at: DateTime<Utc>,
+    pub ends_at: DateTime<Utc>,
+    pub running: bool,
+}
+
+pub async fn virtual_participation<'e, E: sqlx::PgExecutor<'e>>(
+    executor: E,
+    contest_id: i64,
+    user_id: i64,
+) -> Result<Option<VirtualParticipation>, sqlx::Error> {
+    sqlx::query_as!(
+        VirtualParticipation,
+        r#"SELECT v.started_at, v.started_at + (c.ends_at - c.starts_at) AS "ends_at!", NOW() < v.started_at + (c.ends_at - c.starts_at) AS "running!" FROM virtual_participations v JOIN contests c ON c.contest_id = v.contest_id WHERE v.contest_id = $1 AND v.user_id = $2"#,
+        contest_id,
+        user_id
+    )
+    .fetch_optional(executor)
+    .await
+}
+
+fn check_times(contest: &ContestJson) -> Result<(), ContestError> {
+    if contest.ends_at <= contest.starts_at {
+        return Err(ContestError::Invalid(
+            "A contest must end after it starts".into(),
+        ));
+    }
+    if contest.freeze_minutes() as i64 > (contest.ends_at - contest.starts_at).num_minutes() {
+        return Err(ContestError::Invalid(
+            "A freeze cannot be longer than the contest".into(),
+        ));
+    }
+    Ok(())
+}
+
+#[derive(Serialize)]
+struct ContestSummary {
+    contest_id: i64,
+    title: String,
+    starts_at: DateTime<Utc>,
+    ends_at: DateTime<Utc>,
+    /// `upcoming`, `running` or `ended`.
+    phase: String,
+    rules: String,
+    registrations: i64,
+}
+
+/// Lists every contest, the latest first.
+pub async fn list_contests(pg_pool: Data<PgPool>) -> Result<HttpResponse, ContestError> {
+    let contests = sqlx::query_as!(
+        ContestSummary,
+        r#"SELECT c.contest_id, c.title, c.starts_at, c.ends_at, CASE WHEN NOW() < c.starts_at THEN 'upcoming' WHEN NOW() < c.ends_at THEN 'running' ELSE 'ended' END AS "phase!", c.rules, (SELECT COUNT(*) FROM contest_registrations r WHERE r.contest_id = c.contest_id) AS "registrations!" FROM contests c ORDER BY c.starts_at DESC, c.contest_id DESC"#
+    )
+    .fetch_all(pg_pool.as_ref())
+    .await?;
+    Ok(HttpResponse::Ok().json(contests))
+}
+
+/// Creates a contest owned by the calling problem setter.
+#[instrument(skip(session, pg_pool, contest))]
+pub async fn create_contest(
+    session: Session,
+    pg_pool: Data<PgPool>,
+    contest: web::Json<ContestJson>,
+) -> Result<HttpResponse, ContestError> {
+    let auth =
+        SessionAuth::require(&session, Role::ProblemSetter).ok_or(ContestError::Unauthorized)?;
+    contest.validate()?;
+    check_times(&contest)?;
+
+    let rules: &str = contest.rules.into();
+    let contest_id = sqlx::query!(
+        "INSERT INTO contests (title, description, starts_at, ends_at, owner_id, rules, penalty_minutes, freeze_minutes) VALUES ($1, $2, $3, $4, $5, $6, $7, $8) RETURNING contest_id",
+        contest.title,
+        contest.description,
+        contest.starts_at,
+        contest.ends_at,
+        auth.user_id,
+        rules,
+        contest.penalty_minutes,
+        contest.freeze_minutes()
+    )
+    .fetch_one(pg_pool.as_ref())
+    .await?
+    .contest_id;
+
+    info!(
+        "Contest {} created by user_id: {}",
+        contest_id, auth.user_id
+    );
+    Ok(HttpResponse::Ok().json(json!({ "contest_id": contest_id })))
+}
+
+/// Changes a contest's title, description or times.
+#[instrument(skip(session, pg_pool, contest))]
+pub async fn update_contest(
+    session: Session,
+    pg_pool: Data<PgPool>,
+    path: web::Path<(i64,)>,
+    contest: web::Json<ContestJson>,
+) -> Result<HttpResponse, ContestError> {
+    let auth =
+        SessionAuth::require(&session, Role::ProblemSetter).ok_or(ContestError::Unauthorized)?;
+    contest.validate()?;
+    check_times(&contest)?;
+    let contest_id = path.into_inner().0;
+
+    let mut conn = pg_pool.acquire().await?;
+    let window = contest_window(conn.as_mut(), contest_id)
+        .await?
+        .ok_or(ContestError::NotFound)?;
+    if !window.can_manage(&auth) {
+        return Err(ContestError::Forbidden);
+    }
+    let rules: &str = contest.rules.into();
+    sqlx::query!(
+        "UPDATE contests SET title = $2, description = $3, starts_at = $4, ends_at = $5, rules = $6, penalty_minutes = $7, freeze_minutes = $8 WHERE contest_id = $1",
+        contest_id,
+        contest.title,
+        contest.description,
+        contest.starts_at,
+        contest.ends_at,
+        rules,
+        contest.penalty_minutes,
+        contest.freeze_minutes()
+    )
+    .execute(conn.as_mut())
+    .await?;
+
+    info!(
+        "Contest {} updated by user_id: {}",
+        contest_id, auth.user_id
+    );
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Replaces a contest's problem set. Problems are listed in order with their label,
+/// and the manager must be able to edit each of them.
+#[instrument(skip(session, pg_pool, body))]
+pub async fn set_contest_problems(
+    session: Session,
+    pg_pool: Data<PgPool>,
+    path: web::Path<(i64,)>,
+    body: web::Json<ContestProblemsJson>,
+) -> Result<HttpResponse, ContestError> {
+    let auth =
+        SessionAuth::require(&session, Role::ProblemSetter).ok_or(ContestError::Unauthorized)?;
+    let contest_id = path.into_inner().0;
+
+    let mut labels = HashSet::new();
+    let mut problem_ids = HashSet::new();
+    for problem in &body.problems {
+        let label = problem.label.trim();
+        if label.is_empty() || label.len() > 10 {
+            return Err(ContestError::Invalid(
+                "Labels must be 1 to 10 characters".into(),
+            ));
+        }
+        if !labels.insert(label) || !problem_ids.insert(problem.problem_id) {
+            return Err(ContestError::Invalid(
+                "Problems and labels must be unique".into(),
+            ));
+        }
+    }
+
+    let mut transaction = pg_pool.begin().await?;
+    let window = contest_window(transaction.as_mut(), contest_id)
+        .await?
+        .ok_or(ContestError::NotFound)?;
+    if !window.can_manage(&auth) {
+        return Err(ContestError::Forbidden);
+    }
+    for problem in &body.problems {
+        match problem_access(transaction.as_mut(), problem.problem_id, &auth).await? {
+            Some(access) if access.can_edit() => {}
+            _ => {
+                return Err(ContestError::Invalid(format!(
+                    "Problem {} can't be added by this user",
+                    problem.problem_id
+                )));
+            }
+        }
+    }
+
+    sqlx::query!(
+        "DELETE FROM contest_problems WHERE contest_id = $1",
+        contest_id
+    )
+    .execute(transaction.as_mut())
+    .await?;
+    for (position, problem) in body.problems.iter().enumerate() {
+        sqlx::query!(
+            "INSERT INTO contest_problems (contest_id, problem_id, label, position) VALUES ($1, $2, $3, $4)",
+            contest_id,
+            problem.problem_id,
+            problem.label.trim(),
+            position as i32
+        )
+        .execute(transaction.as_mut())
+        .await?;
+    }
+    transaction.commit().await?;
+
+    info!(
+        "Problem set of contest {} replaced with {} problems by user_id: {}",
+        contest_id,
+        body.problems.len(),
+        auth.user_id
+    );
+    Ok(HttpResponse::Ok().finish())
+}
+
+#[derive(Serialize)]
+struct ContestProblemSummary {
+    label: String,
+    problem_id: i64,
+    title: String,
+}
+
+#[derive(Serialize)]
+struct ContestDetail {
+    contest_id: i64,
+    title: String,
+    description: String,
+    starts_at: DateTime<Utc>,
+    ends_at: DateTime<Utc>,
+    owner_id: Option<i64>,
+    rules: String,
+    penalty_minutes: i32,
+    freeze_minutes: i32,
+    unfrozen_at: Option<DateTime<Utc>>,
+    registered: bool,
+    /// Empty until the contest starts, except for its managers.
+    problems: Vec<ContestProblemSummary>,
+}
+
+/// Shows a contest. Its problems are only listed once it has started.
+pub async fn get_contest(
+    session: Session,
+    pg_pool: Data<PgPool>,
+    path: web::Path<(i64,)>,
+) -> Result<HttpResponse, ContestError> {
+    let contest_id = path.into_inner().0;
+    let auth = session.get::<SessionAuth>("auth").ok().flatten();
+    let user_id = auth.as_ref().map(|auth| auth.user_id);
+
+    let mut conn = pg_pool.acquire().await?;
+    let contest = sqlx::query!(
+        r#"SELECT c.contest_id, c.title, c.description, c.starts_at, c.ends_at, c.owner_id, c.rules, c.penalty_minutes, c.freeze_minutes, c.unfrozen_at, NOW() >= c.starts_at AS "started!", (EXISTS (SELECT 1 FROM contest_registrations r WHERE r.contest_id = c.contest_id AND r.user_id = $2) OR EXISTS (SELECT 1 FROM contest_team_registrations tr JOIN team_members m ON m.team_id = tr.team_id WHERE tr.contest_id = c.contest_id AND m.user_id = $2)) AS "registered!" FROM contests c WHERE c.contest_id = $1"#,
+        contest_id,
+        user_id
+    )
+    .fetch_optional(conn.as_mut())
+    .await?
+    .ok_or(ContestError::NotFound)?;
+
+    let manager =
+        auth.is_some_and(|auth| auth.role >= Role::Admin || contest.owner_id == Some(auth.user_id));
+    let problems = if contest.started || manager {
+        sqlx::query_as!(
+            ContestProblemSummary,
+            "SELECT cp.label, cp.problem_id, p.title FROM contest_problems cp JOIN problems p ON p.problem_id = cp.problem_id WHERE cp.contest_id = $1 AND p.deleted_at IS NULL ORDER BY cp.position",
+            contest_id
+        )
+        .fetch_all(conn.as_mut())
+        .await?
+    } else {
+        Vec::new()
+    };
+
+    Ok(HttpResponse::Ok().json(ContestDetail {
+        contest_id: contest.contest_id,
+        title: contest.title,
+        description: contest.description,
+        starts_at: contest.starts_at,
+        ends_at: contest.ends_at,
+        owner_id: contest.owner_id,
+        rules: contest.rules,
+        penalty_minutes: contest.penalty_minutes,
+        freeze_minutes: contest.freeze_minutes,
+        unfrozen_at: contest.unfrozen_at,
+        registered: contest.registered,
+        problems,
+    }))
+}
+
+#[derive(Serialize)]
+struct Sample {
+    input: String,
+    output: String,
+}
+
+#[derive(Serialize)]
+struct ContestProblem {
+    label: String,
+    problem_id: i64,
+    title: String,
+    statement: String,
+    time_limit_ms: Option<i32>,
+    memory_limit_mb: Option<i32>,
+    samples: Vec<Sample>,
+}
+
+/// Shows a contest problem with its samples. Hidden until the contest starts,
+/// except to its managers, whatever the problem's own visibility.
+pub async fn contest_problem(
+    session: Session,
+    pg_pool: Data<PgPool>,
+    path: web::Path<(i64, String)>,
+) -> Result<HttpResponse, ContestError> {
+    let (contest_id, label) = path.into_inner();
+    let mut conn = pg_pool.acquire().await?;
+    let window = contest_window(conn.as_mut(), contest_id)
+        .await?
+        .ok_or(ContestError::NotFound)?;
+    if !window.started {
+        let manager = session
+            .get::<SessionAuth>("auth")
+            .ok()
+            .flatten()
+            .is_some_and(|auth| window.can_manage(&auth));
+        if !manager {
+            return Err(ContestError::NotFound);
+        }
+    }
+
+    let problem = sqlx::query!(
+        "SELECT cp.label, p.problem_id, p.title, p.statement, p.time_limit_ms, p.memory_limit_mb FROM contest_problems cp JOIN problems p ON p.problem_id = cp.problem_id WHERE cp.contest_id = $1 AND cp.label = $2 AND p.deleted_at IS NULL",
+        contest_id,
+        label
+    )
+    .fetch_optional(conn.as_mut())
+    .await?
+    .ok_or(ContestError::NotFound)?;
+    let samples = sqlx::query_as!(
+        Sample,
+        "SELECT testcase AS input, output FROM problem_testcases WHERE problem_id = $1 AND is_sample AND validation = 'valid' ORDER BY position, testcase_id",
+        problem.problem_id
+    )
+    .fetch_all(conn.as_mut())
+    .await?;
+
+    Ok(HttpResponse::Ok().json(ContestProblem {
+        label: problem.label,
+        problem_id: problem.problem_id,
+        title: problem.title,
+        statement: problem.statement,
+        time_limit_ms: problem.time_limit_ms,
+        memory_limit_mb: problem.memory_limit_mb,
+        samples,
+    }))
+}
+
+/// Registers the logged in user for a contest that has not ended, or with
+/// `team_id` a team they captain. Nobody takes part twice, alone or with a team.
+#[instrument(skip(session, pg_pool))]
+pub async fn register_for_contest(
+    session: Session,
+    pg_pool: Data<PgPool>,
+    path: web::Path<(i64,)>,
+    query: web::Query<RegistrationQuery>,
+) -> Result<HttpResponse, ContestError> {
+    let auth = SessionAuth::require(&session, Role::User).ok_or(ContestError::Unauthorized)?;
+    let contest_id = path.into_inner().0;
+
+    let mut transaction = pg_pool.begin().await?;
+    let window = contest_window(transaction.as_mut(), contest_id)
+        .await?
+        .ok_or(ContestError::NotFound)?;
+    if window.ended {
+        return Err(ContestError::Conflict("Contest has ended".into()));
+    }
+    if let Some(team_id) = query.team_id {
+        let captain_id =
+            sqlx::query_scalar!("SELECT captain_id FROM teams WHERE team_id = $1", team_id)
+                .fetch_optional(transaction.as_mut())
+                .await?;
+        match captain_id {
+            None => return Err(ContestError::Invalid("No such team".into())),
+            Some(captain_id) if captain_id != auth.user_id => {
+                return Err(ContestError::Forbidden);
+            }
+            Some(_) => {}
+        }
+        let registered = sqlx::query!(
+            "INSERT INTO contest_team_registrations (contest_id, team_id) VALUES ($1, $2) ON CONFLICT DO NOTHING",
+            contest_id,
+            team_id
+        )
+        .execute(transaction.as_mut())
+        .await?
+        .rows_affected();
+        if registered > 0 {
+            // counts the team's own registration, so look for a second one
+            let taken = sqlx::query_scalar!(
+                r#"SELECT EXISTS (SELECT 1 FROM team_members m WHERE m.team_id = $2 AND (EXISTS (SELECT 1 FROM contest_registrations r WHERE r.contest_id = $1 AND r.user_id = m.user_id) OR EXISTS (SELECT 1 FROM contest_team_registrations tr JOIN team_members o ON o.team_id = tr.team_id WHERE tr.contest_id = $1 AND tr.team_id <> $2 AND o.user_id = m.user_id))) AS "taken!""#,
+                contest_id,
+                team_id
+            )
+            .fetch_one(transaction.as_mut())
+            .await?;
+            if taken {
+                return Err(ContestError::Conflict(
+                    "A team member is already registered for the contest".into(),
+                ));
+            }
+        }
+        transaction.commit().await?;
+
+        info!(
+            "Team {} registered for contest {} by user_id: {}",
+            team_id, contest_id, auth.user_id
+        );
+        return Ok(HttpResponse::Ok().finish());
+    }
+
+    if contest_team(transaction.as_mut(), contest_id, auth.user_id)
+        .await?
+        .is_some()
+    {
+        return Err(ContestError::Conflict(
+            "Already registered with a team".into(),
+        ));
+    }
+    sqlx::query!(
+        "INSERT INTO contest_registrations (contest_id, user_id) VALUES ($1, $2) ON CONFLICT DO NOTHING",
+        contest_id,
+        auth.user_id
+    )
+    .execute(transaction.as_mut())
+    .await?;
+    transaction.commit().await?;
+
+    info!(
+        "User {} registered for contest {}",
+        auth.user_id, contest_id
+    );
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Withdraws the logged in user, or with `team_id` a team they captain, from a
+/// contest that has not started.
+#[instrument(skip(session, pg_pool))]
+pub async fn unregister_from_contest(
+    session: Session,
+    pg_pool: Data<PgPool>,
+    path: web::Path<(i64,)>,
+    query: web::Query<RegistrationQuery>,
+) -> Result<HttpResponse, ContestError> {
+    let auth = SessionAuth::require(&session, Role::User).ok_or(ContestError::Unauthorized)?;
+    let contest_id = path.into_inner().0;
+
+    let mut conn = pg_pool.acquire().await?;
+    let window = contest_window(conn.as_mut(), contest_id)
+        .await?
+        .ok_or(ContestError::NotFound)?;
+    if window.started {
+        return Err(ContestError::Conflict("Contest has started".into()));
+    }
+    if let Some(team_id) = query.team_id {
+        let res = sqlx::query!(
+            "DELETE FROM contest_team_registrations tr USING teams t WHERE t.team_id = tr.team_id AND tr.contest_id = $1 AND tr.team_id = $2 AND t.captain_id = $3",
+            contest_id,
+            team_id,
+            auth.user_id
+        )
+        .execute(conn.as_mut())
+        .await?;
+        if res.rows_affected() == 0 {
+            return Err(ContestError::NotFound);
+        }
+
+        info!(
+            "Team {} unregistered from contest {} by user_id: {}",
+            team_id, contest_id, auth.user_id
+        );
+        return Ok(HttpResponse::Ok().finish());
+    }
+    let res = sqlx::query!(
+        "DELETE FROM contest_registrations WHERE contest_id = $1 AND user_id = $2",
+        contest_id,
+        auth.user_id
+    )
+    .execute(conn.as_mut())
+    .await?;
+    if res.rows_affected() == 0 {
+        return Err(ContestError::NotFound);
+    }
+
+    info!(
+        "User {} unregistered from contest {}",
+        auth.user_id, contest_id
+    );
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Starts the logged in user's virtual participation in a finished contest. Users
+/// who registered for the contest, alone or in a team, or submitted during it can't
+/// take it again.
+#[instrument(skip(session, pg_pool))]
+pub async fn start_virtual_participation(
+    session: Session,
+    pg_pool: Data<PgPool>,
+    path: web::Path<(i64,)>,
+) -> Result<HttpResponse, ContestError> {
+    let auth = SessionAuth::require(&session, Role::User).ok_or(ContestError::Unauthorized)?;
+    let contest_id = path.into_inner().0;
+
+    let mut transaction = pg_pool.begin().await?;
+    let window = contest_window(transaction.as_mut(), contest_id)
+        .await?
+        .ok_or(ContestError::NotFound)?;
+    let competed = sqlx::query!(
+        r#"SELECT EXISTS (SELECT 1 FROM submit_status WHERE contest_id = $1 AND user_id = $2 AND NOT is_virtual) OR EXISTS (SELECT 1 FROM contest_registrations WHERE contest_id = $1 AND user_id = $2) OR EXISTS (SELECT 1 FROM contest_team_registrations r JOIN team_members m ON m.team_id = r.team_id WHERE r.contest_id = $1 AND m.user_id = $2) AS "competed!""#,
+        contest_id,
+        auth.user_id
+    )
+    .fetch_one(transaction.as_mut())
+    .await?
+    .competed;
+    window.can_start_virtual(competed)?;
+    let started = sqlx::query!(
+        "INSERT INTO virtual_participations (contest_id, user_id) VALUES ($1, $2) ON CONFLICT DO NOTHING",
+        contest_id,
+        auth.user_id
+    )
+    .execute(transaction.as_mut())
+    .await?
+    .rows_affected();
+    if started == 0 {
+        return Err(ContestError::Conflict(
+            "Virtual participation already started".into(),
+        ));
+    }
+    let participation = virtual_participation(transaction.as_mut(), contest_id, auth.user_id)
+        .await?
+        .ok_or(ContestError::NotFound)?;
+    transaction.commit().await?;
+
+    info!(
+        "User {} started a virtual participation in contest {}",
+        auth.user_id, contest_id
+    );
+    Ok(HttpResponse::Ok().json(participation))
+}
+
+/// Shows the logged in user's virtual participation in a contest.
+pub async fn get_virtual_participation(
+    session: Session,
+    pg_pool: Data<PgPool>,
+    path: web::Path<(i64,)>,
+) -> Result<HttpResponse, ContestError> {
+    let auth = SessionAuth::require(&session, Role::User).ok_or(ContestError::Unauthorized)?;
+    let contest_id = path.into_inner().0;
+
+    let participation = virtual_participation(pg_pool.as_ref(), contest_id, auth.user_id)
+        .await?
+        .ok_or(ContestError::NotFound)?;
+    Ok(HttpResponse::Ok().json(participation))
+}
@@ -0,0 +1,809 @@
+use std::collections::HashSet;
+
+use actix_session::Session;
+use actix_web::{
+    HttpResponse, ResponseError,
+    web::{self, Data},
+};
+use chrono::{DateTime, Utc};
+use models::ExecStatus;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::{PgConnection, PgPool};
+use tracing::{info, instrument};
+use validator::Validate;
+
+use crate::grades::{Gradebook, GradebookProblem, GradedSubmission, Student, grade_students};
+use crate::routes::{access::problem_access, role::Role, session::SessionAuth};
+
+/// Part a member plays in a course.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum CourseRole {
+    /// Manages the course's members, problem set and assignments.
+    Instructor,
+    Student,
+}
+
+impl From<CourseRole> for &str {
+    fn from(value: CourseRole) -> Self {
+        match value {
+            CourseRole::Instructor => "instructor",
+            CourseRole::Student => "student",
+        }
+    }
+}
+
+impl TryFrom<&str> for CourseRole {
+    type Error = &'static str;
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "instructor" => Ok(Self::Instructor),
+            "student" => Ok(Self::Student),
+            _ => Err("No such course role exists"),
+        }
+    }
+}
+
+#[derive(Deserialize, Validate)]
+pub struct CourseJson {
+    #[validate(length(min = 1, max = 200, message = "Name must be 1 to 200 characters"))]
+    name: String,
+    #[serde(default)]
+    description: String,
+}
+
+#[derive(Deserialize)]
+pub struct CourseMemberJson {
+    role: CourseRole,
+}
+
+#[derive(Deserialize)]
+pub struct CourseProblemsJson {
+    problem_ids: Vec<i64>,
+}
+
+#[derive(Deserialize, Validate)]
+pub struct AssignmentJson {
+    #[validate(length(min = 1, max = 200, message = "Title must be 1 to 200 characters"))]
+    title: String,
+    #[serde(default)]
+    description: String,
+    /// Hidden from students until then; open right away when unset.
+    #[serde(default)]
+    opens_at: Option<DateTime<Utc>>,
+    due_at: DateTime<Utc>,
+    /// Late submissions are taken until then; none when unset.
+    #[serde(default)]
+    late_until: Option<DateTime<Utc>>,
+    #[serde(default)]
+    #[validate(range(min = 0, max = 100, message = "Late penalty must be 0 to 100 percent"))]
+    late_penalty_percent: i32,
+    /// Problems from the course's problem set, in order.
+    problem_ids: Vec<i64>,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum CourseError {
+    #[error("{0}")]
+    DatabaseError(#[from] sqlx::Error),
+
+    #[error("Validation error: {0}")]
+    Validation(#[from] validator::ValidationErrors),
+
+    #[error("{0}")]
+    Invalid(String),
+
+    #[error("{0}")]
+    Conflict(String),
+
+    #[error("No such course")]
+    NotFound,
+
+    #[error("Unauthorized")]
+    Unauthorized,
+
+    #[error("Forbidden")]
+    Forbidden,
+}
+
+impl ResponseError for CourseError {
+    fn error_response(&self) -> HttpResponse<actix_web::body::BoxBody> {
+        match self {
+            Self::Validation(_) | Self::Invalid(_) => {
+                HttpResponse::BadRequest().body(self.to_string())
+            }
+            Self::Conflict(e) => HttpResponse::Conflict().body(e.clone()),
+            Self::NotFound => HttpResponse::NotFound().finish(),
+            Self::Unauthorized => HttpResponse::Unauthorized().finish(),
+            Self::Forbidden => HttpResponse::Forbidden().finish(),
+            Self::DatabaseError(e) => HttpResponse::InternalServerError().body(e.to_string()),
+        }
+    }
+}
+
+/// The user's part in a course, `None` for non-members. Admins act as instructors.
+pub async fn course_role<'e, E: sqlx::PgExecutor<'e>>(
+    executor: E,
+    course_id: i64,
+    auth: &SessionAuth,
+) -> Result<Option<CourseRole>, sqlx::Error> {
+    if auth.role >= Role::Admin {
+        return Ok(Some(CourseRole::Instructor));
+    }
+    let role = sqlx::query_scalar!(
+        "SELECT role FROM course_members WHERE course_id = $1 AND user_id = $2",
+        course_id,
+        auth.user_id
+    )
+    .fetch_optional(executor)
+    .await?;
+    Ok(role.and_then(|role| CourseRole::try_from(role.as_str()).ok()))
+}
+
+/// Fails unless the course exists and the user is one of its instructors.
+pub async fn ensure_instructor(
+    conn: &mut PgConnection,
+    course_id: i64,
+    auth: &SessionAuth,
+) -> Result<(), CourseError> {
+    let exists = sqlx::query_scalar!(
+        r#"SELECT EXISTS (SELECT 1 FROM courses WHERE course_id = $1) AS "exists!""#,
+        course_id
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+    if !exists {
+        return Err(CourseError::NotFound);
+    }
+    match course_role(conn, course_id, auth).await? {
+        Some(CourseRole::Instructor) => Ok(()),
+        _ => Err(CourseError::Forbidden),
+    }
+}
+
+/// Where an assignment stands relative to the database clock.
+pub struct AssignmentWindow {
+    pub course_id: i64,
+    pub opened: bool,
+    /// Past the due date and any late period.
+    pub closed: bool,
+}
+
+pub async fn assignment_window<'e, E: sqlx::PgExecutor<'e>>(
+    executor: E,
+    assignment_id: i64,
+) -> Result<Option<AssignmentWindow>, sqlx::Error> {
+    sqlx::query_as!(
+        AssignmentWindow,
+        r#"SELECT course_id, opens_at IS NULL OR NOW() >= opens_at AS "opened!", NOW() > COALESCE(late_until, due_at) AS "closed!" FROM assignments WHERE assignment_id = $1"#,
+        assignment_id
+    )
+    .fetch_optional(executor)
+    .await
+}
+
+/// Creates a course taught by the calling problem setter.
+#[instrument(skip(session, pg_pool, course))]
+pub async fn create_course(
+    session: Session,
+    pg_pool: Data<PgPool>,
+    course: web::Json<CourseJson>,
+) -> Result<HttpResponse, CourseError> {
+    let auth =
+        SessionAuth::require(&session, Role::ProblemSetter).ok_or(CourseError::Unauthorized)?;
+    course.validate()?;
+
+    let mut transaction = pg_pool.begin().await?;
+    let course_id = sqlx::query_scalar!(
+        "INSERT INTO courses (name, description, owner_id) VALUES ($1, $2, $3) RETURNING course_id",
+        course.name,
+        course.description,
+        auth.user_id
+    )
+    .fetch_one(transaction.as_mut())
+    .await?;
+    sqlx::query!(
+        "INSERT INTO course_members (course_id, user_id, role) VALUES ($1, $2, 'instructor')",
+        course_id,
+        auth.user_id
+    )
+    .execute(transaction.as_mut())
+    .await?;
+    transaction.commit().await?;
+
+    info!("Course {} created by user_id: {}", course_id, auth.user_id);
+    Ok(HttpResponse::Ok().json(json!({ "course_id": course_id })))
+}
+
+#[derive(Serialize)]
+struct CourseSummary {
+    course_id: i64,
+    name: String,
+    role: String,
+}
+
+/// Lists the courses the logged in user is a member of.
+pub async fn my_courses(
+    session: Session,
+    pg_pool: Data<PgPool>,
+) -> Result<HttpResponse, CourseError> {
+    let auth = SessionAuth::require(&session, Role::User).ok_or(CourseError::Unauthorized)?;
+
+    let courses = sqlx::query_as!(
+        CourseSummary,
+        "SELECT c.course_id, c.name, m.role FROM courses c JOIN course_members m ON m.course_id = c.course_id WHERE m.user_id = $1 ORDER BY c.created_at DESC",
+        auth.user_id
+    )
+    .fetch_all(pg_pool.as_ref())
+    .await?;
+    Ok(HttpResponse::Ok().json(courses))
+}
+
+#[derive(Serialize)]
+struct CourseProblemSummary {
+    problem_id: i64,
+    title: String,
+    difficulty: String,
+}
+
+#[derive(Serialize)]
+struct AssignmentSummary {
+    assignment_id: i64,
+    title: String,
+    opens_at: Option<DateTime<Utc>>,
+    due_at: DateTime<Utc>,
+    late_until: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize)]
+struct CourseDetail {
+    course_id: i64,
+    name: String,
+    description: String,
+    role: CourseRole,
+    problems: Vec<CourseProblemSummary>,
+    /// Students only see assignments that have opened.
+    assignments: Vec<AssignmentSummary>,
+}
+
+/// Shows a course with its problem set and assignments to its members.
+pub async fn get_course(
+    session: Session,
+    pg_pool: Data<PgPool>,
+    path: web::Path<(i64,)>,
+) -> Result<HttpResponse, CourseError> {
+    let auth = SessionAuth::require(&session, Role::User).ok_or(CourseError::Unauthorized)?;
+    let course_id = path.into_inner().0;
+
+    let mut conn = pg_pool.acquire().await?;
+    let course = sqlx::query!(
+        "SELECT name, description FROM courses WHERE course_id = $1",
+        course_id
+    )
+    .fetch_optional(conn.as_mut())
+    .await?
+    .ok_or(CourseError::NotFound)?;
+    let role = course_role(conn.as_mut(), course_id, &auth)
+        .await?
+        .ok_or(CourseError::NotFound)?;
+    let problems = sqlx::query_as!(
+        CourseProblemSummary,
+        "SELECT p.problem_id, p.title, p.difficulty FROM course_problems cp JOIN problems p ON p.problem_id = cp.problem_id WHERE cp.course_id = $1 AND p.deleted_at IS NULL ORDER BY cp.position",
+        course_id
+    )
+    .fetch_all(conn.as_mut())
+    .await?;
+    let assignments = sqlx::query_as!(
+        AssignmentSummary,
+        "SELECT assignment_id, title, opens_at, due_at, late_until FROM assignments WHERE course_id = $1 AND ($2 OR opens_at IS NULL OR NOW() >= opens_at) ORDER BY due_at, assignment_id",
+        course_id,
+        role == CourseRole::Instructor
+    )
+    .fetch_all(conn.as_mut())
+    .await?;
+
+    Ok(HttpResponse::Ok().json(CourseDetail {
+        course_id,
+        name: course.name,
+        description: course.description,
+        role,
+        problems,
+        assignments,
+    }))
+}
+
+#[derive(Serialize)]
+struct CourseMember {
+    user_id: i64,
+    username: String,
+    role: String,
+}
+
+/// Lists a course's instructors and students.
+pub async fn list_course_members(
+    session: Session,
+    pg_pool: Data<PgPool>,
+    path: web::Path<(i64,)>,
+) -> Result<HttpResponse, CourseError> {
+    let auth = SessionAuth::require(&session, Role::User).ok_or(CourseError::Unauthorized)?;
+    let course_id = path.into_inner().0;
+
+    let mut conn = pg_pool.acquire().await?;
+    ensure_instructor(&mut conn, course_id, &auth).await?;
+    let members = sqlx::query_as!(
+        CourseMember,
+        "SELECT m.user_id, u.username, m.role FROM course_members m JOIN users u ON u.user_id = m.user_id WHERE m.course_id = $1 ORDER BY m.role, u.username",
+        course_id
+    )
+    .fetch_all(conn.as_mut())
+    .await?;
+    Ok(HttpResponse::Ok().json(members))
+}
+
+/// Adds a member to a course, or changes their role.
+#[instrument(skip(session, pg_pool, body))]
+pub async fn set_course_member(
+    session: Session,
+    pg_pool: Data<PgPool>,
+    path: web::Path<(i64, i64)>,
+    body: web::Json<CourseMemberJson>,
+) -> Result<HttpResponse, CourseError> {
+    let auth = SessionAuth::require(&session, Role::User).ok_or(CourseError::Unauthorized)?;
+    let (course_id, user_id) = path.into_inner();
+
+    let mut conn = pg_pool.acquire().await?;
+    ensure_instructor(&mut conn, course_id, &auth).await?;
+    let owner_id = sqlx::query_scalar!(
+        "SELECT owner_id FROM courses WHERE course_id = $1",
+        course_id
+    )
+    .fetch_one(conn.as_mut())
+    .await?;
+    if user_id == owner_id && body.role != CourseRole::Instructor {
+        return Err(CourseError::Conflict(
+            "The course's creator stays an instructor".into(),
+        ));
+    }
+    let role: &str = body.role.into();
+    let res = sqlx::query!(
+        "INSERT INTO course_members (course_id, user_id, role) SELECT $1, user_id, $3 FROM users WHERE user_id = $2 ON CONFLICT (course_id, user_id) DO UPDATE SET role = EXCLUDED.role",
+        course_id,
+        user_id,
+        role
+    )
+    .execute(conn.as_mut())
+    .await?;
+    if res.rows_affected() == 0 {
+        return Err(CourseError::Invalid("No such user".into()));
+    }
+
+    info!(
+        "User {} set as {} of course {} by user_id: {}",
+        user_id, role, course_id, auth.user_id
+    );
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Removes a member from a course. Their submissions are kept.
+#[instrument(skip(session, pg_pool))]
+pub async fn remove_course_member(
+    session: Session,
+    pg_pool: Data<PgPool>,
+    path: web::Path<(i64, i64)>,
+) -> Result<HttpResponse, CourseError> {
+    let auth = SessionAuth::require(&session, Role::User).ok_or(CourseError::Unauthorized)?;
+    let (course_id, user_id) = path.into_inner();
+
+    let mut conn = pg_pool.acquire().await?;
+    ensure_instructor(&mut conn, course_id, &auth).await?;
+    let res = sqlx::query!(
+        "DELETE FROM course_members m USING courses c WHERE c.course_id = m.course_id AND m.course_id = $1 AND m.user_id = $2 AND c.owner_id <> $2",
+        course_id,
+        user_id
+    )
+    .execute(conn.as_mut())
+    .await?;
+    if res.rows_affected() == 0 {
+        return Err(CourseError::Invalid(
+            "Not a member, or the course's creator".into(),
+        ));
+    }
+
+    info!(
+        "User {} removed from course {} by user_id: {}",
+        user_id, course_id, auth.user_id
+    );
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Replaces a course's problem set. The instructor must be able to edit each problem.
+#[instrument(skip(session, pg_pool, body))]
+pub async fn set_course_problems(
+    session: Session,
+    pg_pool: Data<PgPool>,
+    path: web::Path<(i64,)>,
+    body: web::Json<CourseProblemsJson>,
+) -> Result<HttpResponse, CourseError> {
+    let auth = SessionAuth::require(&session, Role::User).ok_or(CourseError::Unauthorized)?;
+    let course_id = path.into_inner().0;
+    let mut seen = HashSet::new();
+    if !body.problem_ids.iter().all(|id| seen.insert(*id)) {
+        return Err(CourseError::Invalid("Problems must be unique".into()));
+    }
+
+    let mut transaction = pg_pool.begin().await?;
+    ensure_instructor(&mut transaction, course_id, &auth).await?;
+    for problem_id in &body.problem_ids {
+        match problem_access(transaction.as_mut(), *problem_id, &auth).await? {
+            Some(access) if access.can_edit() => {}
+            _ => {
+                return Err(CourseError::Invalid(format!(
+                    "Problem {} can't be added by this user",
+                    problem_id
+                )));
+            }
+        }
+    }
+    let in_use = sqlx::query_scalar!(
+        r#"SELECT EXISTS (SELECT 1 FROM assignment_problems ap JOIN assignments a ON a.assignment_id = ap.assignment_id WHERE a.course_id = $1 AND NOT ap.problem_id = ANY($2)) AS "in_use!""#,
+        course_id,
+        &body.problem_ids
+    )
+    .fetch_one(transaction.as_mut())
+    .await?;
+    if in_use {
+        return Err(CourseError::Conflict(
+            "An assignment uses a problem left out of the set".into(),
+        ));
+    }
+
+    sqlx::query!(
+        "DELETE FROM course_problems WHERE course_id = $1",
+        course_id
+    )
+    .execute(transaction.as_mut())
+    .await?;
+    sqlx::query!(
+        "INSERT INTO course_problems (course_id, problem_id, position) SELECT $1, p.problem_id, (p.position - 1)::INT FROM UNNEST($2::BIGINT[]) WITH ORDINALITY AS p(problem_id, position)",
+        course_id,
+        &body.problem_ids
+    )
+    .execute(transaction.as_mut())
+    .await?;
+    transaction.commit().await?;
+
+    info!(
+        "Problem set of course {} replaced with {} problems by user_id: {}",
+        course_id,
+        body.problem_ids.len(),
+        auth.user_id
+    );
+    Ok(HttpResponse::Ok().finish())
+}
+
+#[derive(Serialize)]
+struct Sample {
+    input: String,
+    output: String,
+}
+
+#[derive(Serialize)]
+struct CourseProblem {
+    problem_id: i64,
+    title: String,
+    statement: String,
+    time_limit_ms: Option<i32>,
+    memory_limit_mb: Option<i32>,
+    samples: Vec<Sample>,
+}
+
+/// Shows a problem of the course's set with its samples to the course's members,
+/// whatever the problem's own visibility.
+pub async fn course_problem(
+    session: Session,
+    pg_pool: Data<PgPool>,
+    path: web::Path<(i64, i64)>,
+) -> Result<HttpResponse, CourseError> {
+    let auth = SessionAuth::require(&session, Role::User).ok_or(CourseError::Unauthorized)?;
+    let (course_id, problem_id) = path.into_inner();
+
+    let mut conn = pg_pool.acquire().await?;
+    if course_role(conn.as_mut(), course_id, &auth)
+        .await?
+        .is_none()
+    {
+        return Err(CourseError::NotFound);
+    }
+    let problem = sqlx::query!(
+        "SELECT p.problem_id, p.title, p.statement, p.time_limit_ms, p.memory_limit_mb FROM course_problems cp JOIN problems p ON p.problem_id = cp.problem_id WHERE cp.course_id = $1 AND cp.problem_id = $2 AND p.deleted_at IS NULL",
+        course_id,
+        problem_id
+    )
+    .fetch_optional(conn.as_mut())
+    .await?
+    .ok_or(CourseError::NotFound)?;
+    let samples = sqlx::query_as!(
+        Sample,
+        "SELECT testcase AS input, output FROM problem_testcases WHERE problem_id = $1 AND is_sample AND validation = 'valid' ORDER BY position, testcase_id",
+        problem.problem_id
+    )
+    .fetch_all(conn.as_mut())
+    .await?;
+
+    Ok(HttpResponse::Ok().json(CourseProblem {
+        problem_id: problem.problem_id,
+        title: problem.title,
+        statement: problem.statement,
+        time_limit_ms: problem.time_limit_ms,
+        memory_limit_mb: problem.memory_limit_mb,
+        samples,
+    }))
+}
+
+fn check_assignment(assignment: &AssignmentJson) -> Result<(), CourseError> {
+    if assignment
+        .opens_at
+        .is_some_and(|opens_at| opens_at >= assignment.due_at)
+    {
+        return Err(CourseError::Invalid(
+            "An assignment must open before it is due".into(),
+        ));
+    }
+    if assignment
+        .late_until
+        .is_some_and(|late_until| late_until <= assignment.due_at)
+    {
+        return Err(CourseError::Invalid(
+            "Late submissions must end after the due date".into(),
+        ));
+    }
+    let mut seen = HashSet::new();
+    if !assignment.problem_ids.iter().all(|id| seen.insert(*id)) {
+        return Err(CourseError::Invalid("Problems must be unique".into()));
+    }
+    Ok(())
+}
+
+/// Stores an assignment's problems, which must all be in the course's problem set.
+async fn set_assignment_problems(
+    conn: &mut PgConnection,
+    course_id: i64,
+    assignment_id: i64,
+    problem_ids: &[i64],
+) -> Result<(), CourseError> {
+    sqlx::query!(
+        "DELETE FROM assignment_problems WHERE assignment_id = $1",
+        assignment_id
+    )
+    .execute(&mut *conn)
+    .await?;
+    let added = sqlx::query!(
+        "INSERT INTO assignment_problems (assignment_id, problem_id, position) SELECT $1, p.problem_id, (p.position - 1)::INT FROM UNNEST($3::BIGINT[]) WITH ORDINALITY AS p(problem_id, position) JOIN course_problems cp ON cp.course_id = $2 AND cp.problem_id = p.problem_id",
+        assignment_id,
+        course_id,
+        problem_ids
+    )
+    .execute(&mut *conn)
+    .await?
+    .rows_affected() as usize;
+    if added != problem_ids.len() {
+        return Err(CourseError::Invalid(
+            "Assignment problems must be in the course's problem set".into(),
+        ));
+    }
+    Ok(())
+}
+
+/// Creates an assignment from problems of the course's set.
+#[instrument(skip(session, pg_pool, assignment))]
+pub async fn create_assignment(
+    session: Session,
+    pg_pool: Data<PgPool>,
+    path: web::Path<(i64,)>,
+    assignment: web::Json<AssignmentJson>,
+) -> Result<HttpResponse, CourseError> {
+    let auth = SessionAuth::require(&session, Role::User).ok_or(CourseError::Unauthorized)?;
+    assignment.validate()?;
+    check_assignment(&assignment)?;
+    let course_id = path.into_inner().0;
+
+    let mut transaction = pg_pool.begin().await?;
+    ensure_instructor(&mut transaction, course_id, &auth).await?;
+    let assignment_id = sqlx::query_scalar!(
+        "INSERT INTO assignments (course_id, title, description, opens_at, due_at, late_until, late_penalty_percent) VALUES ($1, $2, $3, $4, $5, $6, $7) RETURNING assignment_id",
+        course_id,
+        assignment.title,
+        assignment.description,
+        assignment.opens_at,
+        assignment.due_at,
+        assignment.late_until,
+        assignment.late_penalty_percent
+    )
+    .fetch_one(transaction.as_mut())
+    .await?;
+    set_assignment_problems(
+        &mut transaction,
+        course_id,
+        assignment_id,
+        &assignment.problem_ids,
+    )
+    .await?;
+    transaction.commit().await?;
+
+    info!(
+        "Assignment {} created in course {} by user_id: {}",
+        assignment_id, course_id, auth.user_id
+    );
+    Ok(HttpResponse::Ok().json(json!({ "assignment_id": assignment_id })))
+}
+
+/// Changes an assignment's details, deadlines or problems.
+#[instrument(skip(session, pg_pool, assignment))]
+pub async fn update_assignment(
+    session: Session,
+    pg_pool: Data<PgPool>,
+    path: web::Path<(i64, i64)>,
+    assignment: web::Json<AssignmentJson>,
+) -> Result<HttpResponse, CourseError> {
+    let auth = SessionAuth::require(&session, Role::User).ok_or(CourseError::Unauthorized)?;
+    assignment.validate()?;
+    check_assignment(&assignment)?;
+    let (course_id, assignment_id) = path.into_inner();
+
+    let mut transaction = pg_pool.begin().await?;
+    ensure_instructor(&mut transaction, course_id, &auth).await?;
+    let res = sqlx::query!(
+        "UPDATE assignments SET title = $3, description = $4, opens_at = $5, due_at = $6, late_until = $7, late_penalty_percent = $8 WHERE course_id = $1 AND assignment_id = $2",
+        course_id,
+        assignment_id,
+        assignment.title,
+        assignment.description,
+        assignment.opens_at,
+        assignment.due_at,
+        assignment.late_until,
+        assignment.late_penalty_percent
+    )
+    .execute(transaction.as_mut())
+    .await?;
+    if res.rows_affected() == 0 {
+        return Err(CourseError::NotFound);
+    }
+    set_assignment_problems(
+        &mut transaction,
+        course_id,
+        assignment_id,
+        &assignment.problem_ids,
+    )
+    .await?;
+    transaction.commit().await?;
+
+    info!(
+        "Assignment {} of course {} updated by user_id: {}",
+        assignment_id, course_id, auth.user_id
+    );
+    Ok(HttpResponse::Ok().finish())
+}
+
+#[derive(Serialize)]
+struct AssignmentProblem {
+    problem_id: i64,
+    title: String,
+}
+
+#[derive(Serialize)]
+struct AssignmentDetail {
+    assignment_id: i64,
+    title: String,
+    description: String,
+    opens_at: Option<DateTime<Utc>>,
+    due_at: DateTime<Utc>,
+    late_until: Option<DateTime<Utc>>,
+    late_penalty_percent: i32,
+    problems: Vec<AssignmentProblem>,
+}
+
+/// Shows an assignment to the course's members, to students once it has opened.
+pub async fn get_assignment(
+    session: Session,
+    pg_pool: Data<PgPool>,
+    path: web::Path<(i64, i64)>,
+) -> Result<HttpResponse, CourseError> {
+    let auth = SessionAuth::require(&session, Role::User).ok_or(CourseError::Unauthorized)?;
+    let (course_id, assignment_id) = path.into_inner();
+
+    let mut conn = pg_pool.acquire().await?;
+    let role = course_role(conn.as_mut(), course_id, &auth)
+        .await?
+        .ok_or(CourseError::NotFound)?;
+    let window = assignment_window(conn.as_mut(), assignment_id)
+        .await?
+        .filter(|window| window.course_id == course_id)
+        .ok_or(CourseError::NotFound)?;
+    if !window.opened && role != CourseRole::Instructor {
+        return Err(CourseError::NotFound);
+    }
+    let assignment = sqlx::query!(
+        "SELECT title, description, opens_at, due_at, late_until, late_penalty_percent FROM assignments WHERE assignment_id = $1",
+        assignment_id
+    )
+    .fetch_one(conn.as_mut())
+    .await?;
+    let problems = sqlx::query_as!(
+        AssignmentProblem,
+        "SELECT p.problem_id, p.title FROM assignment_problems ap JOIN problems p ON p.problem_id = ap.problem_id WHERE ap.assignment_id = $1 AND p.deleted_at IS NULL ORDER BY ap.position",
+        assignment_id
+    )
+    .fetch_all(conn.as_mut())
+    .await?;
+
+    Ok(HttpResponse::Ok().json(AssignmentDetail {
+        assignment_id,
+        title: assignment.title,
+        description: assignment.description,
+        opens_at: assignment.opens_at,
+        due_at: assignment.due_at,
+        late_until: assignment.late_until,
+        late_penalty_percent: assignment.late_penalty_percent,
+        problems,
+    }))
+}
+
+/// Grades every student of the course on every problem of the assignment,
+/// problems labelled by their title.
+pub async fn assignment_gradebook(
+    conn: &mut PgConnection,
+    course_id: i64,
+    assignment_id: i64,
+) -> Result<Gradebook, CourseError> {
+    let late_penalty_percent = sqlx::query_scalar!(
+        "SELECT late_penalty_percent FROM assignments WHERE course_id = $1 AND assignment_id = $2",
+        course_id,
+        assignment_id
+    )
+    .fetch_optional(&mut *conn)
+    .await?
+    .ok_or(CourseError::NotFound)?;
+    let problems = sqlx::query_as!(
+        GradebookProblem,
+        "SELECT ap.problem_id, p.title AS label FROM assignment_problems ap JOIN problems p ON p.problem_id = ap.problem_id WHERE ap.assignment_id = $1 ORDER BY ap.position",
+        assignment_id
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+    let students = sqlx::query_as!(
+        Student,
+        "SELECT m.user_id, u.username FROM course_members m JOIN users u ON u.user_id = m.user_id WHERE m.course_id = $1 AND m.role = 'student' ORDER BY u.username",
+        course_id
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+    let pass: &str = ExecStatus::Passed.into();
+    let submissions = sqlx::query_as!(
+        GradedSubmission,
+        r#"SELECT s.user_id, s.problem_id, s.status, COALESCE(s.score, CASE WHEN s.status = $2 THEN 100 ELSE 0 END) AS "score!", s.created_at > a.due_at AS "late!", s.created_at FROM submit_status s JOIN assignments a ON a.assignment_id = s.assignment_id WHERE s.assignment_id = $1 AND s.kind = 'submission' ORDER BY s.created_at, s.submission_id"#,
+        assignment_id,
+        pass
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let problem_ids: Vec<i64> = problems.iter().map(|problem| problem.problem_id).collect();
+    let students = grade_students(late_penalty_percent, &problem_ids, &students, &submissions);
+    Ok(Gradebook { problems, students })
+}
+
+/// Summarises each student's best result on every problem of an assignment.
+pub async fn assignment_summary(
+    session: Session,
+    pg_pool: Data<PgPool>,
+    path: web::Path<(i64, i64)>,
+) -> Result<HttpResponse, CourseError> {
+    let auth = SessionAuth::require(&session, Role::User).ok_or(CourseError::Unauthorized)?;
+    let (course_id, assignment_id) = path.into_inner();
+
+    let mut conn = pg_pool.acquire().await?;
+    ensure_instructor(&mut conn, course_id, &auth).await?;
+    let gradebook = assignment_gradebook(&mut conn, course_id, assignment_id).await?;
+    Ok(HttpResponse::Ok().json(gradebook.students))
+}
@@ -31,10 +31,11 @@ pub async fn create_problem(
     {
         let mut transaction = pg_pool.begin().await.unwrap();
         let row : Result<_,sqlx::Error> = sqlx::query!(
-            "INSERT INTO problems (title, difficulty, statement) VALUES($1, $2, $3) RETURNING problem_id",
+            "INSERT INTO problems (title, difficulty, statement, owner_id) VALUES($1, $2, $3, $4) RETURNING problem_id",
             form.title,
             form.difficulty,
-            form.statement
+            form.statement,
+            session_auth.user_id
         )
         .fetch_one(transaction.as_mut())
         .await;
@@ -0,0 +1,323 @@
+use std::cmp::min;
+use std::collections::HashSet;
+
+use actix_session::Session;
+use actix_web::{
+    HttpResponse, ResponseError,
+    web::{self, Data},
+};
+use lapin::{
+    Channel,
+    message::Delivery,
+    options::{BasicAckOptions, BasicGetOptions, BasicNackOptions},
+    types::{AMQPValue, FieldTable},
+};
+use models::{ExecStatus, WorkerTask};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use tracing::{error, info, instrument, warn};
+
+use crate::outbox::{self, OutboxError};
+use crate::pool::ChannelPool;
+use crate::routes::{role::Role, session::SessionAuth};
+
+const DEAD_LETTER_QUEUE: &str = "dlq";
+const MAX_SCAN: usize = 1000;
+
+#[derive(thiserror::Error, Debug)]
+pub enum DeadLetterError {
+    #[error("{0}")]
+    QueueError(#[from] lapin::Error),
+
+    #[error("{0}")]
+    PoolError(#[from] deadpool::managed::PoolError<lapin::Error>),
+
+    #[error("{0}")]
+    PublishError(#[from] OutboxError),
+
+    #[error("{0}")]
+    DatabaseError(#[from] sqlx::Error),
+}
+
+impl ResponseError for DeadLetterError {
+    fn error_response(&self) -> HttpResponse<actix_web::body::BoxBody> {
+        HttpResponse::InternalServerError().body(self.to_string())
+    }
+}
+
+#[derive(Serialize)]
+pub struct DeadLetter {
+    submission_id: Option<i64>,
+    problem_id: Option<i64>,
+    user_id: Option<i64>,
+    /// Runtime queue the task was dead-lettered from.
+    routing_key: Option<String>,
+    reason: Option<String>,
+    count: i64,
+}
+
+fn field<'a>(table: &'a FieldTable, key: &str) -> Option<&'a AMQPValue> {
+    table.inner().get(key)
+}
+
+impl DeadLetter {
+    fn from_delivery(delivery: &Delivery) -> DeadLetter {
+        let task: Option<WorkerTask> = serde_json::from_slice(&delivery.data).ok();
+
+        // RabbitMQ prepends to x-death, so the first entry is the latest dead-lettering
+        let death = delivery
+            .properties
+            .headers()
+            .as_ref()
+            .and_then(|headers| field(headers, "x-death"))
+            .and_then(AMQPValue::as_array)
+            .and_then(|deaths| deaths.as_slice().first())
+            .and_then(AMQPValue::as_field_table);
+
+        let reason = death
+            .and_then(|d| field(d, "reason"))
+            .and_then(AMQPValue::as_long_string)
+            .map(|reason| reason.to_string());
+        let count = death
+            .and_then(|d| field(d, "count"))
+            .and_then(AMQPValue::as_long_long_int)
+            .unwrap_or_default();
+        let routing_key = death
+            .and_then(|d| field(d, "routing-keys"))
+            .and_then(AMQPValue::as_array)
+            .and_then(|keys| keys.as_slice().first())
+            .and_then(AMQPValue::as_long_string)
+            .map(|key| key.to_string());
+
+        DeadLetter {
+            submission_id: task.as_ref().map(|t| t.submission_id),
+            problem_id: task.as_ref().map(|t| t.problem_id),
+            user_id: task.as_ref().map(|t| t.user_id),
+            routing_key,
+            reason,
+            count,
+        }
+    }
+}
+
+/// Takes up to `limit` messages off the dead-letter queue without acking them.
+/// Every returned delivery must be acked or requeued by the caller.
+async fn fetch_dead_letters(
+    channel: &Channel,
+    limit: usize,
+) -> Result<Vec<(Delivery, DeadLetter)>, lapin::Error> {
+    let mut letters = Vec::new();
+    while letters.len() < limit {
+        match channel
+            .basic_get(DEAD_LETTER_QUEUE, BasicGetOptions::default())
+            .await?
+        {
+            Some(message) => {
+                let letter = DeadLetter::from_delivery(&message.delivery);
+                letters.push((message.delivery, letter));
+            }
+            None => break,
+        }
+    }
+    Ok(letters)
+}
+
+async fn requeue(delivery: &Delivery) -> Result<(), lapin::Error> {
+    delivery
+        .nack(BasicNackOptions {
+            multiple: false,
+            requeue: true,
+        })
+        .await?;
+    Ok(())
+}
+
+fn is_admin(session: &Session) -> bool {
+    SessionAuth::require(session, Role::Admin).is_some()
+}
+
+#[derive(Deserialize)]
+pub struct DeadLetterQuery {
+    limit: Option<usize>,
+}
+
+#[instrument(skip_all)]
+pub async fn list_dead_letters(
+    session: Session,
+    channel_pool: Data<ChannelPool>,
+    query: web::Query<DeadLetterQuery>,
+) -> Result<HttpResponse, DeadLetterError> {
+    if !is_admin(&session) {
+        return Ok(HttpResponse::Unauthorized().finish());
+    }
+    let limit = min(query.limit.unwrap_or(50), MAX_SCAN);
+
+    let channel = channel_pool.get().await?;
+    let letters = fetch_dead_letters(&channel, limit).await?;
+
+    let mut listed = Vec::with_capacity(letters.len());
+    for (delivery, letter) in letters {
+        requeue(&delivery).await?;
+        listed.push(letter);
+    }
+
+    Ok(HttpResponse::Ok().json(listed))
+}
+
+#[derive(Deserialize)]
+pub struct DeadLetterSelection {
+    submission_ids: HashSet<i64>,
+}
+
+#[derive(Serialize)]
+struct DeadLetterOutcome {
+    handled: Vec<i64>,
+    not_found: Vec<i64>,
+}
+
+impl DeadLetterOutcome {
+    fn new(selection: &DeadLetterSelection, handled: Vec<i64>) -> DeadLetterOutcome {
+        let not_found = selection
+            .submission_ids
+            .iter()
+            .filter(|id| !handled.contains(id))
+            .copied()
+            .collect();
+        DeadLetterOutcome { handled, not_found }
+    }
+}
+
+/// Publishes the selected dead-lettered tasks back to the runtime queue they failed on.
+#[instrument(skip_all)]
+pub async fn replay_dead_letters(
+    session: Session,
+    channel_pool: Data<ChannelPool>,
+    pg_pool: Data<PgPool>,
+    selection: web::Json<DeadLetterSelection>,
+) -> Result<HttpResponse, DeadLetterError> {
+    if !is_admin(&session) {
+        return Ok(HttpResponse::Unauthorized().finish());
+    }
+    let judge_error: &str = ExecStatus::JudgeError.into();
+    let pending: &str = ExecStatus::Pending.into();
+
+    let channel = channel_pool.get().await?;
+    let letters = fetch_dead_letters(&channel, MAX_SCAN).await?;
+
+    let mut replayed = Vec::new();
+    let mut failure: Option<DeadLetterError> = None;
+    for (delivery, letter) in letters {
+        let selected = letter
+            .submission_id
+            .filter(|id| failure.is_none() && selection.submission_ids.contains(id));
+        let (Some(submission_id), Some(routing_key)) = (selected, &letter.routing_key) else {
+            requeue(&delivery).await?;
+            continue;
+        };
+
+        // workers give up with a judge error verdict, which must be reset for the task to be claimable again
+        let reset = sqlx::query!(
+            "UPDATE submit_status SET status = $1, output = NULL WHERE submission_id = $2 AND status = $3",
+            pending,
+            submission_id,
+            judge_error
+        )
+        .execute(pg_pool.as_ref())
+        .await;
+        if let Err(e) = reset {
+            error!(
+                "Failed to reset submission_id={} for replay: {}",
+                submission_id, e
+            );
+            requeue(&delivery).await?;
+            failure = Some(e.into());
+            continue;
+        }
+
+        match outbox::publish(&channel, submission_id, routing_key, &delivery.data).await {
+            Ok(()) => {
+                delivery.ack(BasicAckOptions::default()).await?;
+                info!(
+                    "Replayed submission_id={} to queue {}",
+                    submission_id, routing_key
+                );
+                replayed.push(submission_id);
+            }
+            Err(e) => {
+                // hand every remaining message back before reporting the failure
+                error!("Failed to replay submission_id={}: {}", submission_id, e);
+                requeue(&delivery).await?;
+                failure = Some(e.into());
+            }
+        }
+    }
+
+    if let Some(e) = failure {
+        return Err(e);
+    }
+    Ok(HttpResponse::Ok().json(DeadLetterOutcome::new(&selection, replayed)))
+}
+
+/// Drops the selected dead-lettered tasks and gives their submissions a judge error verdict.
+#[instrument(skip_all)]
+pub async fn discard_dead_letters(
+    session: Session,
+    channel_pool: Data<ChannelPool>,
+    pg_pool: Data<PgPool>,
+    selection: web::Json<DeadLetterSelection>,
+) -> Result<HttpResponse, DeadLetterError> {
+    if !is_admin(&session) {
+        return Ok(HttpResponse::Unauthorized().finish());
+    }
+    let judge_error: &str = ExecStatus::JudgeError.into();
+    let pending: &str = ExecStatus::Pending.into();
+
+    let channel = channel_pool.get().await?;
+    let letters = fetch_dead_letters(&channel, MAX_SCAN).await?;
+
+    let mut discarded = Vec::new();
+    let mut failure = None;
+    for (delivery, letter) in letters {
+        let Some(submission_id) = letter
+            .submission_id
+            .filter(|id| failure.is_none() && selection.submission_ids.contains(id))
+        else {
+            requeue(&delivery).await?;
+            continue;
+        };
+
+        let output = letter
+            .reason
+            .map(|reason| format!("Dead-lettered: {}", reason));
+        let res = sqlx::query!(
+            "UPDATE submit_status SET status = $1, output = $2, lease_expires_at = NULL WHERE submission_id = $3 AND status = $4",
+            judge_error,
+            output,
+            submission_id,
+            pending
+        )
+        .execute(pg_pool.as_ref())
+        .await;
+
+        match res {
+            Ok(_) => {
+                delivery.ack(BasicAckOptions::default()).await?;
+                info!("Discarded dead-lettered submission_id={}", submission_id);
+                discarded.push(submission_id);
+            }
+            Err(e) => {
+                warn!(
+                    "Failed to mark submission_id={} as judge error: {}",
+                    submission_id, e
+                );
+                requeue(&delivery).await?;
+                failure = Some(e);
+            }
+        }
+    }
+
+    if let Some(e) = failure {
+        return Err(e.into());
+    }
+    Ok(HttpResponse::Ok().json(DeadLetterOutcome::new(&selection, discarded)))
+}
@@ -0,0 +1,241 @@
+use actix_session::Session;
+use actix_web::{
+    HttpResponse, Responder, ResponseError,
+    web::{self, Data, Form},
+};
+use models::Checker;
+use sqlx::PgPool;
+use tracing::{info, warn};
+
+use crate::routes::{
+    access::{AccessDenied, ensure_editor, ensure_manager},
+    input_validator::testcases_validated,
+    reference::reference_verified,
+    role::Role,
+    session::SessionAuth,
+    visibility::Visibility,
+};
+
+/// Difficulties a problem can be filed under.
+pub const DIFFICULTIES: [&str; 3] = ["easy", "medium", "hard"];
+
+#[derive(serde::Deserialize)]
+pub struct UpdateProblemForm {
+    title: Option<String>,
+    difficulty: Option<String>,
+    statement: Option<String>,
+    time_limit_ms: Option<i32>,
+    memory_limit_mb: Option<i32>,
+    checker: Option<String>,
+}
+
+/// Updates the given fields of a problem. Testcases are managed under
+/// `/problem/{problemID}/testcases`.
+pub async fn update_problem(
+    pg_pool: Data<PgPool>,
+    session: Session,
+    path: web::Path<(i64,)>,
+    form: Form<UpdateProblemForm>,
+) -> impl Responder {
+    let Some(session_auth) = SessionAuth::require(&session, Role::ProblemSetter) else {
+        return HttpResponse::Unauthorized().finish();
+    };
+    let problem_id = path.into_inner().0;
+
+    if let Some(difficulty) = &form.difficulty
+        && !DIFFICULTIES.contains(&difficulty.as_str())
+    {
+        return HttpResponse::BadRequest().body(format!(
+            "Difficulty must be one of {}",
+            DIFFICULTIES.join(", ")
+        ));
+    }
+
+    if let Some(checker) = &form.checker
+        && let Err(e) = Checker::try_from(checker.as_str())
+    {
+        return HttpResponse::BadRequest().body(e);
+    }
+
+    let mut transaction = match pg_pool.begin().await {
+        Ok(transaction) => transaction,
+        Err(e) => return HttpResponse::InternalServerError().body(e.to_string()),
+    };
+    if let Err(e) = ensure_editor::<AccessDenied>(&mut transaction, problem_id, &session_auth).await
+    {
+        return e.error_response();
+    }
+
+    let row: Result<_, sqlx::Error> = sqlx::query!(
+        "UPDATE problems SET title = COALESCE($2, title), difficulty = COALESCE($3, difficulty), statement = COALESCE($4, statement), time_limit_ms = COALESCE($5, time_limit_ms), memory_limit_mb = COALESCE($6, memory_limit_mb), checker = COALESCE($7, checker), judging_updated_at = CASE WHEN $5::INT IS NULL AND $7::TEXT IS NULL THEN judging_updated_at ELSE NOW() END WHERE problem_id = $1 AND deleted_at IS NULL RETURNING problem_id",
+        problem_id,
+        form.title,
+        form.difficulty,
+        form.statement,
+        form.time_limit_ms,
+        form.memory_limit_mb,
+        form.checker
+    )
+    .fetch_optional(transaction.as_mut())
+    .await;
+
+    match row {
+        Ok(Some(_)) => {}
+        Ok(None) => return HttpResponse::NotFound().finish(),
+        Err(e) => return HttpResponse::InternalServerError().body(e.to_string()),
+    }
+
+    match transaction.commit().await {
+        Ok(_) => {
+            info!(
+                "Problem {} updated by user_id: {}",
+                problem_id, session_auth.user_id
+            );
+            HttpResponse::Ok().finish()
+        }
+        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
+
+/// Hides a problem from listings and new submissions. Its testcases and
+/// submission history are kept.
+pub async fn delete_problem(
+    pg_pool: Data<PgPool>,
+    session: Session,
+    path: web::Path<(i64,)>,
+) -> impl Responder {
+    let Some(session_auth) = SessionAuth::require(&session, Role::ProblemSetter) else {
+        return HttpResponse::Unauthorized().finish();
+    };
+    let problem_id = path.into_inner().0;
+    let mut conn = match pg_pool.acquire().await {
+        Ok(conn) => conn,
+        Err(e) => return HttpResponse::InternalServerError().body(e.to_string()),
+    };
+    if let Err(e) = ensure_manager::<AccessDenied>(&mut conn, problem_id, &session_auth).await {
+        return e.error_response();
+    }
+
+    let row: Result<_, sqlx::Error> = sqlx::query!(
+        "UPDATE problems SET deleted_at = NOW() WHERE problem_id = $1 AND deleted_at IS NULL RETURNING problem_id",
+        problem_id
+    )
+    .fetch_optional(&mut *conn)
+    .await;
+
+    match row {
+        Ok(Some(_)) => {
+            warn!(
+                "Problem {} deleted by user_id: {}",
+                problem_id, session_auth.user_id
+            );
+            HttpResponse::Ok().finish()
+        }
+        Ok(None) => HttpResponse::NotFound().finish(),
+        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
+
+#[derive(serde::Deserialize)]
+pub struct VisibilityChange {
+    visibility: Visibility,
+}
+
+/// Moves a problem between draft, review, published and archived. Only an
+/// Admin can publish, and only problems that were sent for review and whose
+/// reference solution passes. Other moves are open to the problem's owner and
+/// co-authors.
+pub async fn change_visibility(
+    pg_pool: Data<PgPool>,
+    session: Session,
+    path: web::Path<(i64,)>,
+    body: web::Json<VisibilityChange>,
+) -> impl Responder {
+    let Some(session_auth) = SessionAuth::require(&session, Role::ProblemSetter) else {
+        return HttpResponse::Unauthorized().finish();
+    };
+    let problem_id = path.into_inner().0;
+    let target = body.visibility;
+
+    let mut transaction = match pg_pool.begin().await {
+        Ok(transaction) => transaction,
+        Err(e) => return HttpResponse::InternalServerError().body(e.to_string()),
+    };
+
+    let row: Result<_, sqlx::Error> = sqlx::query!(
+        "SELECT visibility FROM problems WHERE problem_id = $1 AND deleted_at IS NULL FOR UPDATE",
+        problem_id
+    )
+    .fetch_optional(transaction.as_mut())
+    .await;
+
+    let current = match row {
+        Ok(Some(row)) => match Visibility::try_from(row.visibility.as_str()) {
+            Ok(visibility) => visibility,
+            Err(e) => return HttpResponse::InternalServerError().body(e),
+        },
+        Ok(None) => return HttpResponse::NotFound().finish(),
+        Err(e) => return HttpResponse::InternalServerError().body(e.to_string()),
+    };
+    // Admin steps are checked against the role below, the rest need edit rights
+    if let Err(e) = ensure_editor::<AccessDenied>(&mut transaction, problem_id, &session_auth).await
+    {
+        return e.error_response();
+    }
+
+    match current.transition_role(target) {
+        None => {
+            let (from, to): (&str, &str) = (current.into(), target.into());
+            return HttpResponse::Conflict()
+                .body(format!("Cannot move a {} problem to {}", from, to));
+        }
+        Some(role) if session_auth.role < role => return HttpResponse::Forbidden().finish(),
+        Some(_) => {}
+    }
+    // a problem is first published only once its reference solution passes every
+    // testcase and its validator accepted them all
+    if current == Visibility::Review && target == Visibility::Published {
+        match reference_verified(&mut transaction, problem_id).await {
+            Ok(true) => {}
+            Ok(false) => {
+                return HttpResponse::Conflict()
+                    .body("The reference solution has not passed on the current testcases");
+            }
+            Err(e) => return HttpResponse::InternalServerError().body(e.to_string()),
+        }
+        match testcases_validated(&mut transaction, problem_id).await {
+            Ok(true) => {}
+            Ok(false) => {
+                return HttpResponse::Conflict()
+                    .body("Some testcases are awaiting validation or were rejected");
+            }
+            Err(e) => return HttpResponse::InternalServerError().body(e.to_string()),
+        }
+    }
+
+    let visibility: &str = target.into();
+    let published: &str = Visibility::Published.into();
+    let updated: Result<_, sqlx::Error> = sqlx::query!(
+        "UPDATE problems SET visibility = $2, published_at = CASE WHEN $2 = $3 THEN COALESCE(published_at, NOW()) ELSE published_at END WHERE problem_id = $1",
+        problem_id,
+        visibility,
+        published
+    )
+    .execute(transaction.as_mut())
+    .await;
+    if let Err(e) = updated {
+        return HttpResponse::InternalServerError().body(e.to_string());
+    }
+
+    match transaction.commit().await {
+        Ok(_) => {
+            let from: &str = current.into();
+            info!(
+                "Problem {} moved from {} to {} by user_id: {}",
+                problem_id, from, visibility, session_auth.user_id
+            );
+            HttpResponse::Ok().json(serde_json::json!({ "visibility": target }))
+        }
+        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
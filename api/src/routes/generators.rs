@@ -0,0 +1,336 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use actix_session::Session;
+use actix_web::{
+    HttpResponse, ResponseError,
+    web::{self, Data},
+};
+use models::{RuntimeConfigs, TaskKind, TestcaseLimits, WorkerTask};
+use serde::Serialize;
+use sqlx::PgPool;
+use tracing::{info, instrument, warn};
+use validator::Validate;
+
+use crate::generator::{ScriptError, parse_generator_script, valid_generator_name};
+use crate::outbox;
+use crate::pool::ChannelPool;
+use crate::routes::{
+    access::{AccessError, ensure_editor},
+    role::Role,
+    session::SessionAuth,
+};
+
+#[derive(serde::Deserialize, Validate)]
+pub struct GeneratorJson {
+    #[validate(length(min = 1, message = "Code cannot be empty"))]
+    code: String,
+    #[validate(length(min = 1, max = 50, message = "Environment must be specified"))]
+    env: String,
+}
+
+#[derive(serde::Deserialize)]
+pub struct GenerateJson {
+    script: String,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum GeneratorError {
+    #[error("{0}")]
+    DatabaseError(#[from] sqlx::Error),
+
+    #[error("No such environment {0}")]
+    InvalidEnvironment(String),
+
+    #[error("Validation error: {0}")]
+    Validation(#[from] validator::ValidationErrors),
+
+    #[error("Invalid generator script: {0}")]
+    Script(#[from] ScriptError),
+
+    #[error("{0}")]
+    Invalid(String),
+
+    #[error("{0}")]
+    TooLarge(String),
+
+    #[error("{0}")]
+    Conflict(String),
+
+    #[error("No such problem")]
+    NotFound,
+
+    #[error("Unauthorized")]
+    Unauthorized,
+
+    #[error("Forbidden")]
+    Forbidden,
+}
+
+impl ResponseError for GeneratorError {
+    fn error_response(&self) -> HttpResponse<actix_web::body::BoxBody> {
+        match self {
+            Self::InvalidEnvironment(_)
+            | Self::Validation(_)
+            | Self::Script(_)
+            | Self::Invalid(_) => HttpResponse::BadRequest().body(self.to_string()),
+            Self::TooLarge(e) => HttpResponse::PayloadTooLarge().body(e.clone()),
+            Self::Conflict(e) => HttpResponse::Conflict().body(e.clone()),
+            Self::NotFound => HttpResponse::NotFound().finish(),
+            Self::Unauthorized => HttpResponse::Unauthorized().finish(),
+            Self::Forbidden => HttpResponse::Forbidden().finish(),
+            _ => HttpResponse::InternalServerError().body(self.to_string()),
+        }
+    }
+}
+
+impl AccessError for GeneratorError {
+    const NOT_FOUND: Self = Self::NotFound;
+    const FORBIDDEN: Self = Self::Forbidden;
+}
+
+#[derive(Serialize)]
+struct Generator {
+    name: String,
+    env: String,
+}
+
+#[derive(Serialize)]
+struct Generators {
+    script: Option<String>,
+    generators: Vec<Generator>,
+}
+
+/// Lists a problem's generators and its last generator script.
+#[instrument(skip(session, pg_pool))]
+pub async fn list_generators(
+    session: Session,
+    pg_pool: Data<PgPool>,
+    path: web::Path<(i64,)>,
+) -> Result<HttpResponse, GeneratorError> {
+    let auth =
+        SessionAuth::require(&session, Role::ProblemSetter).ok_or(GeneratorError::Unauthorized)?;
+    let problem_id = path.into_inner().0;
+
+    let mut conn = pg_pool.acquire().await?;
+    ensure_editor::<GeneratorError>(&mut conn, problem_id, &auth).await?;
+    let script = sqlx::query_scalar!(
+        "SELECT generator_script FROM problems WHERE problem_id = $1",
+        problem_id
+    )
+    .fetch_one(conn.as_mut())
+    .await?;
+    let generators = sqlx::query_as!(
+        Generator,
+        "SELECT name, env FROM problem_generators WHERE problem_id = $1 ORDER BY name",
+        problem_id
+    )
+    .fetch_all(conn.as_mut())
+    .await?;
+
+    Ok(HttpResponse::Ok().json(Generators { script, generators }))
+}
+
+/// Adds a generator to a problem, or replaces the one with the same name.
+#[instrument(skip(request, session, runtimeconfigs, pg_pool))]
+pub async fn set_generator(
+    request: web::Json<GeneratorJson>,
+    path: web::Path<(i64, String)>,
+    session: Session,
+    runtimeconfigs: Data<RuntimeConfigs>,
+    pg_pool: Data<PgPool>,
+) -> Result<HttpResponse, GeneratorError> {
+    let auth =
+        SessionAuth::require(&session, Role::ProblemSetter).ok_or(GeneratorError::Unauthorized)?;
+    request.validate()?;
+    if !runtimeconfigs.0.contains_key(&request.env) {
+        warn!("Invalid environment: {}", request.env);
+        return Err(GeneratorError::InvalidEnvironment(request.env.clone()));
+    }
+    let (problem_id, name) = path.into_inner();
+    if !valid_generator_name(&name) || name.len() > 50 {
+        return Err(GeneratorError::Invalid(format!(
+            "Invalid generator name {}",
+            name
+        )));
+    }
+
+    let mut conn = pg_pool.acquire().await?;
+    ensure_editor::<GeneratorError>(&mut conn, problem_id, &auth).await?;
+    sqlx::query!(
+        "INSERT INTO problem_generators (problem_id, name, code, env) VALUES ($1, $2, $3, $4) ON CONFLICT (problem_id, name) DO UPDATE SET code = EXCLUDED.code, env = EXCLUDED.env",
+        problem_id,
+        name,
+        request.code,
+        request.env
+    )
+    .execute(conn.as_mut())
+    .await?;
+
+    info!(
+        "Generator {} of problem {} set by user_id: {}",
+        name, problem_id, auth.user_id
+    );
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Removes a generator from a problem. Testcases it generated are kept.
+#[instrument(skip(session, pg_pool))]
+pub async fn delete_generator(
+    session: Session,
+    pg_pool: Data<PgPool>,
+    path: web::Path<(i64, String)>,
+) -> Result<HttpResponse, GeneratorError> {
+    let auth =
+        SessionAuth::require(&session, Role::ProblemSetter).ok_or(GeneratorError::Unauthorized)?;
+    let (problem_id, name) = path.into_inner();
+
+    let mut conn = pg_pool.acquire().await?;
+    ensure_editor::<GeneratorError>(&mut conn, problem_id, &auth).await?;
+    let res = sqlx::query!(
+        "DELETE FROM problem_generators WHERE problem_id = $1 AND name = $2",
+        problem_id,
+        name
+    )
+    .execute(conn.as_mut())
+    .await?;
+    if res.rows_affected() == 0 {
+        return Err(GeneratorError::NotFound);
+    }
+
+    info!(
+        "Generator {} of problem {} removed by user_id: {}",
+        name, problem_id, auth.user_id
+    );
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Stores a generator script and queues it. The worker replaces the problem's
+/// generated testcases with the outputs, then queues the reference solution to
+/// produce their expected outputs. The run is polled through `/{submissionID}/status`.
+#[instrument(skip(request, channel_pool, session, limits, pg_pool))]
+pub async fn generate_testcases(
+    request: web::Json<GenerateJson>,
+    path: web::Path<(i64,)>,
+    channel_pool: Data<ChannelPool>,
+    session: Session,
+    limits: Data<TestcaseLimits>,
+    pg_pool: Data<PgPool>,
+) -> Result<HttpResponse, GeneratorError> {
+    let auth =
+        SessionAuth::require(&session, Role::ProblemSetter).ok_or(GeneratorError::Unauthorized)?;
+    let problem_id = path.into_inner().0;
+    let invocations = parse_generator_script(&request.script)?;
+    if invocations.is_empty() {
+        return Err(GeneratorError::Invalid(
+            "Generator script has no invocations".into(),
+        ));
+    }
+
+    let mut transaction = pg_pool.begin().await?;
+    ensure_editor::<GeneratorError>(&mut transaction, problem_id, &auth).await?;
+
+    let reference = sqlx::query_scalar!(
+        "SELECT reference_code FROM problems WHERE problem_id = $1",
+        problem_id
+    )
+    .fetch_one(transaction.as_mut())
+    .await?;
+    if reference.is_none() {
+        return Err(GeneratorError::Conflict(
+            "Generated testcases need a reference solution for their outputs".into(),
+        ));
+    }
+
+    let used: BTreeSet<&str> = invocations
+        .iter()
+        .map(|invocation| invocation.generator.as_str())
+        .collect();
+    let rows = sqlx::query!(
+        "SELECT name, code, env FROM problem_generators WHERE problem_id = $1",
+        problem_id
+    )
+    .fetch_all(transaction.as_mut())
+    .await?;
+    let mut generators = BTreeMap::new();
+    let mut envs = BTreeSet::new();
+    for row in rows {
+        if used.contains(row.name.as_str()) {
+            envs.insert(row.env);
+            generators.insert(row.name, row.code);
+        }
+    }
+    if let Some(missing) = used.iter().find(|name| !generators.contains_key(**name)) {
+        return Err(GeneratorError::Invalid(format!(
+            "No such generator {}",
+            missing
+        )));
+    }
+    // one worker runs the whole script, so it can only serve one environment
+    let mut envs = envs.into_iter();
+    let env = envs.next().unwrap_or_default();
+    if envs.next().is_some() {
+        return Err(GeneratorError::Invalid(
+            "Generators used by one script must share an environment".into(),
+        ));
+    }
+
+    let manual = sqlx::query_scalar!(
+        r#"SELECT COUNT(*) AS "count!" FROM problem_testcases WHERE problem_id = $1 AND NOT generated"#,
+        problem_id
+    )
+    .fetch_one(transaction.as_mut())
+    .await?;
+    if manual as usize + invocations.len() > limits.max_testcases {
+        return Err(GeneratorError::TooLarge(format!(
+            "A problem can have at most {} testcases",
+            limits.max_testcases
+        )));
+    }
+
+    sqlx::query!(
+        "UPDATE problems SET generator_script = $2 WHERE problem_id = $1",
+        problem_id,
+        request.script
+    )
+    .execute(transaction.as_mut())
+    .await?;
+    let submission_id = sqlx::query!(
+        "INSERT INTO submit_status (user_id, problem_id, kind) VALUES ($1, $2, 'generation') RETURNING submission_id",
+        auth.user_id,
+        problem_id
+    )
+    .fetch_one(transaction.as_mut())
+    .await?
+    .submission_id;
+
+    let invocation_count = invocations.len();
+    let worker_task = WorkerTask {
+        code: String::new(),
+        problem_id,
+        user_id: auth.user_id,
+        submission_id,
+        kind: TaskKind::Generation {
+            generators,
+            invocations,
+            max_input_bytes: limits.max_testcase_bytes,
+        },
+    };
+    let payload = serde_json::to_vec(&worker_task).unwrap();
+    outbox::enqueue(transaction.as_mut(), submission_id, &env, &payload).await?;
+    transaction.commit().await?;
+
+    let dispatched = outbox::dispatch(
+        pg_pool.as_ref(),
+        &channel_pool,
+        submission_id,
+        &env,
+        &payload,
+    )
+    .await;
+
+    info!(
+        "Generator script of problem {} with {} invocations queued as submission {} by user_id: {}",
+        problem_id, invocation_count, submission_id, auth.user_id
+    );
+    Ok(outbox::queued_response(submission_id, dispatched))
+}
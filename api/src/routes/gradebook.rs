@@ -0,0 +1,140 @@
+use actix_session::Session;
+use actix_web::{
+    HttpResponse,
+    http::header::{ContentDisposition, DispositionParam, DispositionType},
+    web::{self, Data},
+};
+use models::ExecStatus;
+use serde::Deserialize;
+use sqlx::PgPool;
+use tracing::{info, instrument};
+
+use crate::grades::{Gradebook, GradebookProblem, GradedSubmission, Student, grade_students};
+use crate::routes::{
+    contests::{ContestError, contest_window},
+    courses::{CourseError, assignment_gradebook, ensure_instructor},
+    role::Role,
+    session::SessionAuth,
+};
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum GradebookFormat {
+    #[default]
+    Json,
+    Csv,
+}
+
+#[derive(Deserialize)]
+pub struct GradebookQuery {
+    #[serde(default)]
+    format: GradebookFormat,
+}
+
+/// Sends the gradebook as a `<name>.json` or `<name>.csv` attachment.
+fn gradebook_response(gradebook: &Gradebook, format: GradebookFormat, name: &str) -> HttpResponse {
+    let (extension, content_type, body) = match format {
+        GradebookFormat::Json => (
+            "json",
+            "application/json",
+            serde_json::to_string(gradebook).unwrap(),
+        ),
+        GradebookFormat::Csv => ("csv", "text/csv; charset=utf-8", gradebook.to_csv()),
+    };
+    HttpResponse::Ok()
+        .content_type(content_type)
+        .insert_header(ContentDisposition {
+            disposition: DispositionType::Attachment,
+            parameters: vec![DispositionParam::Filename(format!(
+                "{}.{}",
+                name, extension
+            ))],
+        })
+        .body(body)
+}
+
+/// Exports an assignment's grades for the course's instructors.
+#[instrument(skip(session, pg_pool, query))]
+pub async fn export_assignment_grades(
+    session: Session,
+    pg_pool: Data<PgPool>,
+    path: web::Path<(i64, i64)>,
+    query: web::Query<GradebookQuery>,
+) -> Result<HttpResponse, CourseError> {
+    let auth = SessionAuth::require(&session, Role::User).ok_or(CourseError::Unauthorized)?;
+    let (course_id, assignment_id) = path.into_inner();
+
+    let mut conn = pg_pool.acquire().await?;
+    ensure_instructor(&mut conn, course_id, &auth).await?;
+    let gradebook = assignment_gradebook(&mut conn, course_id, assignment_id).await?;
+
+    info!(
+        "Grades of assignment {} exported by user_id: {}",
+        assignment_id, auth.user_id
+    );
+    Ok(gradebook_response(
+        &gradebook,
+        query.format,
+        &format!("assignment-{}-grades", assignment_id),
+    ))
+}
+
+/// Exports a contest's grades for its managers: every registered user, alone or in a
+/// team, graded on their own submissions. Virtual submissions don't count.
+#[instrument(skip(session, pg_pool, query))]
+pub async fn export_contest_grades(
+    session: Session,
+    pg_pool: Data<PgPool>,
+    path: web::Path<(i64,)>,
+    query: web::Query<GradebookQuery>,
+) -> Result<HttpResponse, ContestError> {
+    let auth = SessionAuth::require(&session, Role::User).ok_or(ContestError::Unauthorized)?;
+    let contest_id = path.into_inner().0;
+
+    let mut conn = pg_pool.acquire().await?;
+    let window = contest_window(conn.as_mut(), contest_id)
+        .await?
+        .ok_or(ContestError::NotFound)?;
+    if !window.can_manage(&auth) {
+        return Err(ContestError::Forbidden);
+    }
+    let problems = sqlx::query_as!(
+        GradebookProblem,
+        "SELECT problem_id, label FROM contest_problems WHERE contest_id = $1 ORDER BY position",
+        contest_id
+    )
+    .fetch_all(conn.as_mut())
+    .await?;
+    let students = sqlx::query_as!(
+        Student,
+        "SELECT user_id, username FROM users WHERE user_id IN (SELECT user_id FROM contest_registrations WHERE contest_id = $1 UNION SELECT m.user_id FROM contest_team_registrations r JOIN team_members m ON m.team_id = r.team_id WHERE r.contest_id = $1) ORDER BY username",
+        contest_id
+    )
+    .fetch_all(conn.as_mut())
+    .await?;
+    let pass: &str = ExecStatus::Passed.into();
+    let submissions = sqlx::query_as!(
+        GradedSubmission,
+        r#"SELECT user_id, problem_id, status, COALESCE(score, CASE WHEN status = $2 THEN 100 ELSE 0 END) AS "score!", FALSE AS "late!", created_at FROM submit_status WHERE contest_id = $1 AND kind = 'submission' AND NOT is_virtual ORDER BY created_at, submission_id"#,
+        contest_id,
+        pass
+    )
+    .fetch_all(conn.as_mut())
+    .await?;
+
+    let problem_ids: Vec<i64> = problems.iter().map(|problem| problem.problem_id).collect();
+    let gradebook = Gradebook {
+        students: grade_students(0, &problem_ids, &students, &submissions),
+        problems,
+    };
+
+    info!(
+        "Grades of contest {} exported by user_id: {}",
+        contest_id, auth.user_id
+    );
+    Ok(gradebook_response(
+        &gradebook,
+        query.format,
+        &format!("contest-{}-grades", contest_id),
+    ))
+}
@@ -0,0 +1,278 @@
+use actix_session::Session;
+use actix_web::{
+    HttpResponse, ResponseError,
+    web::{self, Data},
+};
+use models::{RuntimeConfigs, TaskKind, WorkerTask};
+use serde::Serialize;
+use serde_json::json;
+use sqlx::{PgConnection, PgPool};
+use tracing::{info, instrument, warn};
+use validator::Validate;
+
+use crate::outbox;
+use crate::pool::ChannelPool;
+use crate::routes::{
+    access::{AccessError, ensure_editor},
+    role::Role,
+    session::SessionAuth,
+};
+
+#[derive(serde::Deserialize, Validate)]
+pub struct ValidatorJson {
+    #[validate(length(min = 1, message = "Code cannot be empty"))]
+    code: String,
+    #[validate(length(min = 1, max = 50, message = "Environment must be specified"))]
+    env: String,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum ValidatorError {
+    #[error("{0}")]
+    DatabaseError(#[from] sqlx::Error),
+
+    #[error("No such environment {0}")]
+    InvalidEnvironment(String),
+
+    #[error("Validation error: {0}")]
+    Validation(#[from] validator::ValidationErrors),
+
+    #[error("No such problem")]
+    NotFound,
+
+    #[error("Unauthorized")]
+    Unauthorized,
+
+    #[error("Forbidden")]
+    Forbidden,
+}
+
+impl ResponseError for ValidatorError {
+    fn error_response(&self) -> HttpResponse<actix_web::body::BoxBody> {
+        match self {
+            Self::InvalidEnvironment(_) | Self::Validation(_) => {
+                HttpResponse::BadRequest().body(self.to_string())
+            }
+            Self::NotFound => HttpResponse::NotFound().finish(),
+            Self::Unauthorized => HttpResponse::Unauthorized().finish(),
+            Self::Forbidden => HttpResponse::Forbidden().finish(),
+            _ => HttpResponse::InternalServerError().body(self.to_string()),
+        }
+    }
+}
+
+impl AccessError for ValidatorError {
+    const NOT_FOUND: Self = Self::NotFound;
+    const FORBIDDEN: Self = Self::Forbidden;
+}
+
+/// A validation task written to the outbox, published once its transaction commits.
+pub struct QueuedValidation {
+    pub submission_id: i64,
+    routing_key: String,
+    payload: Vec<u8>,
+}
+
+impl QueuedValidation {
+    /// Publishes the task. A failure is only logged, the outbox relay retries it.
+    pub async fn dispatch(&self, pg_pool: &PgPool, channel_pool: &ChannelPool) {
+        if let Err(e) = outbox::dispatch(
+            pg_pool,
+            channel_pool,
+            self.submission_id,
+            &self.routing_key,
+            &self.payload,
+        )
+        .await
+        {
+            warn!(
+                "Validation {} left to the outbox relay: {}",
+                self.submission_id, e
+            );
+        }
+    }
+}
+
+/// Marks `testcase_ids` as pending and queues the problem's validator against them.
+/// Returns `None` when the problem has no validator, the testcases then stay valid.
+pub async fn queue_validation(
+    conn: &mut PgConnection,
+    problem_id: i64,
+    user_id: i64,
+    testcase_ids: &[i64],
+) -> Result<Option<QueuedValidation>, sqlx::Error> {
+    let problem = sqlx::query!(
+        "SELECT validator_code, validator_env FROM problems WHERE problem_id = $1",
+        problem_id
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+    let (Some(code), Some(env)) = (problem.validator_code, problem.validator_env) else {
+        return Ok(None);
+    };
+    if testcase_ids.is_empty() {
+        return Ok(None);
+    }
+
+    sqlx::query!(
+        "UPDATE problem_testcases SET validation = 'pending', validation_message = NULL WHERE problem_id = $1 AND testcase_id = ANY($2)",
+        problem_id,
+        testcase_ids
+    )
+    .execute(&mut *conn)
+    .await?;
+    let submission_id = sqlx::query!(
+        "INSERT INTO submit_status (user_id, problem_id, kind) VALUES ($1, $2, 'validation') RETURNING submission_id",
+        user_id,
+        problem_id
+    )
+    .fetch_one(&mut *conn)
+    .await?
+    .submission_id;
+
+    let worker_task = WorkerTask {
+        code,
+        problem_id,
+        user_id,
+        submission_id,
+        kind: TaskKind::Validation,
+    };
+    let payload = serde_json::to_vec(&worker_task).unwrap();
+    outbox::enqueue(conn, submission_id, &env, &payload).await?;
+
+    Ok(Some(QueuedValidation {
+        submission_id,
+        routing_key: env,
+        payload,
+    }))
+}
+
+/// Whether every testcase of the problem passed its validator.
+pub async fn testcases_validated(
+    conn: &mut PgConnection,
+    problem_id: i64,
+) -> Result<bool, sqlx::Error> {
+    let row = sqlx::query!(
+        r#"SELECT NOT EXISTS (SELECT 1 FROM problem_testcases WHERE problem_id = $1 AND validation <> 'valid') AS "validated!""#,
+        problem_id
+    )
+    .fetch_one(conn)
+    .await?;
+    Ok(row.validated)
+}
+
+#[derive(Serialize)]
+struct Validator {
+    code: Option<String>,
+    env: Option<String>,
+}
+
+/// Shows a problem's input validator.
+#[instrument(skip(session, pg_pool))]
+pub async fn get_validator(
+    session: Session,
+    pg_pool: Data<PgPool>,
+    path: web::Path<(i64,)>,
+) -> Result<HttpResponse, ValidatorError> {
+    let auth =
+        SessionAuth::require(&session, Role::ProblemSetter).ok_or(ValidatorError::Unauthorized)?;
+    let problem_id = path.into_inner().0;
+
+    let mut conn = pg_pool.acquire().await?;
+    ensure_editor::<ValidatorError>(&mut conn, problem_id, &auth).await?;
+    let validator = sqlx::query_as!(
+        Validator,
+        "SELECT validator_code AS code, validator_env AS env FROM problems WHERE problem_id = $1",
+        problem_id
+    )
+    .fetch_one(conn.as_mut())
+    .await?;
+
+    Ok(HttpResponse::Ok().json(validator))
+}
+
+/// Sets a problem's input validator and revalidates all of its testcases.
+#[instrument(skip(request, channel_pool, session, runtimeconfigs, pg_pool))]
+pub async fn set_validator(
+    request: web::Json<ValidatorJson>,
+    path: web::Path<(i64,)>,
+    channel_pool: Data<ChannelPool>,
+    session: Session,
+    runtimeconfigs: Data<RuntimeConfigs>,
+    pg_pool: Data<PgPool>,
+) -> Result<HttpResponse, ValidatorError> {
+    let auth =
+        SessionAuth::require(&session, Role::ProblemSetter).ok_or(ValidatorError::Unauthorized)?;
+    request.validate()?;
+    if !runtimeconfigs.0.contains_key(&request.env) {
+        warn!("Invalid environment: {}", request.env);
+        return Err(ValidatorError::InvalidEnvironment(request.env.clone()));
+    }
+    let problem_id = path.into_inner().0;
+
+    let mut transaction = pg_pool.begin().await?;
+    ensure_editor::<ValidatorError>(&mut transaction, problem_id, &auth).await?;
+    sqlx::query!(
+        "UPDATE problems SET validator_code = $2, validator_env = $3 WHERE problem_id = $1",
+        problem_id,
+        request.code,
+        request.env
+    )
+    .execute(transaction.as_mut())
+    .await?;
+
+    let testcase_ids: Vec<i64> = sqlx::query_scalar!(
+        "SELECT testcase_id FROM problem_testcases WHERE problem_id = $1",
+        problem_id
+    )
+    .fetch_all(transaction.as_mut())
+    .await?;
+    let queued =
+        queue_validation(&mut transaction, problem_id, auth.user_id, &testcase_ids).await?;
+    transaction.commit().await?;
+
+    if let Some(queued) = &queued {
+        queued.dispatch(pg_pool.as_ref(), &channel_pool).await;
+    }
+    info!(
+        "Validator of problem {} set by user_id: {}",
+        problem_id, auth.user_id
+    );
+    Ok(HttpResponse::Ok().json(json!({
+        "submission_id": queued.map(|queued| queued.submission_id)
+    })))
+}
+
+/// Removes a problem's input validator. Its testcases all count as valid again.
+#[instrument(skip(session, pg_pool))]
+pub async fn delete_validator(
+    session: Session,
+    pg_pool: Data<PgPool>,
+    path: web::Path<(i64,)>,
+) -> Result<HttpResponse, ValidatorError> {
+    let auth =
+        SessionAuth::require(&session, Role::ProblemSetter).ok_or(ValidatorError::Unauthorized)?;
+    let problem_id = path.into_inner().0;
+
+    let mut transaction = pg_pool.begin().await?;
+    ensure_editor::<ValidatorError>(&mut transaction, problem_id, &auth).await?;
+    sqlx::query!(
+        "UPDATE problems SET validator_code = NULL, validator_env = NULL WHERE problem_id = $1",
+        problem_id
+    )
+    .execute(transaction.as_mut())
+    .await?;
+    sqlx::query!(
+        "UPDATE problem_testcases SET validation = 'valid', validation_message = NULL WHERE problem_id = $1",
+        problem_id
+    )
+    .execute(transaction.as_mut())
+    .await?;
+    transaction.commit().await?;
+
+    info!(
+        "Validator of problem {} removed by user_id: {}",
+        problem_id, auth.user_id
+    );
+    Ok(HttpResponse::Ok().finish())
+}
@@ -1,7 +1,20 @@
+pub mod access;
+pub mod clarifications;
+pub mod collaborators;
+pub mod contests;
+pub mod courses;
 pub mod create_problem;
+pub mod dead_letters;
+pub mod edit_problem;
+pub mod generators;
+pub mod gradebook;
+pub mod input_validator;
 pub mod login;
+pub mod problem_package;
 pub mod problems;
+pub mod reference;
 pub mod role;
+pub mod scoreboard;
 pub mod session;
 pub mod signup;
 pub mod signup_confirmation;
@@ -9,12 +22,33 @@ pub mod stats;
 pub mod status;
 pub mod submissions;
 pub mod submit;
+pub mod subtasks;
+pub mod tags;
+pub mod teams;
+pub mod testcases;
+pub mod visibility;
 
+pub use clarifications::*;
+pub use collaborators::*;
+pub use contests::*;
+pub use courses::*;
+pub use dead_letters::*;
+pub use edit_problem::*;
+pub use generators::*;
+pub use gradebook::*;
+pub use input_validator::*;
 pub use login::*;
+pub use problem_package::*;
 pub use problems::*;
+pub use reference::*;
+pub use scoreboard::*;
 pub use signup::*;
 pub use signup_confirmation::*;
 pub use stats::*;
 pub use status::*;
 pub use submissions::*;
 pub use submit::*;
+pub use subtasks::*;
+pub use tags::*;
+pub use teams::*;
+pub use testcases::*;
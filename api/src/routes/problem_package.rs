@@ -0,0 +1,218 @@
+use actix_session::Session;
+use actix_web::{
+    HttpResponse, ResponseError,
+    http::header::{ContentDisposition, DispositionParam, DispositionType},
+    web::{self, Bytes, Data},
+};
+use models::{BlobStore, Checker, TestcaseLimits, storage::StorageError};
+use serde::Deserialize;
+use sqlx::PgPool;
+use tracing::{info, instrument};
+
+use crate::package::{
+    PackageError, PackageLimits, PackageTestcase, ProblemPackage, read_package, write_package,
+};
+use crate::routes::{access::problem_access, role::Role, session::SessionAuth};
+
+#[derive(thiserror::Error, Debug)]
+pub enum ProblemPackageError {
+    #[error("{0}")]
+    DatabaseError(#[from] sqlx::Error),
+
+    #[error("{0}")]
+    PackageError(#[from] PackageError),
+
+    #[error("{0}")]
+    ArchiveError(#[from] zip::result::ZipError),
+
+    #[error("{0}")]
+    StorageError(#[from] StorageError),
+
+    #[error("No such problem")]
+    NotFound,
+
+    #[error("Unauthorized")]
+    Unauthorized,
+
+    #[error("Forbidden")]
+    Forbidden,
+}
+
+impl ResponseError for ProblemPackageError {
+    fn error_response(&self) -> HttpResponse<actix_web::body::BoxBody> {
+        match self {
+            Self::PackageError(PackageError::TooLarge(_)) => {
+                HttpResponse::PayloadTooLarge().body(self.to_string())
+            }
+            Self::PackageError(_) => HttpResponse::BadRequest().body(self.to_string()),
+            Self::NotFound => HttpResponse::NotFound().finish(),
+            Self::Unauthorized => HttpResponse::Unauthorized().finish(),
+            Self::Forbidden => HttpResponse::Forbidden().finish(),
+            _ => HttpResponse::InternalServerError().body(self.to_string()),
+        }
+    }
+}
+
+struct PackagedProblem {
+    title: String,
+    difficulty: String,
+    statement: String,
+    time_limit_ms: Option<i32>,
+    memory_limit_mb: Option<i32>,
+    checker: String,
+}
+
+struct PackagedTestcase {
+    testcase: String,
+    output: String,
+    is_sample: bool,
+    input_blob: Option<String>,
+    output_blob: Option<String>,
+}
+
+/// A testcase part's content, read from blob storage when it is kept there.
+async fn packaged_text(
+    storage: &BlobStore,
+    inline: String,
+    blob: Option<String>,
+) -> Result<String, ProblemPackageError> {
+    let Some(hash) = blob else {
+        return Ok(inline);
+    };
+    String::from_utf8(storage.read(&hash).await?).map_err(|_| {
+        PackageError::Unsupported(format!("testcase blob {} is not text", hash)).into()
+    })
+}
+
+/// Downloads a problem with all of its testcases as a `problem.json` package.
+#[instrument(skip(session, pg_pool, storage))]
+pub async fn export_problem_package(
+    session: Session,
+    pg_pool: Data<PgPool>,
+    storage: Data<BlobStore>,
+    path: web::Path<(i64,)>,
+) -> Result<HttpResponse, ProblemPackageError> {
+    let auth = SessionAuth::require(&session, Role::ProblemSetter)
+        .ok_or(ProblemPackageError::Unauthorized)?;
+    let problem_id = path.into_inner().0;
+    match problem_access(pg_pool.as_ref(), problem_id, &auth).await? {
+        None => return Err(ProblemPackageError::NotFound),
+        Some(access) if !access.can_edit() => return Err(ProblemPackageError::Forbidden),
+        Some(_) => {}
+    }
+
+    let problem = sqlx::query_as!(
+        PackagedProblem,
+        "SELECT title, difficulty, statement, time_limit_ms, memory_limit_mb, checker FROM problems WHERE problem_id = $1 AND deleted_at IS NULL",
+        problem_id
+    )
+    .fetch_optional(pg_pool.as_ref())
+    .await?
+    .ok_or(ProblemPackageError::NotFound)?;
+
+    let testcases = sqlx::query_as!(
+        PackagedTestcase,
+        "SELECT testcase, output, is_sample, input_blob, output_blob FROM problem_testcases WHERE problem_id = $1 ORDER BY position, testcase_id",
+        problem_id
+    )
+    .fetch_all(pg_pool.as_ref())
+    .await?;
+    let mut package_testcases = Vec::with_capacity(testcases.len());
+    for testcase in testcases {
+        package_testcases.push(PackageTestcase {
+            input: packaged_text(&storage, testcase.testcase, testcase.input_blob).await?,
+            output: packaged_text(&storage, testcase.output, testcase.output_blob).await?,
+            sample: testcase.is_sample,
+        });
+    }
+
+    let package = ProblemPackage {
+        title: problem.title,
+        difficulty: Some(problem.difficulty),
+        statement: problem.statement,
+        limits: PackageLimits {
+            time_limit_ms: problem.time_limit_ms,
+            memory_limit_mb: problem.memory_limit_mb,
+        },
+        checker: Checker::try_from(problem.checker.as_str()).unwrap_or_default(),
+        testcases: package_testcases,
+    };
+    let archive = write_package(&package)?;
+
+    Ok(HttpResponse::Ok()
+        .content_type("application/zip")
+        .insert_header(ContentDisposition {
+            disposition: DispositionType::Attachment,
+            parameters: vec![DispositionParam::Filename(format!(
+                "problem-{}.zip",
+                problem_id
+            ))],
+        })
+        .body(archive))
+}
+
+#[derive(Deserialize)]
+pub struct ImportParams {
+    /// Used when the package has no difficulty of its own.
+    difficulty: Option<String>,
+}
+
+/// Creates a problem from a native, Kattis or Polygon package.
+#[instrument(skip(session, pg_pool, limits, params, body))]
+pub async fn import_problem_package(
+    session: Session,
+    pg_pool: Data<PgPool>,
+    limits: Data<TestcaseLimits>,
+    params: web::Query<ImportParams>,
+    body: Bytes,
+) -> Result<HttpResponse, ProblemPackageError> {
+    let auth = SessionAuth::require(&session, Role::ProblemSetter)
+        .ok_or(ProblemPackageError::Unauthorized)?;
+
+    let (format, package) = read_package(&body, &limits)?;
+    let difficulty = package
+        .difficulty
+        .or(params.into_inner().difficulty)
+        .unwrap_or_else(|| "easy".into());
+    let checker: &str = package.checker.into();
+
+    let mut transaction = pg_pool.begin().await?;
+    let row = sqlx::query!(
+        "INSERT INTO problems (title, difficulty, statement, time_limit_ms, memory_limit_mb, checker, owner_id) VALUES ($1, $2, $3, $4, $5, $6, $7) RETURNING problem_id",
+        package.title,
+        difficulty,
+        package.statement,
+        package.limits.time_limit_ms,
+        package.limits.memory_limit_mb,
+        checker,
+        auth.user_id
+    )
+    .fetch_one(transaction.as_mut())
+    .await?;
+
+    for (position, testcase) in package.testcases.iter().enumerate() {
+        sqlx::query!(
+            "INSERT INTO problem_testcases (problem_id, testcase, output, is_sample, position) VALUES ($1, $2, $3, $4, $5)",
+            row.problem_id,
+            testcase.input,
+            testcase.output,
+            testcase.sample,
+            position as i32
+        )
+        .execute(transaction.as_mut())
+        .await?;
+    }
+    transaction.commit().await?;
+
+    info!(
+        "Problem {} imported from a {:?} package with {} testcases by user_id: {}",
+        row.problem_id,
+        format,
+        package.testcases.len(),
+        auth.user_id
+    );
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "problem_id": row.problem_id,
+        "format": format,
+    })))
+}
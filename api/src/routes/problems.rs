@@ -1,41 +1,142 @@
 use std::cmp::min;
 
+use actix_session::Session;
 use actix_web::{
     HttpResponse, Responder,
     web::{self, Data},
 };
+use models::ExecStatus;
 use serde::{Deserialize, Serialize};
 use sqlx::PgPool;
 
+use crate::routes::{
+    access::problem_access, contests::hidden_by_contest, role::Role, session::SessionAuth,
+    visibility::Visibility,
+};
+
 #[derive(Serialize)]
 struct Problem {
     problem_id: i64,
     title: String,
     difficulty: String,
     statement: String,
+    visibility: String,
+    tags: Vec<String>,
+    /// Distinct users with a passing submission.
+    solved_count: i64,
+}
+
+#[derive(Serialize)]
+struct Sample {
+    input: String,
+    output: String,
+}
+
+#[derive(Serialize)]
+struct ProblemWithSamples {
+    #[serde(flatten)]
+    problem: Problem,
+    samples: Vec<Sample>,
+}
+
+#[derive(Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SolvedFilter {
+    Solved,
+    Unsolved,
+}
+
+#[derive(Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ProblemSort {
+    Id,
+    Newest,
+    MostSolved,
+    Relevance,
+}
+
+impl From<ProblemSort> for &str {
+    fn from(value: ProblemSort) -> Self {
+        match value {
+            ProblemSort::Id => "id",
+            ProblemSort::Newest => "newest",
+            ProblemSort::MostSolved => "most_solved",
+            ProblemSort::Relevance => "relevance",
+        }
+    }
 }
 
 #[derive(Deserialize)]
-pub struct Pagination {
+pub struct ProblemQuery {
     limit: Option<i64>,
     offset: Option<i64>,
+    difficulty: Option<String>,
+    tag: Option<String>,
+    /// Solved state for the logged in user.
+    status: Option<SolvedFilter>,
+    /// Full-text search over title and statement.
+    q: Option<String>,
+    /// Defaults to `relevance` when searching and `id` otherwise.
+    sort: Option<ProblemSort>,
+    /// Defaults to `published`, other states list the problems the user works on.
+    visibility: Option<Visibility>,
 }
 
 pub async fn list_problems(
     pg_pool: Data<PgPool>,
-    pagination: web::Query<Pagination>,
+    session: Session,
+    query: web::Query<ProblemQuery>,
 ) -> impl Responder {
-    let limit = if let Some(limit) = pagination.limit {
+    let limit = if let Some(limit) = query.limit {
         min(limit, 50)
     } else {
         50
     };
-    let offset = pagination.offset.unwrap_or_default();
+    let offset = query.offset.unwrap_or_default();
+
+    let solved = query.status.map(|status| status == SolvedFilter::Solved);
+    let user_id = match (solved, session.get::<SessionAuth>("auth")) {
+        (None, _) => None,
+        (Some(_), Ok(Some(auth))) => Some(auth.user_id),
+        (Some(_), _) => return HttpResponse::Unauthorized().finish(),
+    };
+    let visibility = query.visibility.unwrap_or(Visibility::Published);
+    // unpublished problems are listed to their owners and collaborators, or to Admins
+    let member_id = if visibility == Visibility::Published {
+        None
+    } else {
+        match session.get::<SessionAuth>("auth") {
+            Ok(Some(auth)) if auth.role >= Role::Admin => None,
+            Ok(Some(auth)) => Some(auth.user_id),
+            _ => return HttpResponse::Unauthorized().finish(),
+        }
+    };
+    let visibility: &str = visibility.into();
+    let search = query.q.as_deref().map(str::trim).filter(|q| !q.is_empty());
+    let sort: &str = query
+        .sort
+        .unwrap_or(if search.is_some() {
+            ProblemSort::Relevance
+        } else {
+            ProblemSort::Id
+        })
+        .into();
+    let pass: &str = ExecStatus::Passed.into();
+
     let rows: Result<_, sqlx::Error> = sqlx::query_as!(
         Problem,
-        "SELECT problem_id, title, difficulty, statement FROM problems ORDER BY problem_id LIMIT $1 OFFSET $2",
+        r#"SELECT p.problem_id, p.title, p.difficulty, p.statement, p.visibility, ARRAY(SELECT t.name FROM problem_tags pt JOIN tags t ON t.tag_id = pt.tag_id WHERE pt.problem_id = p.problem_id ORDER BY t.name) AS "tags!", sc.solved_count AS "solved_count!" FROM problems p CROSS JOIN LATERAL (SELECT COUNT(DISTINCT s.user_id) AS solved_count FROM submit_status s WHERE s.problem_id = p.problem_id AND s.status = $9 AND s.kind = 'submission') sc WHERE p.deleted_at IS NULL AND p.visibility = $10 AND ($10 <> 'published' OR NOT EXISTS (SELECT 1 FROM contest_problems cp JOIN contests ct ON ct.contest_id = cp.contest_id WHERE cp.problem_id = p.problem_id AND ct.starts_at > NOW())) AND ($11::BIGINT IS NULL OR p.owner_id = $11 OR EXISTS (SELECT 1 FROM problem_collaborators c WHERE c.problem_id = p.problem_id AND c.user_id = $11)) AND ($3::TEXT IS NULL OR p.difficulty = $3) AND ($4::TEXT IS NULL OR EXISTS (SELECT 1 FROM problem_tags pt JOIN tags t ON t.tag_id = pt.tag_id WHERE pt.problem_id = p.problem_id AND t.name = $4)) AND ($5::TEXT IS NULL OR p.search_vector @@ websearch_to_tsquery('english', $5)) AND ($6::BOOL IS NULL OR $6 = EXISTS (SELECT 1 FROM submit_status s WHERE s.problem_id = p.problem_id AND s.user_id = $7 AND s.status = $9 AND s.kind = 'submission')) ORDER BY CASE WHEN $8 = 'relevance' THEN ts_rank(p.search_vector, websearch_to_tsquery('english', COALESCE($5, ''))) END DESC, CASE WHEN $8 = 'most_solved' THEN sc.solved_count END DESC, CASE WHEN $8 = 'newest' THEN p.created_at END DESC, p.problem_id LIMIT $1 OFFSET $2"#,
         limit,
-        offset
+        offset,
+        query.difficulty,
+        query.tag.as_deref().map(|tag| tag.trim().to_lowercase()),
+        search,
+        solved,
+        user_id,
+        sort,
+        pass,
+        visibility,
+        member_id
     )
     .fetch_all(pg_pool.as_ref())
     .await;
@@ -46,18 +147,55 @@ pub async fn list_problems(
     }
 }
 
-pub async fn problem(pg_pool: Data<PgPool>, path: web::Path<(i64,)>) -> impl Responder {
+pub async fn problem(
+    pg_pool: Data<PgPool>,
+    session: Session,
+    path: web::Path<(i64,)>,
+) -> impl Responder {
     let problem_id = path.into_inner().0;
+    let pass: &str = ExecStatus::Passed.into();
     let problem: Result<_, sqlx::Error> = sqlx::query_as!(
         Problem,
-        "SELECT problem_id, title, difficulty, statement FROM problems WHERE problem_id = $1",
+        r#"SELECT p.problem_id, p.title, p.difficulty, p.statement, p.visibility, ARRAY(SELECT t.name FROM problem_tags pt JOIN tags t ON t.tag_id = pt.tag_id WHERE pt.problem_id = p.problem_id ORDER BY t.name) AS "tags!", (SELECT COUNT(DISTINCT s.user_id) FROM submit_status s WHERE s.problem_id = p.problem_id AND s.status = $2 AND s.kind = 'submission') AS "solved_count!" FROM problems p WHERE p.problem_id = $1 AND p.deleted_at IS NULL"#,
         problem_id,
+        pass
     )
-    .fetch_one(pg_pool.as_ref())
+    .fetch_optional(pg_pool.as_ref())
+    .await;
+
+    let problem = match problem {
+        Ok(Some(p)) => p,
+        Ok(None) => return HttpResponse::NotFound().finish(),
+        Err(e) => return HttpResponse::InternalServerError().body(e.to_string()),
+    };
+    // drafts, problems in review and problems of upcoming contests are only shown to their
+    // testers and authors
+    let public = Visibility::try_from(problem.visibility.as_str()).is_ok_and(|v| v.is_public())
+        && match hidden_by_contest(pg_pool.as_ref(), problem_id).await {
+            Ok(hidden) => !hidden,
+            Err(e) => return HttpResponse::InternalServerError().body(e.to_string()),
+        };
+    if !public {
+        let Ok(Some(auth)) = session.get::<SessionAuth>("auth") else {
+            return HttpResponse::NotFound().finish();
+        };
+        match problem_access(pg_pool.as_ref(), problem_id, &auth).await {
+            Ok(access) if access.is_some_and(|access| access.can_test()) => {}
+            Ok(_) => return HttpResponse::NotFound().finish(),
+            Err(e) => return HttpResponse::InternalServerError().body(e.to_string()),
+        }
+    }
+
+    let samples: Result<_, sqlx::Error> = sqlx::query_as!(
+        Sample,
+        "SELECT testcase AS input, output FROM problem_testcases WHERE problem_id = $1 AND is_sample AND validation = 'valid' ORDER BY position, testcase_id",
+        problem_id,
+    )
+    .fetch_all(pg_pool.as_ref())
     .await;
 
-    match problem {
-        Ok(p) => HttpResponse::Ok().json(p),
+    match samples {
+        Ok(samples) => HttpResponse::Ok().json(ProblemWithSamples { problem, samples }),
         Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
     }
 }
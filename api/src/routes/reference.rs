@@ -0,0 +1,203 @@
+use actix_session::Session;
+use actix_web::{
+    HttpResponse, ResponseError,
+    web::{self, Data},
+};
+use models::{ExecStatus, RuntimeConfigs, TaskKind, WorkerTask};
+use serde::Serialize;
+use sqlx::{PgConnection, PgPool};
+use tracing::{info, instrument, warn};
+use validator::Validate;
+
+use crate::outbox;
+use crate::pool::ChannelPool;
+use crate::routes::{
+    access::{AccessError, ensure_editor},
+    role::Role,
+    session::SessionAuth,
+};
+
+#[derive(serde::Deserialize, Validate)]
+pub struct ReferenceJson {
+    #[validate(length(min = 1, message = "Code cannot be empty"))]
+    code: String,
+    #[validate(length(min = 1, max = 50, message = "Environment must be specified"))]
+    env: String,
+    /// Replace every expected output with the reference solution's output
+    /// instead of checking against it.
+    #[serde(default)]
+    fill_outputs: bool,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum ReferenceError {
+    #[error("{0}")]
+    DatabaseError(#[from] sqlx::Error),
+
+    #[error("No such environment {0}")]
+    InvalidEnvironment(String),
+
+    #[error("Validation error: {0}")]
+    Validation(#[from] validator::ValidationErrors),
+
+    #[error("No such problem")]
+    NotFound,
+
+    #[error("Unauthorized")]
+    Unauthorized,
+
+    #[error("Forbidden")]
+    Forbidden,
+}
+
+impl ResponseError for ReferenceError {
+    fn error_response(&self) -> HttpResponse<actix_web::body::BoxBody> {
+        match self {
+            Self::InvalidEnvironment(_) | Self::Validation(_) => {
+                HttpResponse::BadRequest().body(self.to_string())
+            }
+            Self::NotFound => HttpResponse::NotFound().finish(),
+            Self::Unauthorized => HttpResponse::Unauthorized().finish(),
+            Self::Forbidden => HttpResponse::Forbidden().finish(),
+            _ => HttpResponse::InternalServerError().body(self.to_string()),
+        }
+    }
+}
+
+impl AccessError for ReferenceError {
+    const NOT_FOUND: Self = Self::NotFound;
+    const FORBIDDEN: Self = Self::Forbidden;
+}
+
+/// Whether the latest reference run passed on the problem's current testcases,
+/// time limit and checker.
+pub async fn reference_verified(
+    conn: &mut PgConnection,
+    problem_id: i64,
+) -> Result<bool, sqlx::Error> {
+    let passed: &str = ExecStatus::Passed.into();
+    let row = sqlx::query!(
+        r#"SELECT EXISTS (SELECT 1 FROM problems p JOIN submit_status s ON s.submission_id = p.reference_submission_id WHERE p.problem_id = $1 AND s.status = $2 AND s.created_at >= p.judging_updated_at AND NOT EXISTS (SELECT 1 FROM problem_testcases t WHERE t.problem_id = p.problem_id AND t.updated_at > s.created_at)) AS "verified!""#,
+        problem_id,
+        passed
+    )
+    .fetch_one(conn)
+    .await?;
+    Ok(row.verified)
+}
+
+/// Stores a problem's reference solution and queues it against every testcase.
+/// The run is polled like any submission through `/{submissionID}/status`.
+#[instrument(skip(request, channel_pool, session, runtimeconfigs, pg_pool))]
+pub async fn submit_reference(
+    request: web::Json<ReferenceJson>,
+    path: web::Path<(i64,)>,
+    channel_pool: Data<ChannelPool>,
+    session: Session,
+    runtimeconfigs: Data<RuntimeConfigs>,
+    pg_pool: Data<PgPool>,
+) -> Result<HttpResponse, ReferenceError> {
+    let auth =
+        SessionAuth::require(&session, Role::ProblemSetter).ok_or(ReferenceError::Unauthorized)?;
+    request.validate()?;
+    if !runtimeconfigs.0.contains_key(&request.env) {
+        warn!("Invalid environment: {}", request.env);
+        return Err(ReferenceError::InvalidEnvironment(request.env.clone()));
+    }
+    let problem_id = path.into_inner().0;
+
+    let mut transaction = pg_pool.begin().await?;
+    ensure_editor::<ReferenceError>(&mut transaction, problem_id, &auth).await?;
+
+    let submission_id = sqlx::query!(
+        "INSERT INTO submit_status (user_id, problem_id, kind) VALUES ($1, $2, 'reference') RETURNING submission_id",
+        auth.user_id,
+        problem_id
+    )
+    .fetch_one(transaction.as_mut())
+    .await?
+    .submission_id;
+
+    sqlx::query!(
+        "UPDATE problems SET reference_code = $2, reference_env = $3, reference_submission_id = $4 WHERE problem_id = $1",
+        problem_id,
+        request.code,
+        request.env,
+        submission_id
+    )
+    .execute(transaction.as_mut())
+    .await?;
+
+    let worker_task = WorkerTask {
+        code: request.code.clone(),
+        problem_id,
+        user_id: auth.user_id,
+        submission_id,
+        kind: TaskKind::Reference {
+            fill_outputs: request.fill_outputs,
+        },
+    };
+    let payload = serde_json::to_vec(&worker_task).unwrap();
+    outbox::enqueue(transaction.as_mut(), submission_id, &request.env, &payload).await?;
+    transaction.commit().await?;
+
+    let dispatched = outbox::dispatch(
+        pg_pool.as_ref(),
+        &channel_pool,
+        submission_id,
+        &request.env,
+        &payload,
+    )
+    .await;
+
+    info!(
+        "Reference solution for problem {} queued as submission {} by user_id: {}",
+        problem_id, submission_id, auth.user_id
+    );
+    Ok(outbox::queued_response(submission_id, dispatched))
+}
+
+#[derive(Serialize)]
+struct Reference {
+    code: Option<String>,
+    env: Option<String>,
+    submission_id: Option<i64>,
+    status: Option<String>,
+    testcase_number: Option<i32>,
+    output: Option<String>,
+    /// The latest run passed on the current testcases, so the problem can be published.
+    verified: bool,
+}
+
+/// Shows a problem's reference solution and the outcome of its latest run.
+#[instrument(skip(session, pg_pool))]
+pub async fn get_reference(
+    session: Session,
+    pg_pool: Data<PgPool>,
+    path: web::Path<(i64,)>,
+) -> Result<HttpResponse, ReferenceError> {
+    let auth =
+        SessionAuth::require(&session, Role::ProblemSetter).ok_or(ReferenceError::Unauthorized)?;
+    let problem_id = path.into_inner().0;
+
+    let mut conn = pg_pool.acquire().await?;
+    ensure_editor::<ReferenceError>(&mut conn, problem_id, &auth).await?;
+
+    let row = sqlx::query!(
+        r#"SELECT p.reference_code, p.reference_env, s.submission_id AS "submission_id?", s.status AS "status?", s.testcase_number, s.output FROM problems p LEFT JOIN submit_status s ON s.submission_id = p.reference_submission_id WHERE p.problem_id = $1"#,
+        problem_id
+    )
+    .fetch_one(conn.as_mut())
+    .await?;
+    let verified = reference_verified(&mut conn, problem_id).await?;
+
+    Ok(HttpResponse::Ok().json(Reference {
+        code: row.reference_code,
+        env: row.reference_env,
+        submission_id: row.submission_id,
+        status: row.status,
+        testcase_number: row.testcase_number,
+        output: row.output,
+        verified,
+    }))
+}
@@ -0,0 +1,268 @@
+use actix_session::Session;
+use actix_web::{
+    HttpResponse,
+    http::header::ContentType,
+    web::{self, Data},
+};
+use models::ExecStatus;
+use sqlx::{PgConnection, PgPool};
+use tracing::{info, instrument};
+
+use crate::routes::{
+    contests::{ContestError, virtual_participation},
+    role::Role,
+    session::SessionAuth,
+};
+use crate::scoreboard::{
+    ContestRules, ContestSubmission, Participant, ScoreboardCache, ScoreboardProblem,
+    build_scoreboard, resolve_order,
+};
+
+struct ScoreboardContest {
+    rules: String,
+    penalty_minutes: i32,
+    owner_id: Option<i64>,
+    started: bool,
+    ended: bool,
+    /// The public scoreboard hides submissions made since the freeze began.
+    frozen: bool,
+}
+
+impl ScoreboardContest {
+    fn rules(&self) -> ContestRules {
+        ContestRules::try_from(self.rules.as_str()).unwrap_or_default()
+    }
+
+    /// The contest's owner and Admins judge it and see live results.
+    fn is_judge(&self, auth: Option<&SessionAuth>) -> bool {
+        auth.is_some_and(|auth| auth.role >= Role::Admin || self.owner_id == Some(auth.user_id))
+    }
+}
+
+async fn scoreboard_contest(
+    conn: &mut PgConnection,
+    contest_id: i64,
+) -> Result<ScoreboardContest, ContestError> {
+    sqlx::query_as!(
+        ScoreboardContest,
+        r#"SELECT rules, penalty_minutes, owner_id, NOW() >= starts_at AS "started!", NOW() >= ends_at AS "ended!", rules = 'icpc' AND freeze_minutes > 0 AND unfrozen_at IS NULL AND NOW() >= ends_at - make_interval(mins => freeze_minutes) AS "frozen!" FROM contests WHERE contest_id = $1"#,
+        contest_id
+    )
+    .fetch_optional(conn)
+    .await?
+    .ok_or(ContestError::NotFound)
+}
+
+struct Standings {
+    problems: Vec<ScoreboardProblem>,
+    participants: Vec<Participant>,
+    submissions: Vec<ContestSubmission>,
+}
+
+async fn load_standings(
+    conn: &mut PgConnection,
+    contest_id: i64,
+) -> Result<Standings, sqlx::Error> {
+    let problems = sqlx::query_as!(
+        ScoreboardProblem,
+        "SELECT problem_id, label FROM contest_problems WHERE contest_id = $1 ORDER BY position",
+        contest_id
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+    let participants = sqlx::query_as!(
+        Participant,
+        r#"SELECT r.user_id AS "user_id?", NULL::BIGINT AS "team_id?", u.username AS "name!" FROM contest_registrations r JOIN users u ON u.user_id = r.user_id WHERE r.contest_id = $1 UNION ALL SELECT NULL, t.team_id, t.name FROM contest_team_registrations tr JOIN teams t ON t.team_id = tr.team_id WHERE tr.contest_id = $1"#,
+        contest_id
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+    let pass: &str = ExecStatus::Passed.into();
+    let submissions = sqlx::query_as!(
+        ContestSubmission,
+        r#"SELECT s.user_id, s.team_id, s.problem_id, s.status, FLOOR(EXTRACT(EPOCH FROM s.created_at - c.starts_at) / 60)::BIGINT AS "minute!", COALESCE(s.score, CASE WHEN s.status = $2 THEN 100 ELSE 0 END) AS "score!", c.freeze_minutes > 0 AND s.created_at >= c.ends_at - make_interval(mins => c.freeze_minutes) AS "frozen!" FROM submit_status s JOIN contests c ON c.contest_id = s.contest_id WHERE s.contest_id = $1 AND s.kind = 'submission' AND NOT s.is_virtual ORDER BY s.created_at, s.submission_id"#,
+        contest_id,
+        pass
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+    Ok(Standings {
+        problems,
+        participants,
+        submissions,
+    })
+}
+
+/// Ranks a contest's participants under its rules. Served from the cache while it
+/// is fresh; before the contest starts only its judges can see it. During an ICPC
+/// freeze everyone else sees submissions made since the freeze as pending.
+pub async fn contest_scoreboard(
+    session: Session,
+    pg_pool: Data<PgPool>,
+    cache: Data<ScoreboardCache>,
+    path: web::Path<(i64,)>,
+) -> Result<HttpResponse, ContestError> {
+    let contest_id = path.into_inner().0;
+    let auth = session.get::<SessionAuth>("auth").ok().flatten();
+
+    let mut conn = pg_pool.acquire().await?;
+    let contest = scoreboard_contest(&mut conn, contest_id).await?;
+    let judge = contest.is_judge(auth.as_ref());
+    if !contest.started && !judge {
+        return Err(ContestError::Conflict("Contest has not started".into()));
+    }
+    let hide_frozen = contest.frozen && !judge;
+    let key = match (contest.frozen, judge) {
+        (true, true) => ScoreboardCache::judge_key(contest_id),
+        (true, false) => ScoreboardCache::frozen_key(contest_id),
+        (false, _) => ScoreboardCache::key(contest_id),
+    };
+    if let Some(cached) = cache.get(&key).await {
+        return Ok(HttpResponse::Ok()
+            .content_type(ContentType::json())
+            .body(cached));
+    }
+
+    let standings = load_standings(&mut conn, contest_id).await?;
+    let scoreboard = build_scoreboard(
+        contest.rules(),
+        contest.penalty_minutes as i64,
+        &standings.problems,
+        &standings.participants,
+        &standings.submissions,
+        hide_frozen,
+    );
+    let body = serde_json::to_string(&scoreboard).unwrap();
+    if contest.started {
+        cache.put(&key, &body).await;
+    }
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::json())
+        .body(body))
+}
+
+/// Lifts the freeze of a finished contest, so everyone sees the final results.
+#[instrument(skip(session, pg_pool, cache))]
+pub async fn unfreeze_scoreboard(
+    session: Session,
+    pg_pool: Data<PgPool>,
+    cache: Data<ScoreboardCache>,
+    path: web::Path<(i64,)>,
+) -> Result<HttpResponse, ContestError> {
+    let auth = SessionAuth::require(&session, Role::User).ok_or(ContestError::Unauthorized)?;
+    let contest_id = path.into_inner().0;
+
+    let mut conn = pg_pool.acquire().await?;
+    let contest = scoreboard_contest(&mut conn, contest_id).await?;
+    if !contest.is_judge(Some(&auth)) {
+        return Err(ContestError::Forbidden);
+    }
+    if !contest.ended {
+        return Err(ContestError::Conflict("Contest has not ended".into()));
+    }
+    sqlx::query!(
+        "UPDATE contests SET unfrozen_at = NOW() WHERE contest_id = $1 AND unfrozen_at IS NULL",
+        contest_id
+    )
+    .execute(conn.as_mut())
+    .await?;
+    cache.invalidate(&ScoreboardCache::key(contest_id)).await;
+
+    info!(
+        "Scoreboard of contest {} unfrozen by user_id: {}",
+        contest_id, auth.user_id
+    );
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Lists the frozen submissions of a finished ICPC contest in the order a resolve
+/// ceremony reveals them, with each participant's rank before and after.
+pub async fn resolve_scoreboard(
+    session: Session,
+    pg_pool: Data<PgPool>,
+    path: web::Path<(i64,)>,
+) -> Result<HttpResponse, ContestError> {
+    let auth = SessionAuth::require(&session, Role::User).ok_or(ContestError::Unauthorized)?;
+    let contest_id = path.into_inner().0;
+
+    let mut conn = pg_pool.acquire().await?;
+    let contest = scoreboard_contest(&mut conn, contest_id).await?;
+    if !contest.is_judge(Some(&auth)) {
+        return Err(ContestError::Forbidden);
+    }
+    if !contest.ended {
+        return Err(ContestError::Conflict("Contest has not ended".into()));
+    }
+    if contest.rules() != ContestRules::Icpc {
+        return Err(ContestError::Conflict(
+            "Only ICPC scoreboards are frozen".into(),
+        ));
+    }
+
+    let standings = load_standings(&mut conn, contest_id).await?;
+    let steps = resolve_order(
+        contest.penalty_minutes as i64,
+        &standings.problems,
+        &standings.participants,
+        &standings.submissions,
+    );
+    Ok(HttpResponse::Ok().json(steps))
+}
+
+/// Ranks the logged in user's virtual participation among the contest's original
+/// participants. While it runs, the original standings are shown as they were at the
+/// same point of the contest; frozen results stay pending until the contest is unfrozen.
+pub async fn virtual_scoreboard(
+    session: Session,
+    pg_pool: Data<PgPool>,
+    path: web::Path<(i64,)>,
+) -> Result<HttpResponse, ContestError> {
+    let auth = SessionAuth::require(&session, Role::User).ok_or(ContestError::Unauthorized)?;
+    let contest_id = path.into_inner().0;
+
+    let mut conn = pg_pool.acquire().await?;
+    let contest = scoreboard_contest(&mut conn, contest_id).await?;
+    if virtual_participation(conn.as_mut(), contest_id, auth.user_id)
+        .await?
+        .is_none()
+    {
+        return Err(ContestError::NotFound);
+    }
+
+    let problems = sqlx::query_as!(
+        ScoreboardProblem,
+        "SELECT problem_id, label FROM contest_problems WHERE contest_id = $1 ORDER BY position",
+        contest_id
+    )
+    .fetch_all(conn.as_mut())
+    .await?;
+    let participants = sqlx::query_as!(
+        Participant,
+        r#"SELECT r.user_id AS "user_id?", NULL::BIGINT AS "team_id?", u.username AS "name!" FROM contest_registrations r JOIN users u ON u.user_id = r.user_id WHERE r.contest_id = $1 AND r.user_id <> $2 UNION ALL SELECT NULL, t.team_id, t.name FROM contest_team_registrations tr JOIN teams t ON t.team_id = tr.team_id WHERE tr.contest_id = $1 AND NOT EXISTS (SELECT 1 FROM team_members m WHERE m.team_id = tr.team_id AND m.user_id = $2) UNION ALL SELECT user_id, NULL, username FROM users WHERE user_id = $2"#,
+        contest_id,
+        auth.user_id
+    )
+    .fetch_all(conn.as_mut())
+    .await?;
+    let pass: &str = ExecStatus::Passed.into();
+    // minutes count from the contest start, or from the user's own start for virtual submissions
+    let submissions = sqlx::query_as!(
+        ContestSubmission,
+        r#"SELECT s.user_id, s.team_id, s.problem_id, s.status, FLOOR(EXTRACT(EPOCH FROM s.created_at - CASE WHEN s.is_virtual THEN v.started_at ELSE c.starts_at END) / 60)::BIGINT AS "minute!", COALESCE(s.score, CASE WHEN s.status = $3 THEN 100 ELSE 0 END) AS "score!", NOT s.is_virtual AND c.freeze_minutes > 0 AND s.created_at >= c.ends_at - make_interval(mins => c.freeze_minutes) AS "frozen!" FROM submit_status s JOIN contests c ON c.contest_id = s.contest_id JOIN virtual_participations v ON v.contest_id = s.contest_id AND v.user_id = $2 WHERE s.contest_id = $1 AND s.kind = 'submission' AND ((NOT s.is_virtual AND s.created_at - c.starts_at <= NOW() - v.started_at) OR (s.is_virtual AND s.user_id = $2)) ORDER BY s.created_at - CASE WHEN s.is_virtual THEN v.started_at ELSE c.starts_at END, s.submission_id"#,
+        contest_id,
+        auth.user_id,
+        pass
+    )
+    .fetch_all(conn.as_mut())
+    .await?;
+
+    let scoreboard = build_scoreboard(
+        contest.rules(),
+        contest.penalty_minutes as i64,
+        &problems,
+        &participants,
+        &submissions,
+        contest.frozen,
+    );
+    Ok(HttpResponse::Ok().json(scoreboard))
+}
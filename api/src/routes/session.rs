@@ -1,3 +1,4 @@
+use actix_session::Session;
 use serde::{Deserialize, Serialize};
 
 use crate::routes::role::Role;
@@ -7,3 +8,13 @@ pub struct SessionAuth {
     pub user_id: i64,
     pub role: Role,
 }
+
+impl SessionAuth {
+    /// Returns the logged in user if their role is at least `role`.
+    pub fn require(session: &Session, role: Role) -> Option<SessionAuth> {
+        match session.get::<SessionAuth>("auth") {
+            Ok(Some(auth)) if auth.role >= role => Some(auth),
+            _ => None,
+        }
+    }
+}
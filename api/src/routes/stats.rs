@@ -21,7 +21,7 @@ pub async fn stats(pg_pool: Data<PgPool>, session: Session) -> impl Responder {
         let pass: &str = ExecStatus::Passed.into();
         let row: Result<_, sqlx::Error> = sqlx::query_as!(
             Stats,
-            "SELECT difficulty, count(DISTINCT s.problem_id) FROM submit_status s INNER JOIN problems p on s.problem_id = p.problem_id WHERE status=$2 AND user_id = $1 GROUP BY difficulty",
+            "SELECT difficulty, count(DISTINCT s.problem_id) FROM submit_status s INNER JOIN problems p on s.problem_id = p.problem_id WHERE status=$2 AND user_id = $1 AND s.kind = 'submission' GROUP BY difficulty",
             user_id,
             pass
         )
@@ -3,10 +3,11 @@ use actix_web::{
     HttpResponse, Responder,
     web::{self, Data},
 };
+use models::SubtaskScore;
 use serde::Serialize;
 use sqlx::PgPool;
 
-use crate::routes::session::SessionAuth;
+use crate::routes::{access::problem_access, session::SessionAuth};
 
 #[derive(Serialize)]
 struct Status {
@@ -14,25 +15,67 @@ struct Status {
     problem_id: i64,
     status: String,
     output: Option<String>,
+    testcase_number: Option<i32>,
+    /// Points earned, once judged.
+    score: Option<i32>,
+    #[serde(skip)]
+    output_hidden: bool,
+}
+
+#[derive(Serialize)]
+struct StatusWithSubtasks {
+    #[serde(flatten)]
+    status: Status,
+    /// Points earned on each of the problem's subtasks.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    subtasks: Vec<SubtaskScore>,
 }
 pub async fn status(
     path: web::Path<(i64,)>,
     pg_pool: Data<PgPool>,
     session: Session,
 ) -> impl Responder {
-    if let Ok(Some(_auth)) = session.get::<SessionAuth>("auth") {
+    if let Ok(Some(auth)) = session.get::<SessionAuth>("auth") {
         let submission_id = path.into_inner().0;
 
         let row: Result<Status, sqlx::Error> = sqlx::query_as!(
             Status,
-            "SELECT user_id,problem_id,status,output from submit_status WHERE submission_id = $1",
+            "SELECT user_id,problem_id,status,output,testcase_number,score,output_hidden from submit_status WHERE submission_id = $1",
             submission_id
         )
         .fetch_one(pg_pool.as_ref())
         .await;
 
         match row {
-            Ok(row) => HttpResponse::Ok().json(row),
+            Ok(mut row) => {
+                let can_edit = match problem_access(pg_pool.as_ref(), row.problem_id, &auth).await {
+                    Ok(access) => access.is_some_and(|access| access.can_edit()),
+                    Err(e) => return HttpResponse::InternalServerError().body(e.to_string()),
+                };
+                // only the author and the problem's editors may follow a submission
+                if row.user_id != auth.user_id && !can_edit {
+                    return HttpResponse::NotFound().finish();
+                }
+                // output on a hidden testcase can echo its input
+                if row.output_hidden && !can_edit {
+                    row.output = None;
+                }
+                let subtasks = sqlx::query_as!(
+                    SubtaskScore,
+                    "SELECT s.subtask AS name, s.points, s.max_points, s.passed FROM submission_subtasks s LEFT JOIN problem_subtasks p ON p.problem_id = $2 AND p.name = s.subtask WHERE s.submission_id = $1 ORDER BY p.position, s.subtask",
+                    submission_id,
+                    row.problem_id
+                )
+                .fetch_all(pg_pool.as_ref())
+                .await;
+                match subtasks {
+                    Ok(subtasks) => HttpResponse::Ok().json(StatusWithSubtasks {
+                        status: row,
+                        subtasks,
+                    }),
+                    Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+                }
+            }
             Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
         }
     } else {
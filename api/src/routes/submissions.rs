@@ -1,17 +1,26 @@
+use std::cmp::min;
+
 use actix_session::Session;
 use actix_web::{
-    HttpResponse, Responder,
+    HttpResponse, Responder, ResponseError,
     web::{self, Data},
 };
 use sqlx::PgPool;
 
-use crate::routes::session::SessionAuth;
+use crate::routes::{
+    access::{AccessDenied, ensure_editor, problem_access},
+    session::SessionAuth,
+};
 
 #[derive(serde::Serialize, serde::Deserialize)]
 pub struct SubmissionId {
     submission_id: i64,
     status: String,
     output: Option<String>,
+    testcase_number: Option<i32>,
+    score: Option<i32>,
+    #[serde(skip)]
+    output_hidden: bool,
 }
 
 pub async fn submissions(
@@ -19,27 +28,93 @@ pub async fn submissions(
     session: Session,
     path: web::Path<(i64,)>,
 ) -> impl Responder {
-    if let Ok(Some(SessionAuth {
-        user_id,
-        role: _role,
-    })) = session.get::<SessionAuth>("auth")
-    {
+    if let Ok(Some(auth)) = session.get::<SessionAuth>("auth") {
         let problem_id = path.into_inner().0;
+        let can_edit = match problem_access(pg_pool.as_ref(), problem_id, &auth).await {
+            Ok(access) => access.is_some_and(|access| access.can_edit()),
+            Err(e) => return HttpResponse::InternalServerError().body(e.to_string()),
+        };
 
         let row: Result<_, sqlx::Error> = sqlx::query_as!(
             SubmissionId,
-            "SELECT submission_id, status, output from submit_status WHERE user_id = $1 AND problem_id = $2",
-            user_id,
+            "SELECT submission_id, status, output, testcase_number, score, output_hidden from submit_status WHERE user_id = $1 AND problem_id = $2 AND kind = 'submission'",
+            auth.user_id,
             problem_id
         )
         .fetch_all(pg_pool.as_ref())
         .await;
 
         match row {
-            Ok(row) => HttpResponse::Ok().json(row),
+            Ok(mut rows) => {
+                if !can_edit {
+                    for row in rows.iter_mut().filter(|row| row.output_hidden) {
+                        row.output = None;
+                    }
+                }
+                HttpResponse::Ok().json(rows)
+            }
             Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
         }
     } else {
         HttpResponse::Unauthorized().finish()
     }
 }
+
+#[derive(serde::Serialize)]
+pub struct ProblemSubmission {
+    submission_id: i64,
+    user_id: i64,
+    status: String,
+    output: Option<String>,
+    testcase_number: Option<i32>,
+    score: Option<i32>,
+    /// `submission`, or `reference` and `validation` for runs of the problem's own programs.
+    kind: String,
+}
+
+#[derive(serde::Deserialize)]
+pub struct ProblemSubmissionsQuery {
+    limit: Option<i64>,
+    offset: Option<i64>,
+}
+
+/// Lists every user's submissions to a problem, newest first, for its owner and co-authors.
+pub async fn problem_submissions(
+    pg_pool: Data<PgPool>,
+    session: Session,
+    path: web::Path<(i64,)>,
+    query: web::Query<ProblemSubmissionsQuery>,
+) -> impl Responder {
+    let Ok(Some(auth)) = session.get::<SessionAuth>("auth") else {
+        return HttpResponse::Unauthorized().finish();
+    };
+    let problem_id = path.into_inner().0;
+    let mut conn = match pg_pool.acquire().await {
+        Ok(conn) => conn,
+        Err(e) => return HttpResponse::InternalServerError().body(e.to_string()),
+    };
+    if let Err(e) = ensure_editor::<AccessDenied>(&mut conn, problem_id, &auth).await {
+        return e.error_response();
+    }
+
+    let limit = if let Some(limit) = query.limit {
+        min(limit, 50)
+    } else {
+        50
+    };
+    let offset = query.offset.unwrap_or_default();
+    let rows: Result<_, sqlx::Error> = sqlx::query_as!(
+        ProblemSubmission,
+        "SELECT submission_id, user_id, status, output, testcase_number, score, kind FROM submit_status WHERE problem_id = $1 ORDER BY submission_id DESC LIMIT $2 OFFSET $3",
+        problem_id,
+        limit,
+        offset
+    )
+    .fetch_all(&mut *conn)
+    .await;
+
+    match rows {
+        Ok(rows) => HttpResponse::Ok().json(rows),
+        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
@@ -6,16 +6,19 @@ use actix_web::{
 use tracing::{error, info, instrument, warn};
 use validator::Validate;
 
-use lapin::{
-    BasicProperties,
-    options::{BasicPublishOptions, ExchangeDeclareOptions},
-    types::FieldTable,
-};
-use models::{RuntimeConfigs, WorkerTask};
-use serde_json::json;
+use models::{RuntimeConfigs, TaskKind, WorkerTask};
 use sqlx::PgPool;
 
-use crate::routes::session::SessionAuth;
+use crate::outbox;
+use crate::pool::ChannelPool;
+use crate::routes::{
+    access::problem_access,
+    contests::{contest_window, hidden_by_contest, virtual_participation},
+    courses::assignment_window,
+    session::SessionAuth,
+    teams::contest_team,
+    visibility::Visibility,
+};
 
 #[derive(serde::Deserialize, Validate)]
 pub struct SubmitJson {
@@ -23,13 +26,17 @@ pub struct SubmitJson {
     code: String,
     #[validate(length(min = 1, max = 50, message = "Environment must be specified"))]
     env: String,
+    /// Submits as part of this contest instead of for practice, or of the user's
+    /// virtual participation once the contest has ended.
+    #[serde(default)]
+    contest_id: Option<i64>,
+    /// Submits as part of this course assignment. Can't be combined with a contest.
+    #[serde(default)]
+    assignment_id: Option<i64>,
 }
 
 #[derive(thiserror::Error, Debug)]
 pub enum SubmitError {
-    #[error("{0}")]
-    QueueError(#[from] lapin::Error),
-
     #[error("{0}")]
     DatabaseError(#[from] sqlx::Error),
 
@@ -45,17 +52,16 @@ impl ResponseError for SubmitError {
         match self {
             Self::InvalidEnvironment(env) => HttpResponse::BadRequest().body(env.clone()),
             Self::Validation(e) => HttpResponse::BadRequest().body(e.to_string()),
-            Self::QueueError(e) => HttpResponse::InternalServerError().body(e.to_string()),
             Self::DatabaseError(e) => HttpResponse::InternalServerError().body(e.to_string()),
         }
     }
 }
 
-#[instrument(skip(request, conn, session, runtimeconfigs, pg_pool), fields(user_id = tracing::field::Empty))]
+#[instrument(skip(request, channel_pool, session, runtimeconfigs, pg_pool), fields(user_id = tracing::field::Empty))]
 pub async fn submit_problem(
     request: web::Json<SubmitJson>,
     path: web::Path<(i64,)>,
-    conn: Data<lapin::Connection>,
+    channel_pool: Data<ChannelPool>,
     session: Session,
     runtimeconfigs: Data<RuntimeConfigs>,
     pg_pool: Data<PgPool>,
@@ -83,14 +89,124 @@ pub async fn submit_problem(
             problem_id, auth.user_id, request.env
         );
 
-        let channel = conn.create_channel().await?;
+        let mut transaction = pg_pool.begin().await?;
+
+        let mut is_virtual = false;
+        let mut team_id = None;
+        if request.contest_id.is_some() && request.assignment_id.is_some() {
+            return Ok(HttpResponse::BadRequest().body("Submit to a contest or an assignment"));
+        }
+        if let Some(assignment_id) = request.assignment_id {
+            // assignment problems take submissions from the course's members while it is open,
+            // whatever their visibility
+            let window = assignment_window(transaction.as_mut(), assignment_id).await?;
+            let entry = sqlx::query!(
+                r#"SELECT EXISTS (SELECT 1 FROM assignment_problems ap JOIN problems p ON p.problem_id = ap.problem_id WHERE ap.assignment_id = $1 AND ap.problem_id = $2 AND p.deleted_at IS NULL) AS "in_assignment!", EXISTS (SELECT 1 FROM assignments a JOIN course_members m ON m.course_id = a.course_id WHERE a.assignment_id = $1 AND m.user_id = $3) AS "member!""#,
+                assignment_id,
+                problem_id,
+                auth.user_id
+            )
+            .fetch_one(transaction.as_mut())
+            .await?;
+            let Some(window) = window.filter(|window| entry.member && window.opened) else {
+                warn!(
+                    "Submission to assignment {} by non-member user_id: {}",
+                    assignment_id, auth.user_id
+                );
+                return Ok(HttpResponse::NotFound().body("No such assignment"));
+            };
+            if !entry.in_assignment {
+                warn!(
+                    "Submission for problem_id: {} outside assignment {}",
+                    problem_id, assignment_id
+                );
+                return Ok(HttpResponse::NotFound().body("No such assignment problem"));
+            }
+            if window.closed {
+                warn!("Submission to closed assignment {}", assignment_id);
+                return Ok(HttpResponse::Forbidden().body("Assignment is closed"));
+            }
+        } else if let Some(contest_id) = request.contest_id {
+            // contest problems take submissions from registered users while the contest runs,
+            // whatever their visibility
+            let entry = sqlx::query!(
+                r#"SELECT EXISTS (SELECT 1 FROM contest_problems cp JOIN problems p ON p.problem_id = cp.problem_id WHERE cp.contest_id = $1 AND cp.problem_id = $2 AND p.deleted_at IS NULL) AS "in_contest!", EXISTS (SELECT 1 FROM contest_registrations WHERE contest_id = $1 AND user_id = $3) AS "registered!""#,
+                contest_id,
+                problem_id,
+                auth.user_id
+            )
+            .fetch_one(transaction.as_mut())
+            .await?;
+            let window = contest_window(transaction.as_mut(), contest_id).await?;
+            let Some(window) = window.filter(|_| entry.in_contest) else {
+                warn!(
+                    "Submission for problem_id: {} outside contest {}",
+                    problem_id, contest_id
+                );
+                return Ok(HttpResponse::NotFound().body("No such contest problem"));
+            };
+            // after the contest, only a running virtual participation takes submissions
+            let virtual_running = window.ended
+                && virtual_participation(transaction.as_mut(), contest_id, auth.user_id)
+                    .await?
+                    .is_some_and(|participation| participation.running);
+            if !window.ended {
+                // members of a registered team submit for it
+                team_id = contest_team(transaction.as_mut(), contest_id, auth.user_id).await?;
+            }
+            match window.admits(entry.registered || team_id.is_some(), virtual_running) {
+                Ok(virtual_entry) => is_virtual = virtual_entry,
+                Err(refused) => {
+                    warn!("Submission to contest {} refused: {}", contest_id, refused);
+                    return Ok(HttpResponse::Forbidden().body(refused.to_string()));
+                }
+            }
+        } else {
+            let problem = sqlx::query!(
+                "SELECT visibility FROM problems WHERE problem_id = $1 AND deleted_at IS NULL",
+                problem_id
+            )
+            .fetch_optional(transaction.as_mut())
+            .await?;
+
+            let visibility = match problem.map(|p| Visibility::try_from(p.visibility.as_str())) {
+                Some(Ok(visibility)) => visibility,
+                _ => {
+                    warn!("Submission for missing problem_id: {}", problem_id);
+                    return Ok(HttpResponse::NotFound().body("No such problem"));
+                }
+            };
+            // testers may try drafts and problems of upcoming contests,
+            // archived problems no longer take submissions
+            let hidden = match visibility {
+                Visibility::Published => {
+                    hidden_by_contest(transaction.as_mut(), problem_id).await?
+                }
+                Visibility::Archived => {
+                    warn!("Submission for archived problem_id: {}", problem_id);
+                    return Ok(HttpResponse::Forbidden().body("Problem is archived"));
+                }
+                Visibility::Draft | Visibility::Review => true,
+            };
+            if hidden {
+                let access = problem_access(transaction.as_mut(), problem_id, &auth).await?;
+                if !access.is_some_and(|access| access.can_test()) {
+                    warn!("Submission for unpublished problem_id: {}", problem_id);
+                    return Ok(HttpResponse::NotFound().body("No such problem"));
+                }
+            }
+        }
 
         let submission_id = sqlx::query!(
-            r#"INSERT INTO submit_status (user_id, problem_id) VALUES ($1,$2) RETURNING submission_id"#,
+            r#"INSERT INTO submit_status (user_id, problem_id, contest_id, is_virtual, team_id, assignment_id) VALUES ($1,$2,$3,$4,$5,$6) RETURNING submission_id"#,
             auth.user_id,
             problem_id,
+            request.contest_id,
+            is_virtual,
+            team_id,
+            request.assignment_id,
         )
-        .fetch_one(pg_pool.as_ref())
+        .fetch_one(transaction.as_mut())
         .await
         .map_err(|e| {
             error!("Failed to create submission record: {}", e);
@@ -104,43 +220,33 @@ pub async fn submit_problem(
             problem_id,
             user_id: auth.user_id,
             submission_id,
+            kind: TaskKind::Submission,
         };
 
-        channel
-            .exchange_declare(
-                "code",
-                lapin::ExchangeKind::Direct,
-                ExchangeDeclareOptions::default(),
-                FieldTable::default(),
-            )
-            .await
-            .map_err(|e| {
-                error!("Failed to declare exchange: {}", e);
-                SubmitError::QueueError(e)
-            })?;
+        let payload = serde_json::to_vec(&worker_task).unwrap();
 
-        channel
-            .basic_publish(
-                "code",
-                &request.env,
-                BasicPublishOptions::default(),
-                serde_json::to_vec(&worker_task).unwrap().as_ref(),
-                BasicProperties::default(),
-            )
+        // keep the task in the outbox so the relay can retry if publishing below fails
+        outbox::enqueue(transaction.as_mut(), submission_id, &request.env, &payload)
             .await
             .map_err(|e| {
-                error!("Failed to publish to queue: {}", e);
-                SubmitError::QueueError(e)
+                error!("Failed to write submission to outbox: {}", e);
+                SubmitError::DatabaseError(e)
             })?;
 
-        info!(
-            "Submission published to queue successfully. submission_id: {}",
-            submission_id
-        );
+        transaction.commit().await?;
+
+        let dispatched = outbox::dispatch(
+            pg_pool.as_ref(),
+            &channel_pool,
+            submission_id,
+            &request.env,
+            &payload,
+        )
+        .await;
+
+        info!("Submission {} queued", submission_id);
 
-        Ok(HttpResponse::Ok().json(json!({
-            "submission_id" : submission_id
-        })))
+        Ok(outbox::queued_response(submission_id, dispatched))
     } else {
         warn!("Unauthorized submission attempt");
         Ok(HttpResponse::Unauthorized().finish())
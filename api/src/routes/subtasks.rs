@@ -0,0 +1,209 @@
+use std::collections::HashSet;
+
+use actix_session::Session;
+use actix_web::{
+    HttpResponse, ResponseError,
+    web::{self, Data},
+};
+use models::{Subtask, scoring::check_subtask_dependencies};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use tracing::{info, instrument, warn};
+
+use crate::generator::valid_generator_name;
+use crate::routes::{
+    access::{AccessError, ensure_editor},
+    role::Role,
+    session::SessionAuth,
+};
+
+#[derive(thiserror::Error, Debug)]
+pub enum SubtaskError {
+    #[error("{0}")]
+    DatabaseError(#[from] sqlx::Error),
+
+    #[error("{0}")]
+    Invalid(String),
+
+    #[error("No such problem")]
+    NotFound,
+
+    #[error("Unauthorized")]
+    Unauthorized,
+
+    #[error("Forbidden")]
+    Forbidden,
+}
+
+impl ResponseError for SubtaskError {
+    fn error_response(&self) -> HttpResponse<actix_web::body::BoxBody> {
+        match self {
+            Self::DatabaseError(e) => HttpResponse::InternalServerError().body(e.to_string()),
+            Self::Invalid(_) => HttpResponse::BadRequest().body(self.to_string()),
+            Self::NotFound => HttpResponse::NotFound().finish(),
+            Self::Unauthorized => HttpResponse::Unauthorized().finish(),
+            Self::Forbidden => HttpResponse::Forbidden().finish(),
+        }
+    }
+}
+
+impl AccessError for SubtaskError {
+    const NOT_FOUND: Self = Self::NotFound;
+    const FORBIDDEN: Self = Self::Forbidden;
+}
+
+fn default_skip_on_failure() -> bool {
+    true
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct SubtaskJson {
+    name: String,
+    points: i32,
+    #[serde(default)]
+    depends_on: Vec<String>,
+    #[serde(default = "default_skip_on_failure")]
+    skip_on_failure: bool,
+    /// Testcases of the subtask, by id.
+    #[serde(default)]
+    testcases: Vec<i64>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct SubtasksJson {
+    subtasks: Vec<SubtaskJson>,
+}
+
+/// Lists a problem's subtasks in order, each with its testcases.
+#[instrument(skip(session, pg_pool))]
+pub async fn list_subtasks(
+    session: Session,
+    pg_pool: Data<PgPool>,
+    path: web::Path<(i64,)>,
+) -> Result<HttpResponse, SubtaskError> {
+    let auth =
+        SessionAuth::require(&session, Role::ProblemSetter).ok_or(SubtaskError::Unauthorized)?;
+    let problem_id = path.into_inner().0;
+
+    let mut conn = pg_pool.acquire().await?;
+    ensure_editor::<SubtaskError>(&mut conn, problem_id, &auth).await?;
+    let subtasks = sqlx::query_as!(
+        SubtaskJson,
+        r#"SELECT s.name, s.points, s.depends_on, s.skip_on_failure, ARRAY(SELECT t.testcase_id FROM problem_testcases t WHERE t.problem_id = s.problem_id AND t.subtask = s.name ORDER BY t.position, t.testcase_id) AS "testcases!" FROM problem_subtasks s WHERE s.problem_id = $1 ORDER BY s.position"#,
+        problem_id
+    )
+    .fetch_all(conn.as_mut())
+    .await?;
+
+    Ok(HttpResponse::Ok().json(SubtasksJson { subtasks }))
+}
+
+fn check_subtasks(subtasks: &[SubtaskJson]) -> Result<(), String> {
+    let mut names = HashSet::new();
+    let mut testcases = HashSet::new();
+    for subtask in subtasks {
+        if !valid_generator_name(&subtask.name) || subtask.name.len() > 50 {
+            return Err(format!(
+                "Invalid subtask name {}: use up to 50 letters, digits, _ and -",
+                subtask.name
+            ));
+        }
+        if !names.insert(subtask.name.as_str()) {
+            return Err(format!("Subtask {} is listed twice", subtask.name));
+        }
+        if subtask.points < 0 {
+            return Err(format!("Subtask {} has negative points", subtask.name));
+        }
+        if let Some(testcase_id) = subtask.testcases.iter().find(|id| !testcases.insert(**id)) {
+            return Err(format!(
+                "Testcase {} is in more than one subtask",
+                testcase_id
+            ));
+        }
+    }
+    let subtasks: Vec<Subtask> = subtasks
+        .iter()
+        .map(|subtask| Subtask {
+            name: subtask.name.clone(),
+            points: subtask.points,
+            depends_on: subtask.depends_on.clone(),
+            skip_on_failure: subtask.skip_on_failure,
+        })
+        .collect();
+    check_subtask_dependencies(&subtasks)
+}
+
+/// Replaces a problem's subtasks and which testcases belong to each. Testcases
+/// left out of every subtask are still judged but are worth no points.
+#[instrument(skip(session, pg_pool, body))]
+pub async fn set_subtasks(
+    session: Session,
+    pg_pool: Data<PgPool>,
+    path: web::Path<(i64,)>,
+    body: web::Json<SubtasksJson>,
+) -> Result<HttpResponse, SubtaskError> {
+    let auth =
+        SessionAuth::require(&session, Role::ProblemSetter).ok_or(SubtaskError::Unauthorized)?;
+    let problem_id = path.into_inner().0;
+    let subtasks = body.into_inner().subtasks;
+    check_subtasks(&subtasks).map_err(SubtaskError::Invalid)?;
+
+    let mut transaction = pg_pool.begin().await?;
+    ensure_editor::<SubtaskError>(&mut transaction, problem_id, &auth).await?;
+
+    sqlx::query!(
+        "UPDATE problem_testcases SET subtask = NULL WHERE problem_id = $1 AND subtask IS NOT NULL",
+        problem_id
+    )
+    .execute(transaction.as_mut())
+    .await?;
+    sqlx::query!(
+        "DELETE FROM problem_subtasks WHERE problem_id = $1",
+        problem_id
+    )
+    .execute(transaction.as_mut())
+    .await?;
+
+    for (position, subtask) in subtasks.iter().enumerate() {
+        sqlx::query!(
+            "INSERT INTO problem_subtasks (problem_id, name, position, points, depends_on, skip_on_failure) VALUES ($1, $2, $3, $4, $5, $6)",
+            problem_id,
+            subtask.name,
+            position as i32,
+            subtask.points,
+            &subtask.depends_on,
+            subtask.skip_on_failure
+        )
+        .execute(transaction.as_mut())
+        .await?;
+        if subtask.testcases.is_empty() {
+            continue;
+        }
+        let assigned = sqlx::query!(
+            "UPDATE problem_testcases SET subtask = $2 WHERE problem_id = $1 AND testcase_id = ANY($3)",
+            problem_id,
+            subtask.name,
+            &subtask.testcases
+        )
+        .execute(transaction.as_mut())
+        .await?
+        .rows_affected() as usize;
+        if assigned != subtask.testcases.len() {
+            warn!(
+                "Rejected subtasks for problem {}: {} lists testcases of another problem",
+                problem_id, subtask.name
+            );
+            return Err(SubtaskError::Invalid(format!(
+                "Subtask {} lists testcases that are not part of the problem",
+                subtask.name
+            )));
+        }
+    }
+    transaction.commit().await?;
+
+    info!(
+        "Subtasks of problem {} set by user_id: {}",
+        problem_id, auth.user_id
+    );
+    Ok(HttpResponse::Ok().finish())
+}
@@ -0,0 +1,133 @@
+use actix_session::Session;
+use actix_web::{
+    HttpResponse, Responder, ResponseError,
+    web::{self, Data},
+};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use tracing::info;
+
+use crate::routes::{
+    access::{AccessDenied, ensure_editor},
+    role::Role,
+    session::SessionAuth,
+    visibility::Visibility,
+};
+
+const MAX_TAG_LENGTH: usize = 32;
+
+#[derive(Serialize)]
+struct Tag {
+    name: String,
+    problem_count: i64,
+}
+
+/// Lists every tag in use with the number of published problems carrying it.
+pub async fn list_tags(pg_pool: Data<PgPool>) -> impl Responder {
+    let published: &str = Visibility::Published.into();
+    let rows: Result<_, sqlx::Error> = sqlx::query_as!(
+        Tag,
+        r#"SELECT t.name, COUNT(p.problem_id) AS "problem_count!" FROM tags t JOIN problem_tags pt ON pt.tag_id = t.tag_id JOIN problems p ON p.problem_id = pt.problem_id AND p.deleted_at IS NULL AND p.visibility = $1 GROUP BY t.name ORDER BY t.name"#,
+        published
+    )
+    .fetch_all(pg_pool.as_ref())
+    .await;
+
+    match rows {
+        Ok(rows) => HttpResponse::Ok().json(rows),
+        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ProblemTags {
+    tags: Vec<String>,
+}
+
+/// Replaces the tags of a problem. Tags are lowercased and created on first use.
+pub async fn set_problem_tags(
+    pg_pool: Data<PgPool>,
+    session: Session,
+    path: web::Path<(i64,)>,
+    body: web::Json<ProblemTags>,
+) -> impl Responder {
+    let Some(session_auth) = SessionAuth::require(&session, Role::ProblemSetter) else {
+        return HttpResponse::Unauthorized().finish();
+    };
+    let problem_id = path.into_inner().0;
+
+    let mut tags: Vec<String> = body
+        .tags
+        .iter()
+        .map(|tag| tag.trim().to_lowercase())
+        .filter(|tag| !tag.is_empty())
+        .collect();
+    tags.sort();
+    tags.dedup();
+    if let Some(tag) = tags.iter().find(|tag| tag.len() > MAX_TAG_LENGTH) {
+        return HttpResponse::BadRequest().body(format!(
+            "Tag {} is longer than {} bytes",
+            tag, MAX_TAG_LENGTH
+        ));
+    }
+
+    let mut transaction = match pg_pool.begin().await {
+        Ok(transaction) => transaction,
+        Err(e) => return HttpResponse::InternalServerError().body(e.to_string()),
+    };
+    if let Err(e) = ensure_editor::<AccessDenied>(&mut transaction, problem_id, &session_auth).await
+    {
+        return e.error_response();
+    }
+
+    let replaced: Result<_, sqlx::Error> = async {
+        let problem = sqlx::query!(
+            "SELECT problem_id FROM problems WHERE problem_id = $1 AND deleted_at IS NULL FOR UPDATE",
+            problem_id
+        )
+        .fetch_optional(transaction.as_mut())
+        .await?;
+        if problem.is_none() {
+            return Ok(false);
+        }
+
+        sqlx::query!(
+            "INSERT INTO tags (name) SELECT UNNEST($1::TEXT[]) ON CONFLICT (name) DO NOTHING",
+            &tags
+        )
+        .execute(transaction.as_mut())
+        .await?;
+        sqlx::query!(
+            "DELETE FROM problem_tags WHERE problem_id = $1",
+            problem_id
+        )
+        .execute(transaction.as_mut())
+        .await?;
+        sqlx::query!(
+            "INSERT INTO problem_tags (problem_id, tag_id) SELECT $1, tag_id FROM tags WHERE name = ANY($2)",
+            problem_id,
+            &tags
+        )
+        .execute(transaction.as_mut())
+        .await?;
+        Ok(true)
+    }
+    .await;
+
+    match replaced {
+        Ok(true) => {}
+        Ok(false) => return HttpResponse::NotFound().finish(),
+        Err(e) => return HttpResponse::InternalServerError().body(e.to_string()),
+    }
+
+    match transaction.commit().await {
+        Ok(_) => {
+            info!(
+                "Problem {} tagged {:?} by user_id: {}",
+                problem_id, tags, session_auth.user_id
+            );
+            HttpResponse::Ok().json(tags)
+        }
+        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
@@ -0,0 +1,372 @@
+use actix_session::Session;
+use actix_web::{
+    HttpResponse, ResponseError,
+    web::{self, Data},
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::{PgConnection, PgPool};
+use tracing::{info, instrument};
+use validator::Validate;
+
+use crate::routes::{role::Role, session::SessionAuth};
+
+#[derive(Deserialize, Validate)]
+pub struct TeamJson {
+    #[validate(length(min = 1, max = 50, message = "Name must be 1 to 50 characters"))]
+    name: String,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum TeamError {
+    #[error("{0}")]
+    DatabaseError(#[from] sqlx::Error),
+
+    #[error("Validation error: {0}")]
+    Validation(#[from] validator::ValidationErrors),
+
+    #[error("{0}")]
+    Invalid(String),
+
+    #[error("{0}")]
+    Conflict(String),
+
+    #[error("No such team")]
+    NotFound,
+
+    #[error("Unauthorized")]
+    Unauthorized,
+
+    #[error("Forbidden")]
+    Forbidden,
+}
+
+impl ResponseError for TeamError {
+    fn error_response(&self) -> HttpResponse<actix_web::body::BoxBody> {
+        match self {
+            Self::Validation(_) | Self::Invalid(_) => {
+                HttpResponse::BadRequest().body(self.to_string())
+            }
+            Self::Conflict(e) => HttpResponse::Conflict().body(e.clone()),
+            Self::NotFound => HttpResponse::NotFound().finish(),
+            Self::Unauthorized => HttpResponse::Unauthorized().finish(),
+            Self::Forbidden => HttpResponse::Forbidden().finish(),
+            Self::DatabaseError(e) => HttpResponse::InternalServerError().body(e.to_string()),
+        }
+    }
+}
+
+/// The team through which the user takes part in a contest, if any.
+pub async fn contest_team<'e, E: sqlx::PgExecutor<'e>>(
+    executor: E,
+    contest_id: i64,
+    user_id: i64,
+) -> Result<Option<i64>, sqlx::Error> {
+    sqlx::query_scalar!(
+        "SELECT tr.team_id FROM contest_team_registrations tr JOIN team_members m ON m.team_id = tr.team_id WHERE tr.contest_id = $1 AND m.user_id = $2",
+        contest_id,
+        user_id
+    )
+    .fetch_optional(executor)
+    .await
+}
+
+async fn team_captain(conn: &mut PgConnection, team_id: i64) -> Result<i64, TeamError> {
+    sqlx::query_scalar!("SELECT captain_id FROM teams WHERE team_id = $1", team_id)
+        .fetch_optional(conn)
+        .await?
+        .ok_or(TeamError::NotFound)
+}
+
+/// Creates a team captained by the logged in user, its first member.
+#[instrument(skip(session, pg_pool, team))]
+pub async fn create_team(
+    session: Session,
+    pg_pool: Data<PgPool>,
+    team: web::Json<TeamJson>,
+) -> Result<HttpResponse, TeamError> {
+    let auth = SessionAuth::require(&session, Role::User).ok_or(TeamError::Unauthorized)?;
+    team.validate()?;
+
+    let mut transaction = pg_pool.begin().await?;
+    let team_id = sqlx::query_scalar!(
+        "INSERT INTO teams (name, captain_id) VALUES ($1, $2) ON CONFLICT (name) DO NOTHING RETURNING team_id",
+        team.name.trim(),
+        auth.user_id
+    )
+    .fetch_optional(transaction.as_mut())
+    .await?
+    .ok_or_else(|| TeamError::Conflict("Team name is taken".into()))?;
+    sqlx::query!(
+        "INSERT INTO team_members (team_id, user_id) VALUES ($1, $2)",
+        team_id,
+        auth.user_id
+    )
+    .execute(transaction.as_mut())
+    .await?;
+    transaction.commit().await?;
+
+    info!("Team {} created by user_id: {}", team_id, auth.user_id);
+    Ok(HttpResponse::Ok().json(json!({ "team_id": team_id })))
+}
+
+#[derive(Serialize)]
+struct TeamSummary {
+    team_id: i64,
+    name: String,
+    captain_id: i64,
+}
+
+#[derive(Serialize)]
+struct MyTeams {
+    teams: Vec<TeamSummary>,
+    /// Teams the user has been invited to.
+    invitations: Vec<TeamSummary>,
+}
+
+/// Lists the logged in user's teams and pending invitations.
+pub async fn my_teams(session: Session, pg_pool: Data<PgPool>) -> Result<HttpResponse, TeamError> {
+    let auth = SessionAuth::require(&session, Role::User).ok_or(TeamError::Unauthorized)?;
+
+    let mut conn = pg_pool.acquire().await?;
+    let teams = sqlx::query_as!(
+        TeamSummary,
+        "SELECT t.team_id, t.name, t.captain_id FROM teams t JOIN team_members m ON m.team_id = t.team_id WHERE m.user_id = $1 ORDER BY t.name",
+        auth.user_id
+    )
+    .fetch_all(conn.as_mut())
+    .await?;
+    let invitations = sqlx::query_as!(
+        TeamSummary,
+        "SELECT t.team_id, t.name, t.captain_id FROM teams t JOIN team_invitations i ON i.team_id = t.team_id WHERE i.user_id = $1 ORDER BY i.created_at",
+        auth.user_id
+    )
+    .fetch_all(conn.as_mut())
+    .await?;
+
+    Ok(HttpResponse::Ok().json(MyTeams { teams, invitations }))
+}
+
+#[derive(Serialize)]
+struct TeamMember {
+    user_id: i64,
+    username: String,
+}
+
+#[derive(Serialize)]
+struct TeamDetail {
+    team_id: i64,
+    name: String,
+    captain_id: i64,
+    members: Vec<TeamMember>,
+    /// Pending invitations, shown to the captain only.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    invited: Vec<TeamMember>,
+}
+
+/// Shows a team and its members.
+pub async fn get_team(
+    session: Session,
+    pg_pool: Data<PgPool>,
+    path: web::Path<(i64,)>,
+) -> Result<HttpResponse, TeamError> {
+    let auth = session.get::<SessionAuth>("auth").ok().flatten();
+    let team_id = path.into_inner().0;
+
+    let mut conn = pg_pool.acquire().await?;
+    let team = sqlx::query_as!(
+        TeamSummary,
+        "SELECT team_id, name, captain_id FROM teams WHERE team_id = $1",
+        team_id
+    )
+    .fetch_optional(conn.as_mut())
+    .await?
+    .ok_or(TeamError::NotFound)?;
+    let members = sqlx::query_as!(
+        TeamMember,
+        "SELECT m.user_id, u.username FROM team_members m JOIN users u ON u.user_id = m.user_id WHERE m.team_id = $1 ORDER BY m.joined_at",
+        team_id
+    )
+    .fetch_all(conn.as_mut())
+    .await?;
+    let invited = if auth.is_some_and(|auth| auth.user_id == team.captain_id) {
+        sqlx::query_as!(
+            TeamMember,
+            "SELECT i.user_id, u.username FROM team_invitations i JOIN users u ON u.user_id = i.user_id WHERE i.team_id = $1 ORDER BY i.created_at",
+            team_id
+        )
+        .fetch_all(conn.as_mut())
+        .await?
+    } else {
+        Vec::new()
+    };
+
+    Ok(HttpResponse::Ok().json(TeamDetail {
+        team_id: team.team_id,
+        name: team.name,
+        captain_id: team.captain_id,
+        members,
+        invited,
+    }))
+}
+
+/// Invites a user to the team.
+#[instrument(skip(session, pg_pool))]
+pub async fn invite_to_team(
+    session: Session,
+    pg_pool: Data<PgPool>,
+    path: web::Path<(i64, i64)>,
+) -> Result<HttpResponse, TeamError> {
+    let auth = SessionAuth::require(&session, Role::User).ok_or(TeamError::Unauthorized)?;
+    let (team_id, user_id) = path.into_inner();
+
+    let mut conn = pg_pool.acquire().await?;
+    if team_captain(&mut conn, team_id).await? != auth.user_id {
+        return Err(TeamError::Forbidden);
+    }
+    let invitee = sqlx::query!(
+        r#"SELECT EXISTS (SELECT 1 FROM users WHERE user_id = $2) AS "exists!", EXISTS (SELECT 1 FROM team_members WHERE team_id = $1 AND user_id = $2) AS "member!""#,
+        team_id,
+        user_id
+    )
+    .fetch_one(conn.as_mut())
+    .await?;
+    if !invitee.exists {
+        return Err(TeamError::Invalid("No such user".into()));
+    }
+    if invitee.member {
+        return Err(TeamError::Conflict("Already a member of the team".into()));
+    }
+    sqlx::query!(
+        "INSERT INTO team_invitations (team_id, user_id, invited_by) VALUES ($1, $2, $3) ON CONFLICT DO NOTHING",
+        team_id,
+        user_id,
+        auth.user_id
+    )
+    .execute(conn.as_mut())
+    .await?;
+
+    info!(
+        "User {} invited to team {} by user_id: {}",
+        user_id, team_id, auth.user_id
+    );
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Withdraws an invitation: the captain revokes it, or the invited user declines it.
+#[instrument(skip(session, pg_pool))]
+pub async fn withdraw_team_invitation(
+    session: Session,
+    pg_pool: Data<PgPool>,
+    path: web::Path<(i64, i64)>,
+) -> Result<HttpResponse, TeamError> {
+    let auth = SessionAuth::require(&session, Role::User).ok_or(TeamError::Unauthorized)?;
+    let (team_id, user_id) = path.into_inner();
+
+    let mut conn = pg_pool.acquire().await?;
+    if user_id != auth.user_id && team_captain(&mut conn, team_id).await? != auth.user_id {
+        return Err(TeamError::Forbidden);
+    }
+    let res = sqlx::query!(
+        "DELETE FROM team_invitations WHERE team_id = $1 AND user_id = $2",
+        team_id,
+        user_id
+    )
+    .execute(conn.as_mut())
+    .await?;
+    if res.rows_affected() == 0 {
+        return Err(TeamError::NotFound);
+    }
+
+    info!(
+        "Invitation of user {} to team {} withdrawn by user_id: {}",
+        user_id, team_id, auth.user_id
+    );
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Accepts an invitation to the team. Refused when it would put the user in a
+/// contest twice: alone and with the team, or with two teams.
+#[instrument(skip(session, pg_pool))]
+pub async fn join_team(
+    session: Session,
+    pg_pool: Data<PgPool>,
+    path: web::Path<(i64,)>,
+) -> Result<HttpResponse, TeamError> {
+    let auth = SessionAuth::require(&session, Role::User).ok_or(TeamError::Unauthorized)?;
+    let team_id = path.into_inner().0;
+
+    let mut transaction = pg_pool.begin().await?;
+    let res = sqlx::query!(
+        "DELETE FROM team_invitations WHERE team_id = $1 AND user_id = $2",
+        team_id,
+        auth.user_id
+    )
+    .execute(transaction.as_mut())
+    .await?;
+    if res.rows_affected() == 0 {
+        return Err(TeamError::NotFound);
+    }
+    let clash = sqlx::query_scalar!(
+        r#"SELECT EXISTS (SELECT 1 FROM contest_team_registrations tr JOIN contests c ON c.contest_id = tr.contest_id WHERE tr.team_id = $1 AND NOW() < c.ends_at AND (EXISTS (SELECT 1 FROM contest_registrations r WHERE r.contest_id = tr.contest_id AND r.user_id = $2) OR EXISTS (SELECT 1 FROM contest_team_registrations o JOIN team_members m ON m.team_id = o.team_id WHERE o.contest_id = tr.contest_id AND o.team_id <> $1 AND m.user_id = $2))) AS "clash!""#,
+        team_id,
+        auth.user_id
+    )
+    .fetch_one(transaction.as_mut())
+    .await?;
+    if clash {
+        return Err(TeamError::Conflict(
+            "Already registered for a contest the team takes part in".into(),
+        ));
+    }
+    sqlx::query!(
+        "INSERT INTO team_members (team_id, user_id) VALUES ($1, $2) ON CONFLICT DO NOTHING",
+        team_id,
+        auth.user_id
+    )
+    .execute(transaction.as_mut())
+    .await?;
+    transaction.commit().await?;
+
+    info!("User {} joined team {}", auth.user_id, team_id);
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Removes a member: the captain removes anyone else, and members leave on their own.
+/// Submissions already made for the team stay with it.
+#[instrument(skip(session, pg_pool))]
+pub async fn remove_team_member(
+    session: Session,
+    pg_pool: Data<PgPool>,
+    path: web::Path<(i64, i64)>,
+) -> Result<HttpResponse, TeamError> {
+    let auth = SessionAuth::require(&session, Role::User).ok_or(TeamError::Unauthorized)?;
+    let (team_id, user_id) = path.into_inner();
+
+    let mut conn = pg_pool.acquire().await?;
+    let captain_id = team_captain(&mut conn, team_id).await?;
+    if user_id == captain_id {
+        return Err(TeamError::Conflict(
+            "The captain can't leave the team".into(),
+        ));
+    }
+    if user_id != auth.user_id && captain_id != auth.user_id {
+        return Err(TeamError::Forbidden);
+    }
+    let res = sqlx::query!(
+        "DELETE FROM team_members WHERE team_id = $1 AND user_id = $2",
+        team_id,
+        user_id
+    )
+    .execute(conn.as_mut())
+    .await?;
+    if res.rows_affected() == 0 {
+        return Err(TeamError::NotFound);
+    }
+
+    info!(
+        "User {} removed from team {} by user_id: {}",
+        user_id, team_id, auth.user_id
+    );
+    Ok(HttpResponse::Ok().finish())
+}
@@ -0,0 +1,660 @@
+use std::collections::BTreeMap;
+use std::io::{Cursor, Read};
+
+use actix_session::Session;
+use actix_web::{
+    HttpResponse, ResponseError,
+    web::{self, Bytes, Data},
+};
+use futures_util::TryStreamExt;
+use models::{BlobStore, TestcaseLimits, storage::StorageError};
+use serde::{Deserialize, Serialize};
+use sqlx::{PgConnection, PgPool};
+use tokio_util::io::{ReaderStream, StreamReader};
+use tracing::{info, instrument, warn};
+use zip::ZipArchive;
+
+use crate::pool::ChannelPool;
+use crate::routes::{
+    access::{AccessError, ensure_editor},
+    input_validator::queue_validation,
+    role::Role,
+    session::SessionAuth,
+};
+
+#[derive(thiserror::Error, Debug)]
+pub enum TestcaseError {
+    #[error("{0}")]
+    DatabaseError(#[from] sqlx::Error),
+
+    #[error("{0}")]
+    StorageError(#[from] StorageError),
+
+    #[error("Invalid testcase archive: {0}")]
+    InvalidArchive(String),
+
+    #[error("{0}")]
+    Invalid(String),
+
+    #[error("{0}")]
+    TooLarge(String),
+
+    #[error("No such problem or testcase")]
+    NotFound,
+
+    #[error("Unauthorized")]
+    Unauthorized,
+
+    #[error("Forbidden")]
+    Forbidden,
+}
+
+impl ResponseError for TestcaseError {
+    fn error_response(&self) -> HttpResponse<actix_web::body::BoxBody> {
+        match self {
+            Self::DatabaseError(e) => HttpResponse::InternalServerError().body(e.to_string()),
+            Self::StorageError(StorageError::TooLarge(_)) => {
+                HttpResponse::PayloadTooLarge().body(self.to_string())
+            }
+            Self::StorageError(_) => HttpResponse::InternalServerError().body(self.to_string()),
+            Self::InvalidArchive(_) | Self::Invalid(_) => {
+                HttpResponse::BadRequest().body(self.to_string())
+            }
+            Self::TooLarge(e) => HttpResponse::PayloadTooLarge().body(e.clone()),
+            Self::NotFound => HttpResponse::NotFound().finish(),
+            Self::Unauthorized => HttpResponse::Unauthorized().finish(),
+            Self::Forbidden => HttpResponse::Forbidden().finish(),
+        }
+    }
+}
+
+impl AccessError for TestcaseError {
+    const NOT_FOUND: Self = Self::NotFound;
+    const FORBIDDEN: Self = Self::Forbidden;
+}
+
+#[derive(Serialize)]
+struct Testcase {
+    testcase_id: i64,
+    position: i32,
+    is_sample: bool,
+    testcase: String,
+    output: String,
+    /// `pending` until the problem's validator has run, then `valid` or `invalid`.
+    validation: String,
+    validation_message: Option<String>,
+    /// Produced by the generator script, replaced whenever it runs again.
+    generated: bool,
+    /// Hash of the input in blob storage, `testcase` is then empty.
+    input_blob: Option<String>,
+    input_size: Option<i64>,
+    /// Hash of the output in blob storage, `output` is then empty.
+    output_blob: Option<String>,
+    output_size: Option<i64>,
+    /// Subtask the testcase belongs to, if any.
+    subtask: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct NewTestcase {
+    testcase: String,
+    output: String,
+    #[serde(default)]
+    is_sample: bool,
+}
+
+#[derive(Deserialize)]
+pub struct TestcaseUpdate {
+    testcase: Option<String>,
+    output: Option<String>,
+    is_sample: Option<bool>,
+}
+
+#[derive(Deserialize)]
+pub struct TestcaseOrder {
+    testcase_ids: Vec<i64>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ArchiveParams {
+    #[serde(default)]
+    replace: bool,
+}
+
+fn require_setter(session: &Session) -> Result<SessionAuth, TestcaseError> {
+    SessionAuth::require(session, Role::ProblemSetter).ok_or(TestcaseError::Unauthorized)
+}
+
+fn check_size(limits: &TestcaseLimits, name: &str, content: &str) -> Result<(), TestcaseError> {
+    if content.len() > limits.max_testcase_bytes {
+        return Err(TestcaseError::TooLarge(format!(
+            "{} exceeds {} bytes",
+            name, limits.max_testcase_bytes
+        )));
+    }
+    Ok(())
+}
+
+async fn testcase_count(conn: &mut PgConnection, problem_id: i64) -> Result<usize, sqlx::Error> {
+    let row = sqlx::query!(
+        r#"SELECT count(*) AS "count!" FROM problem_testcases WHERE problem_id = $1"#,
+        problem_id
+    )
+    .fetch_one(conn)
+    .await?;
+    Ok(row.count as usize)
+}
+
+async fn append_testcase(
+    conn: &mut PgConnection,
+    problem_id: i64,
+    testcase: &str,
+    output: &str,
+    is_sample: bool,
+) -> Result<i64, sqlx::Error> {
+    let row = sqlx::query!(
+        "INSERT INTO problem_testcases (problem_id, testcase, output, is_sample, position) SELECT $1, $2, $3, $4, COALESCE(MAX(position) + 1, 0) FROM problem_testcases WHERE problem_id = $1 RETURNING testcase_id",
+        problem_id,
+        testcase,
+        output,
+        is_sample
+    )
+    .fetch_one(conn)
+    .await?;
+    Ok(row.testcase_id)
+}
+
+/// Reads `NN.in`/`NN.ans` pairs from a zip archive, ordered by their shared name.
+/// Names that are all digits sort numerically, so `2.in` comes before `10.in`.
+pub fn parse_testcase_archive(
+    archive: &[u8],
+    limits: &TestcaseLimits,
+) -> Result<Vec<(String, String)>, TestcaseError> {
+    if archive.len() > limits.max_archive_bytes {
+        return Err(TestcaseError::TooLarge(format!(
+            "Archive exceeds {} bytes",
+            limits.max_archive_bytes
+        )));
+    }
+    let mut zip = ZipArchive::new(Cursor::new(archive))
+        .map_err(|e| TestcaseError::InvalidArchive(e.to_string()))?;
+
+    // an .in and an .ans per testcase, checked before anything is unpacked
+    if zip.len() > 2 * limits.max_testcases {
+        return Err(TestcaseError::TooLarge(format!(
+            "Archive has more than {} testcases",
+            limits.max_testcases
+        )));
+    }
+
+    let mut pairs: BTreeMap<(u64, String), (Option<String>, Option<String>)> = BTreeMap::new();
+    let mut unpacked = 0;
+    for i in 0..zip.len() {
+        let file = zip
+            .by_index(i)
+            .map_err(|e| TestcaseError::InvalidArchive(e.to_string()))?;
+        if file.is_dir() {
+            continue;
+        }
+        let path = file
+            .enclosed_name()
+            .ok_or_else(|| TestcaseError::InvalidArchive(format!("unsafe path {}", file.name())))?;
+        let name = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or_default()
+            .to_string();
+        let Some((stem, extension)) = name.rsplit_once('.') else {
+            return Err(TestcaseError::InvalidArchive(format!(
+                "unexpected file {}",
+                name
+            )));
+        };
+        if extension != "in" && extension != "ans" {
+            return Err(TestcaseError::InvalidArchive(format!(
+                "unexpected file {}",
+                name
+            )));
+        }
+
+        // the declared size can lie, so cap the bytes actually read as well
+        let max_bytes = limits.max_testcase_bytes as u64;
+        if file.size() > max_bytes {
+            return Err(TestcaseError::TooLarge(format!(
+                "{} exceeds {} bytes",
+                name, limits.max_testcase_bytes
+            )));
+        }
+        let budget = limits.max_unpacked_bytes - unpacked;
+        let mut content = String::new();
+        file.take(max_bytes.min(budget as u64) + 1)
+            .read_to_string(&mut content)
+            .map_err(|_| TestcaseError::InvalidArchive(format!("{} is not valid utf-8", name)))?;
+        check_size(limits, &name, &content)?;
+        if content.len() > budget {
+            return Err(TestcaseError::TooLarge(format!(
+                "Archive unpacks to more than {} bytes",
+                limits.max_unpacked_bytes
+            )));
+        }
+        unpacked += content.len();
+
+        let key = (stem.parse::<u64>().unwrap_or(u64::MAX), stem.to_string());
+        let entry = pairs.entry(key).or_default();
+        let slot = if extension == "in" {
+            &mut entry.0
+        } else {
+            &mut entry.1
+        };
+        if slot.replace(content).is_some() {
+            return Err(TestcaseError::InvalidArchive(format!(
+                "duplicate file {}",
+                name
+            )));
+        }
+    }
+
+    if pairs.len() > limits.max_testcases {
+        return Err(TestcaseError::TooLarge(format!(
+            "Archive has more than {} testcases",
+            limits.max_testcases
+        )));
+    }
+
+    pairs
+        .into_iter()
+        .map(|((_, stem), pair)| match pair {
+            (Some(input), Some(answer)) => Ok((input, answer)),
+            (None, _) => Err(TestcaseError::InvalidArchive(format!(
+                "missing {}.in",
+                stem
+            ))),
+            (_, None) => Err(TestcaseError::InvalidArchive(format!(
+                "missing {}.ans",
+                stem
+            ))),
+        })
+        .collect()
+}
+
+#[instrument(skip(session, pg_pool))]
+pub async fn list_testcases(
+    session: Session,
+    pg_pool: Data<PgPool>,
+    path: web::Path<(i64,)>,
+) -> Result<HttpResponse, TestcaseError> {
+    let auth = require_setter(&session)?;
+    let problem_id = path.into_inner().0;
+
+    let mut conn = pg_pool.acquire().await?;
+    ensure_editor::<TestcaseError>(&mut conn, problem_id, &auth).await?;
+    let testcases = sqlx::query_as!(
+        Testcase,
+        r#"SELECT t.testcase_id, t.position, t.is_sample, t.testcase, t.output, t.validation, t.validation_message, t.generated, t.input_blob, i.size AS "input_size?", t.output_blob, o.size AS "output_size?", t.subtask FROM problem_testcases t LEFT JOIN blobs i ON i.hash = t.input_blob LEFT JOIN blobs o ON o.hash = t.output_blob WHERE t.problem_id = $1 ORDER BY t.position, t.testcase_id"#,
+        problem_id
+    )
+    .fetch_all(conn.as_mut())
+    .await?;
+
+    Ok(HttpResponse::Ok().json(testcases))
+}
+
+#[instrument(skip(session, pg_pool, channel_pool, limits, testcase))]
+pub async fn add_testcase(
+    session: Session,
+    pg_pool: Data<PgPool>,
+    channel_pool: Data<ChannelPool>,
+    limits: Data<TestcaseLimits>,
+    path: web::Path<(i64,)>,
+    testcase: web::Json<NewTestcase>,
+) -> Result<HttpResponse, TestcaseError> {
+    let auth = require_setter(&session)?;
+    let problem_id = path.into_inner().0;
+    check_size(&limits, "testcase", &testcase.testcase)?;
+    check_size(&limits, "output", &testcase.output)?;
+
+    let mut transaction = pg_pool.begin().await?;
+    ensure_editor::<TestcaseError>(&mut transaction, problem_id, &auth).await?;
+    if testcase_count(&mut transaction, problem_id).await? >= limits.max_testcases {
+        return Err(TestcaseError::TooLarge(format!(
+            "Problem already has {} testcases",
+            limits.max_testcases
+        )));
+    }
+    let testcase_id = append_testcase(
+        &mut transaction,
+        problem_id,
+        &testcase.testcase,
+        &testcase.output,
+        testcase.is_sample,
+    )
+    .await?;
+    let validation =
+        queue_validation(&mut transaction, problem_id, auth.user_id, &[testcase_id]).await?;
+    transaction.commit().await?;
+    if let Some(validation) = &validation {
+        validation.dispatch(pg_pool.as_ref(), &channel_pool).await;
+    }
+
+    info!(
+        "Testcase {} added to problem {} by user_id: {}",
+        testcase_id, problem_id, auth.user_id
+    );
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "testcase_id": testcase_id,
+        "validation_submission_id": validation.map(|validation| validation.submission_id),
+    })))
+}
+
+#[instrument(skip(session, pg_pool, channel_pool, limits, update))]
+pub async fn replace_testcase(
+    session: Session,
+    pg_pool: Data<PgPool>,
+    channel_pool: Data<ChannelPool>,
+    limits: Data<TestcaseLimits>,
+    path: web::Path<(i64, i64)>,
+    update: web::Json<TestcaseUpdate>,
+) -> Result<HttpResponse, TestcaseError> {
+    let auth = require_setter(&session)?;
+    let (problem_id, testcase_id) = path.into_inner();
+    if let Some(testcase) = &update.testcase {
+        check_size(&limits, "testcase", testcase)?;
+    }
+    if let Some(output) = &update.output {
+        check_size(&limits, "output", output)?;
+    }
+    let mut transaction = pg_pool.begin().await?;
+    ensure_editor::<TestcaseError>(&mut transaction, problem_id, &auth).await?;
+
+    let row = sqlx::query!(
+        "UPDATE problem_testcases t SET testcase = COALESCE($3, t.testcase), output = COALESCE($4, t.output), input_blob = CASE WHEN $3 IS NULL THEN t.input_blob END, output_blob = CASE WHEN $4 IS NULL THEN t.output_blob END, is_sample = COALESCE($5, t.is_sample), updated_at = NOW() FROM problems p WHERE t.problem_id = p.problem_id AND p.deleted_at IS NULL AND t.problem_id = $1 AND t.testcase_id = $2 RETURNING t.testcase_id, t.is_sample, t.input_blob, t.output_blob",
+        problem_id,
+        testcase_id,
+        update.testcase,
+        update.output,
+        update.is_sample
+    )
+    .fetch_optional(transaction.as_mut())
+    .await?
+    .ok_or(TestcaseError::NotFound)?;
+    // samples are shown with the problem, so they stay inline
+    if row.is_sample && (row.input_blob.is_some() || row.output_blob.is_some()) {
+        return Err(TestcaseError::Invalid(
+            "Sample testcases must be stored inline".into(),
+        ));
+    }
+
+    // a new input has to pass the validator again
+    let validation = match update.testcase {
+        Some(_) => {
+            queue_validation(&mut transaction, problem_id, auth.user_id, &[testcase_id]).await?
+        }
+        None => None,
+    };
+    transaction.commit().await?;
+    if let Some(validation) = &validation {
+        validation.dispatch(pg_pool.as_ref(), &channel_pool).await;
+    }
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "validation_submission_id": validation.map(|validation| validation.submission_id),
+    })))
+}
+
+#[instrument(skip(session, pg_pool))]
+pub async fn delete_testcase(
+    session: Session,
+    pg_pool: Data<PgPool>,
+    path: web::Path<(i64, i64)>,
+) -> Result<HttpResponse, TestcaseError> {
+    let auth = require_setter(&session)?;
+    let (problem_id, testcase_id) = path.into_inner();
+    ensure_editor::<TestcaseError>(&mut *pg_pool.acquire().await?, problem_id, &auth).await?;
+
+    let row = sqlx::query!(
+        "DELETE FROM problem_testcases t USING problems p WHERE t.problem_id = p.problem_id AND p.deleted_at IS NULL AND t.problem_id = $1 AND t.testcase_id = $2 RETURNING t.testcase_id",
+        problem_id,
+        testcase_id
+    )
+    .fetch_optional(pg_pool.as_ref())
+    .await?;
+
+    match row {
+        Some(_) => Ok(HttpResponse::Ok().finish()),
+        None => Err(TestcaseError::NotFound),
+    }
+}
+
+/// Sets the judging order. `testcase_ids` must list every testcase of the problem exactly once.
+#[instrument(skip(session, pg_pool, order))]
+pub async fn reorder_testcases(
+    session: Session,
+    pg_pool: Data<PgPool>,
+    path: web::Path<(i64,)>,
+    order: web::Json<TestcaseOrder>,
+) -> Result<HttpResponse, TestcaseError> {
+    let auth = require_setter(&session)?;
+    let problem_id = path.into_inner().0;
+
+    let mut transaction = pg_pool.begin().await?;
+    ensure_editor::<TestcaseError>(&mut transaction, problem_id, &auth).await?;
+
+    let updated = sqlx::query!(
+        "UPDATE problem_testcases t SET position = (o.position - 1)::INT FROM UNNEST($2::BIGINT[]) WITH ORDINALITY AS o(testcase_id, position) WHERE t.testcase_id = o.testcase_id AND t.problem_id = $1",
+        problem_id,
+        &order.testcase_ids
+    )
+    .execute(transaction.as_mut())
+    .await?
+    .rows_affected() as usize;
+
+    let total = testcase_count(&mut transaction, problem_id).await?;
+    if updated != order.testcase_ids.len() || updated != total {
+        warn!(
+            "Rejected reorder for problem {}: {} of {} testcases listed",
+            problem_id, updated, total
+        );
+        return Err(TestcaseError::Invalid(
+            "testcase_ids must list every testcase of the problem exactly once".into(),
+        ));
+    }
+    transaction.commit().await?;
+
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Uploads a zip of `NN.in`/`NN.ans` files as hidden testcases, appended after
+/// the existing ones or replacing them with `?replace=true`.
+#[instrument(skip(session, pg_pool, channel_pool, limits, body))]
+pub async fn upload_testcase_archive(
+    session: Session,
+    pg_pool: Data<PgPool>,
+    channel_pool: Data<ChannelPool>,
+    limits: Data<TestcaseLimits>,
+    path: web::Path<(i64,)>,
+    params: web::Query<ArchiveParams>,
+    body: Bytes,
+) -> Result<HttpResponse, TestcaseError> {
+    let auth = require_setter(&session)?;
+    let problem_id = path.into_inner().0;
+
+    let testcases = parse_testcase_archive(&body, &limits)?;
+
+    let mut transaction = pg_pool.begin().await?;
+    ensure_editor::<TestcaseError>(&mut transaction, problem_id, &auth).await?;
+
+    if params.replace {
+        sqlx::query!(
+            "DELETE FROM problem_testcases WHERE problem_id = $1",
+            problem_id
+        )
+        .execute(transaction.as_mut())
+        .await?;
+    }
+    if testcase_count(&mut transaction, problem_id).await? + testcases.len() > limits.max_testcases
+    {
+        return Err(TestcaseError::TooLarge(format!(
+            "Problem would exceed {} testcases",
+            limits.max_testcases
+        )));
+    }
+
+    let mut testcase_ids = Vec::with_capacity(testcases.len());
+    for (input, answer) in &testcases {
+        testcase_ids
+            .push(append_testcase(&mut transaction, problem_id, input, answer, false).await?);
+    }
+    let validation =
+        queue_validation(&mut transaction, problem_id, auth.user_id, &testcase_ids).await?;
+    transaction.commit().await?;
+    if let Some(validation) = &validation {
+        validation.dispatch(pg_pool.as_ref(), &channel_pool).await;
+    }
+
+    info!(
+        "Uploaded {} testcases to problem {} by user_id: {}",
+        testcase_ids.len(),
+        problem_id,
+        auth.user_id
+    );
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "testcase_ids": testcase_ids,
+        "validation_submission_id": validation.map(|validation| validation.submission_id),
+    })))
+}
+
+#[derive(Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum TestcasePart {
+    Input,
+    Output,
+}
+
+/// Locks a testcase of a live problem that can take a blob, which samples cannot.
+async fn lock_blob_testcase(
+    conn: &mut PgConnection,
+    problem_id: i64,
+    testcase_id: i64,
+) -> Result<(), TestcaseError> {
+    let testcase = sqlx::query!(
+        "SELECT t.is_sample FROM problem_testcases t JOIN problems p ON p.problem_id = t.problem_id WHERE t.problem_id = $1 AND t.testcase_id = $2 AND p.deleted_at IS NULL FOR UPDATE OF t",
+        problem_id,
+        testcase_id
+    )
+    .fetch_optional(conn)
+    .await?
+    .ok_or(TestcaseError::NotFound)?;
+    if testcase.is_sample {
+        return Err(TestcaseError::Invalid(
+            "Sample testcases must be stored inline".into(),
+        ));
+    }
+    Ok(())
+}
+
+/// Stores a testcase's input or output in blob storage, streamed from the raw request
+/// body. Meant for tests too large for JSON; the inline column is emptied.
+#[instrument(skip(session, pg_pool, channel_pool, limits, storage, body))]
+pub async fn upload_testcase_file(
+    session: Session,
+    pg_pool: Data<PgPool>,
+    channel_pool: Data<ChannelPool>,
+    limits: Data<TestcaseLimits>,
+    storage: Data<BlobStore>,
+    path: web::Path<(i64, i64, TestcasePart)>,
+    body: web::Payload,
+) -> Result<HttpResponse, TestcaseError> {
+    let auth = require_setter(&session)?;
+    let (problem_id, testcase_id, part) = path.into_inner();
+    {
+        let mut conn = pg_pool.acquire().await?;
+        ensure_editor::<TestcaseError>(&mut conn, problem_id, &auth).await?;
+        lock_blob_testcase(&mut conn, problem_id, testcase_id).await?;
+    }
+
+    // stored before the transaction starts, large uploads take a while
+    let reader = StreamReader::new(body.map_err(std::io::Error::other));
+    let blob = storage.put(reader, limits.max_blob_bytes).await?;
+
+    let mut transaction = pg_pool.begin().await?;
+    // checked again, the testcase may have gone during the upload
+    lock_blob_testcase(&mut transaction, problem_id, testcase_id).await?;
+    sqlx::query!(
+        "INSERT INTO blobs (hash, size) VALUES ($1, $2) ON CONFLICT (hash) DO NOTHING",
+        blob.hash,
+        blob.size as i64
+    )
+    .execute(transaction.as_mut())
+    .await?;
+    match part {
+        TestcasePart::Input => sqlx::query!(
+            "UPDATE problem_testcases SET input_blob = $3, testcase = '', updated_at = NOW() WHERE problem_id = $1 AND testcase_id = $2 AND NOT is_sample",
+            problem_id,
+            testcase_id,
+            blob.hash
+        ),
+        TestcasePart::Output => sqlx::query!(
+            "UPDATE problem_testcases SET output_blob = $3, output = '', updated_at = NOW() WHERE problem_id = $1 AND testcase_id = $2 AND NOT is_sample",
+            problem_id,
+            testcase_id,
+            blob.hash
+        ),
+    }
+    .execute(transaction.as_mut())
+    .await?;
+
+    let validation = match part {
+        TestcasePart::Input => {
+            queue_validation(&mut transaction, problem_id, auth.user_id, &[testcase_id]).await?
+        }
+        TestcasePart::Output => None,
+    };
+    transaction.commit().await?;
+    if let Some(validation) = &validation {
+        validation.dispatch(pg_pool.as_ref(), &channel_pool).await;
+    }
+
+    info!(
+        "Testcase {} of problem {} stored as blob {} ({} bytes) by user_id: {}",
+        testcase_id, problem_id, blob.hash, blob.size, auth.user_id
+    );
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "hash": blob.hash,
+        "size": blob.size,
+        "validation_submission_id": validation.map(|validation| validation.submission_id),
+    })))
+}
+
+/// Downloads a testcase's input or output, streamed from blob storage when it lives there.
+#[instrument(skip(session, pg_pool, storage))]
+pub async fn download_testcase_file(
+    session: Session,
+    pg_pool: Data<PgPool>,
+    storage: Data<BlobStore>,
+    path: web::Path<(i64, i64, TestcasePart)>,
+) -> Result<HttpResponse, TestcaseError> {
+    let auth = require_setter(&session)?;
+    let (problem_id, testcase_id, part) = path.into_inner();
+
+    let mut conn = pg_pool.acquire().await?;
+    ensure_editor::<TestcaseError>(&mut conn, problem_id, &auth).await?;
+    let row = sqlx::query!(
+        "SELECT testcase, output, input_blob, output_blob FROM problem_testcases WHERE problem_id = $1 AND testcase_id = $2",
+        problem_id,
+        testcase_id
+    )
+    .fetch_optional(conn.as_mut())
+    .await?
+    .ok_or(TestcaseError::NotFound)?;
+
+    let (inline, blob) = match part {
+        TestcasePart::Input => (row.testcase, row.input_blob),
+        TestcasePart::Output => (row.output, row.output_blob),
+    };
+    let mut response = HttpResponse::Ok();
+    response.content_type("application/octet-stream");
+    match blob {
+        Some(hash) => Ok(response.streaming(ReaderStream::new(storage.open(&hash).await?))),
+        None => Ok(response.body(inline)),
+    }
+}
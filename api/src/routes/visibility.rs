@@ -0,0 +1,57 @@
+use serde::{Deserialize, Serialize};
+
+use crate::routes::role::Role;
+
+/// Lifecycle of a problem. Only published and archived problems are shown to users.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum Visibility {
+    Draft,
+    Review,
+    Published,
+    Archived,
+}
+
+impl Visibility {
+    /// Whether regular users can open the problem.
+    pub fn is_public(&self) -> bool {
+        matches!(self, Self::Published | Self::Archived)
+    }
+
+    /// Role needed to move a problem from `self` to `to`, `None` if the move is not allowed.
+    pub fn transition_role(&self, to: Visibility) -> Option<Role> {
+        match (self, to) {
+            (Self::Draft, Self::Review) => Some(Role::ProblemSetter),
+            // setters withdraw their own request, admins reject it
+            (Self::Review, Self::Draft) => Some(Role::ProblemSetter),
+            (Self::Review, Self::Published) => Some(Role::Admin),
+            (Self::Published, Self::Archived) => Some(Role::Admin),
+            (Self::Archived, Self::Published) => Some(Role::Admin),
+            _ => None,
+        }
+    }
+}
+
+impl From<Visibility> for &str {
+    fn from(value: Visibility) -> Self {
+        match value {
+            Visibility::Draft => "draft",
+            Visibility::Review => "review",
+            Visibility::Published => "published",
+            Visibility::Archived => "archived",
+        }
+    }
+}
+
+impl TryFrom<&str> for Visibility {
+    type Error = &'static str;
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "draft" => Ok(Self::Draft),
+            "review" => Ok(Self::Review),
+            "published" => Ok(Self::Published),
+            "archived" => Ok(Self::Archived),
+            _ => Err("No such visibility exists"),
+        }
+    }
+}
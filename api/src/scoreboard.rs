@@ -0,0 +1,429 @@
+use std::collections::HashMap;
+
+use models::ExecStatus;
+use redis::{AsyncCommands, aio::ConnectionManager};
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+/// How a contest ranks its participants.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ContestRules {
+    /// Most problems solved, then least penalty time.
+    #[default]
+    Icpc,
+    /// Highest sum of the best score on each problem.
+    Ioi,
+}
+
+impl From<ContestRules> for &str {
+    fn from(value: ContestRules) -> Self {
+        match value {
+            ContestRules::Icpc => "icpc",
+            ContestRules::Ioi => "ioi",
+        }
+    }
+}
+
+impl TryFrom<&str> for ContestRules {
+    type Error = &'static str;
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "icpc" => Ok(Self::Icpc),
+            "ioi" => Ok(Self::Ioi),
+            _ => Err("No such contest rules exist"),
+        }
+    }
+}
+
+pub struct ScoreboardProblem {
+    pub problem_id: i64,
+    pub label: String,
+}
+
+/// A registered user, or a registered team whose members submit together.
+pub struct Participant {
+    pub user_id: Option<i64>,
+    pub team_id: Option<i64>,
+    /// Username or team name.
+    pub name: String,
+}
+
+/// Who a scoreboard row belongs to.
+#[derive(PartialEq, Eq, Hash, Clone, Copy)]
+enum Entrant {
+    User(i64),
+    Team(i64),
+}
+
+impl Entrant {
+    fn of(user_id: Option<i64>, team_id: Option<i64>) -> Self {
+        match team_id {
+            Some(team_id) => Self::Team(team_id),
+            None => Self::User(user_id.unwrap_or_default()),
+        }
+    }
+}
+
+/// A contest submission, in the order it was made.
+pub struct ContestSubmission {
+    pub user_id: i64,
+    /// Set when the user submitted for their team.
+    pub team_id: Option<i64>,
+    pub problem_id: i64,
+    pub status: String,
+    /// Whole minutes since the contest started.
+    pub minute: i64,
+    pub score: i32,
+    /// Made during the scoreboard freeze.
+    pub frozen: bool,
+}
+
+impl ContestSubmission {
+    fn entrant(&self) -> Entrant {
+        self.team_id
+            .map_or(Entrant::User(self.user_id), Entrant::Team)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ProblemResult {
+    pub label: String,
+    /// Judged submissions that count: up to the first accepted one under ICPC rules, all under IOI.
+    pub attempts: i64,
+    /// Submissions still being judged.
+    pub pending: i64,
+    pub solved: bool,
+    /// Minute of the first accepted submission under ICPC rules, of the best score under IOI.
+    pub minute: Option<i64>,
+    pub first_to_solve: bool,
+    pub score: i32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ScoreboardRow {
+    /// Participants tied on every criterion share a rank.
+    pub rank: usize,
+    /// Set on a user's row.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_id: Option<i64>,
+    /// Set on a team's row.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub team_id: Option<i64>,
+    pub name: String,
+    pub solved: i64,
+    /// ICPC penalty in minutes, 0 under IOI rules.
+    pub penalty: i64,
+    pub score: i64,
+    pub problems: Vec<ProblemResult>,
+}
+
+impl ScoreboardRow {
+    fn entrant(&self) -> Entrant {
+        Entrant::of(self.user_id, self.team_id)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Scoreboard {
+    pub rules: ContestRules,
+    /// Frozen submissions are shown as pending.
+    pub frozen: bool,
+    pub problems: Vec<String>,
+    pub rows: Vec<ScoreboardRow>,
+}
+
+/// Ranks every participant from their submissions. Judge errors are ignored, and
+/// submissions by users who are not participants or to problems outside the set
+/// are skipped. Submissions made for a team count towards the team's row. With
+/// `hide_frozen`, submissions made during the freeze count as pending.
+pub fn build_scoreboard(
+    rules: ContestRules,
+    penalty_minutes: i64,
+    problems: &[ScoreboardProblem],
+    participants: &[Participant],
+    submissions: &[ContestSubmission],
+    hide_frozen: bool,
+) -> Scoreboard {
+    let columns: HashMap<i64, usize> = problems
+        .iter()
+        .enumerate()
+        .map(|(column, problem)| (problem.problem_id, column))
+        .collect();
+    let mut rows: Vec<ScoreboardRow> = participants
+        .iter()
+        .map(|participant| ScoreboardRow {
+            rank: 0,
+            user_id: participant.user_id,
+            team_id: participant.team_id,
+            name: participant.name.clone(),
+            solved: 0,
+            penalty: 0,
+            score: 0,
+            problems: problems
+                .iter()
+                .map(|problem| ProblemResult {
+                    label: problem.label.clone(),
+                    attempts: 0,
+                    pending: 0,
+                    solved: false,
+                    minute: None,
+                    first_to_solve: false,
+                    score: 0,
+                })
+                .collect(),
+        })
+        .collect();
+    let row_of: HashMap<Entrant, usize> = participants
+        .iter()
+        .enumerate()
+        .map(|(index, participant)| (Entrant::of(participant.user_id, participant.team_id), index))
+        .collect();
+
+    let passed: &str = ExecStatus::Passed.into();
+    let pending: &str = ExecStatus::Pending.into();
+    let judge_error: &str = ExecStatus::JudgeError.into();
+    let mut first_solves = vec![false; problems.len()];
+    for submission in submissions {
+        let (Some(&row), Some(&column)) = (
+            row_of.get(&submission.entrant()),
+            columns.get(&submission.problem_id),
+        ) else {
+            continue;
+        };
+        let result = &mut rows[row].problems[column];
+        let status = submission.status.as_str();
+        if status == judge_error {
+            continue;
+        }
+        // under ICPC rules nothing after the first accepted submission counts
+        if rules == ContestRules::Icpc && result.solved {
+            continue;
+        }
+        if status == pending || (hide_frozen && submission.frozen) {
+            result.pending += 1;
+            continue;
+        }
+        result.attempts += 1;
+        match rules {
+            ContestRules::Icpc => {
+                if status == passed {
+                    result.solved = true;
+                    result.minute = Some(submission.minute);
+                }
+            }
+            ContestRules::Ioi => {
+                if submission.score > result.score || result.minute.is_none() {
+                    result.score = result.score.max(submission.score);
+                    result.minute = Some(submission.minute);
+                }
+                result.solved |= status == passed;
+            }
+        }
+        if result.solved && !first_solves[column] {
+            first_solves[column] = true;
+            result.first_to_solve = true;
+        }
+    }
+
+    rank_rows(rules, penalty_minutes, &mut rows);
+    Scoreboard {
+        rules,
+        frozen: hide_frozen,
+        problems: problems
+            .iter()
+            .map(|problem| problem.label.clone())
+            .collect(),
+        rows,
+    }
+}
+
+/// Totals each row from its problem results, then sorts and ranks the rows.
+fn rank_rows(rules: ContestRules, penalty_minutes: i64, rows: &mut [ScoreboardRow]) {
+    for row in rows.iter_mut() {
+        row.solved = 0;
+        row.penalty = 0;
+        row.score = 0;
+        for result in &row.problems {
+            if result.solved {
+                row.solved += 1;
+            }
+            row.score += result.score as i64;
+            if rules == ContestRules::Icpc
+                && result.solved
+                && let Some(minute) = result.minute
+            {
+                row.penalty += minute + penalty_minutes * (result.attempts - 1);
+            }
+        }
+    }
+
+    let key = |row: &ScoreboardRow| match rules {
+        ContestRules::Icpc => (-row.solved, row.penalty),
+        ContestRules::Ioi => (-row.score, 0),
+    };
+    rows.sort_by(|a, b| key(a).cmp(&key(b)).then_with(|| a.name.cmp(&b.name)));
+    for index in 0..rows.len() {
+        rows[index].rank = if index > 0 && key(&rows[index - 1]) == key(&rows[index]) {
+            rows[index - 1].rank
+        } else {
+            index + 1
+        };
+    }
+}
+
+/// One reveal of the resolve ceremony: a participant's frozen submissions on one problem.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ResolveStep {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_id: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub team_id: Option<i64>,
+    pub name: String,
+    pub label: String,
+    /// The problem's result once revealed.
+    pub result: ProblemResult,
+    pub rank_before: usize,
+    pub rank_after: usize,
+}
+
+/// Orders the reveal of frozen submissions under ICPC rules. Starting from the frozen
+/// scoreboard, the lowest ranked participant with a frozen problem has their first
+/// such problem revealed, the scoreboard is reranked, and so on until nothing is hidden.
+pub fn resolve_order(
+    penalty_minutes: i64,
+    problems: &[ScoreboardProblem],
+    participants: &[Participant],
+    submissions: &[ContestSubmission],
+) -> Vec<ResolveStep> {
+    let rules = ContestRules::Icpc;
+    let live = build_scoreboard(
+        rules,
+        penalty_minutes,
+        problems,
+        participants,
+        submissions,
+        false,
+    );
+    let live: HashMap<Entrant, &ScoreboardRow> =
+        live.rows.iter().map(|row| (row.entrant(), row)).collect();
+    let mut rows = build_scoreboard(
+        rules,
+        penalty_minutes,
+        problems,
+        participants,
+        submissions,
+        true,
+    )
+    .rows;
+
+    let columns: HashMap<i64, usize> = problems
+        .iter()
+        .enumerate()
+        .map(|(column, problem)| (problem.problem_id, column))
+        .collect();
+    // problems solved before the freeze have nothing left to reveal
+    let mut hidden: HashMap<Entrant, Vec<bool>> = HashMap::new();
+    for submission in submissions.iter().filter(|submission| submission.frozen) {
+        let entrant = submission.entrant();
+        let (Some(&column), Some(row)) = (
+            columns.get(&submission.problem_id),
+            rows.iter().find(|row| row.entrant() == entrant),
+        ) else {
+            continue;
+        };
+        if !row.problems[column].solved {
+            hidden
+                .entry(entrant)
+                .or_insert_with(|| vec![false; problems.len()])[column] = true;
+        }
+    }
+
+    let mut steps = Vec::new();
+    loop {
+        let next = rows.iter().enumerate().rev().find_map(|(index, row)| {
+            let columns = hidden.get(&row.entrant())?;
+            let column = columns.iter().position(|&hidden| hidden)?;
+            Some((index, column))
+        });
+        let Some((index, column)) = next else {
+            break;
+        };
+        let entrant = rows[index].entrant();
+        let rank_before = rows[index].rank;
+        let result = live[&entrant].problems[column].clone();
+        rows[index].problems[column] = result.clone();
+        hidden.get_mut(&entrant).unwrap()[column] = false;
+        rank_rows(rules, penalty_minutes, &mut rows);
+        let row = rows.iter().find(|row| row.entrant() == entrant).unwrap();
+        steps.push(ResolveStep {
+            user_id: row.user_id,
+            team_id: row.team_id,
+            name: row.name.clone(),
+            label: result.label.clone(),
+            result,
+            rank_before,
+            rank_after: row.rank,
+        });
+    }
+    steps
+}
+
+/// Rendered scoreboards kept in Redis for a few seconds, so contestants polling
+/// the scoreboard do not each recompute it.
+#[derive(Clone)]
+pub struct ScoreboardCache {
+    conn: ConnectionManager,
+    ttl_secs: u64,
+}
+
+impl ScoreboardCache {
+    pub fn new(conn: ConnectionManager, ttl_secs: u64) -> Self {
+        Self { conn, ttl_secs }
+    }
+
+    pub fn key(contest_id: i64) -> String {
+        format!("scoreboard:{}", contest_id)
+    }
+
+    /// Key of the scoreboard everyone else sees during the freeze, kept apart so a
+    /// board cached just before the freeze is not served once it starts.
+    pub fn frozen_key(contest_id: i64) -> String {
+        format!("scoreboard:{}:frozen", contest_id)
+    }
+
+    /// Key of the live scoreboard shown to the contest's judges during the freeze.
+    pub fn judge_key(contest_id: i64) -> String {
+        format!("scoreboard:{}:judge", contest_id)
+    }
+
+    /// A cached scoreboard. Redis being unavailable counts as a miss.
+    pub async fn get(&self, key: &str) -> Option<String> {
+        let mut conn = self.conn.clone();
+        match conn.get::<_, Option<String>>(key).await {
+            Ok(value) => value,
+            Err(e) => {
+                warn!("Failed to read scoreboard cache {}: {}", key, e);
+                None
+            }
+        }
+    }
+
+    pub async fn put(&self, key: &str, value: &str) {
+        if self.ttl_secs == 0 {
+            return;
+        }
+        let mut conn = self.conn.clone();
+        if let Err(e) = conn.set_ex::<_, _, ()>(key, value, self.ttl_secs).await {
+            warn!("Failed to write scoreboard cache {}: {}", key, e);
+        }
+    }
+
+    /// Drops a cached scoreboard that no longer reflects the contest.
+    pub async fn invalidate(&self, key: &str) {
+        let mut conn = self.conn.clone();
+        if let Err(e) = conn.del::<_, ()>(key).await {
+            warn!("Failed to clear scoreboard cache {}: {}", key, e);
+        }
+    }
+}
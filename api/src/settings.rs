@@ -1,8 +1,9 @@
 use std::path::Path;
 
 use models::{
-    ApiConfig, DatabaseConfig, RabbitMQConfig, RedisConfig, RuntimeConfigs,
-    email::EmailClientConfig, utils::get_configuration,
+    ApiConfig, DatabaseConfig, OutboxConfig, RabbitMQConfig, RedisConfig, RuntimeConfigs,
+    ScoreboardConfig, StorageConfig, TestcaseLimits, email::EmailClientConfig,
+    utils::get_configuration,
 };
 
 #[derive(serde::Deserialize)]
@@ -13,6 +14,14 @@ pub struct ApiSettings {
     pub rabbitmq: RabbitMQConfig,
     pub runtimeconfigs: RuntimeConfigs,
     pub email_client: EmailClientConfig,
+    #[serde(default)]
+    pub outbox: OutboxConfig,
+    #[serde(default)]
+    pub testcase_limits: TestcaseLimits,
+    #[serde(default)]
+    pub storage: StorageConfig,
+    #[serde(default)]
+    pub scoreboard: ScoreboardConfig,
 }
 
 impl ApiSettings {
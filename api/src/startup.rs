@@ -2,12 +2,31 @@ use std::net::TcpListener;
 use std::time::Duration;
 
 use crate::ApiSettings;
+use crate::outbox;
+use crate::pool::{ChannelManager, ChannelPool};
 use crate::routes::create_problem::post::create_problem;
 use crate::routes::session::SessionAuth;
 use crate::routes::{
-    list_problems, login, signup_confirmation, stats, status, submissions, submit_problem,
+    add_testcase, answer_clarification, ask_clarification, assignment_summary, change_visibility,
+    contest_problem, contest_scoreboard, course_problem, create_assignment, create_contest,
+    create_course, create_team, delete_generator, delete_problem, delete_testcase,
+    delete_validator, discard_dead_letters, download_testcase_file, export_assignment_grades,
+    export_contest_grades, export_problem_package, generate_testcases, get_assignment, get_contest,
+    get_course, get_reference, get_team, get_validator, get_virtual_participation,
+    import_problem_package, invite_to_team, join_team, list_announcements, list_clarifications,
+    list_collaborators, list_contests, list_course_members, list_dead_letters, list_generators,
+    list_problems, list_subtasks, list_tags, list_testcases, login, make_announcement, my_courses,
+    my_teams, problem_submissions, register_for_contest, remove_collaborator, remove_course_member,
+    remove_team_member, reorder_testcases, replace_testcase, replay_dead_letters,
+    resolve_scoreboard, set_collaborator, set_contest_problems, set_course_member,
+    set_course_problems, set_generator, set_problem_tags, set_subtasks, set_validator,
+    signup_confirmation, start_virtual_participation, stats, status, submissions, submit_problem,
+    submit_reference, unfreeze_scoreboard, unregister_from_contest, update_assignment,
+    update_contest, update_problem, upload_testcase_archive, upload_testcase_file,
+    virtual_scoreboard, withdraw_team_invitation,
 };
 use crate::routes::{problem, signup};
+use crate::scoreboard::ScoreboardCache;
 use actix_cors::Cors;
 use actix_limitation::{Limiter, RateLimiter};
 use actix_session::storage::RedisSessionStore;
@@ -19,8 +38,8 @@ use actix_web::{
     dev::Server,
     web::{self, Data},
 };
-use models::RuntimeConfigs;
 use models::email::EmailClient;
+use models::{BlobStore, RuntimeConfigs, TestcaseLimits};
 use sqlx::PgPool;
 
 #[allow(dead_code)]
@@ -57,6 +76,20 @@ impl Application {
         )
         .await?;
 
+        let channel_pool = ChannelPool::builder(ChannelManager::new(rabbitmq_conn))
+            .max_size(settings.rabbitmq.channel_pool_size)
+            .build()?;
+
+        // topology is declared once here instead of on every publish
+        outbox::declare_exchange(&*channel_pool.get().await?).await?;
+
+        tokio::spawn(outbox::relay(
+            pgpool.clone(),
+            channel_pool.clone(),
+            settings.outbox.clone(),
+        ));
+        tokio::spawn(outbox::sweep(pgpool.clone(), settings.outbox));
+
         let limiter = Limiter::builder(&settings.redis.url())
             .key_by(|req: &ServiceRequest| {
                 req.get_session()
@@ -70,14 +103,22 @@ impl Application {
             .build()
             .unwrap();
 
+        let redis_conn = redis::Client::open(settings.redis.url())?
+            .get_connection_manager()
+            .await?;
+        let scoreboard_cache = ScoreboardCache::new(redis_conn, settings.scoreboard.cache_secs);
+
         let server = run(
             pgpool,
             listener,
             redis_store,
             limiter,
-            rabbitmq_conn,
+            channel_pool,
             email_client,
             settings.runtimeconfigs,
+            settings.testcase_limits,
+            BlobStore::new(&settings.storage),
+            scoreboard_cache,
             settings.application.base_url,
         )
         .await?;
@@ -99,14 +140,20 @@ pub async fn run(
     listener: TcpListener,
     redis_store: RedisSessionStore,
     limiter: Limiter,
-    rabbitmq_conn: lapin::Connection,
+    channel_pool: ChannelPool,
     email_client: EmailClient,
     runtimeconfigs: RuntimeConfigs,
+    testcase_limits: TestcaseLimits,
+    storage: BlobStore,
+    scoreboard_cache: ScoreboardCache,
     base_url: String,
 ) -> Result<Server, anyhow::Error> {
     let data_pgpool = Data::new(pgpool);
-    let data_rabbitmq = Data::new(rabbitmq_conn);
+    let data_channel_pool = Data::new(channel_pool);
     let data_runtimeconfigs = Data::new(runtimeconfigs);
+    let data_testcase_limits = Data::new(testcase_limits);
+    let data_storage = Data::new(storage);
+    let data_scoreboard_cache = Data::new(scoreboard_cache);
     let email_client = Data::new(email_client);
     let application_base_url = Data::new(ApplicationBaseUrl(base_url));
     let secret_key = Key::generate();
@@ -115,7 +162,7 @@ pub async fn run(
         let cors = Cors::default()
             .allowed_origin("http://127.0.0.1:5173")
             .allowed_origin("http://localhost:5173")
-            .allowed_methods(vec!["GET", "POST"])
+            .allowed_methods(vec!["GET", "POST", "PUT", "DELETE"])
             .allowed_headers(&[
                 actix_web::http::header::AUTHORIZATION,
                 actix_web::http::header::ACCEPT,
@@ -136,8 +183,11 @@ pub async fn run(
             )
             .app_data(data_limiter.clone())
             .app_data(data_pgpool.clone())
-            .app_data(data_rabbitmq.clone())
+            .app_data(data_channel_pool.clone())
             .app_data(data_runtimeconfigs.clone())
+            .app_data(data_testcase_limits.clone())
+            .app_data(data_storage.clone())
+            .app_data(data_scoreboard_cache.clone())
             .app_data(email_client.clone())
             .app_data(application_base_url.clone())
             .route("/login", web::post().to(login))
@@ -146,10 +196,241 @@ pub async fn run(
             .route("/{problemID}/submit", web::post().to(submit_problem))
             .route("/{submissionID}/status", web::get().to(status))
             .route("/problem/{problemID}", web::get().to(problem))
+            .route("/problem/{problemID}", web::put().to(update_problem))
+            .route("/problem/{problemID}", web::delete().to(delete_problem))
+            .route(
+                "/problem/{problemID}/visibility",
+                web::post().to(change_visibility),
+            )
             .route("/{problemID}/submissions", web::get().to(submissions))
+            .route(
+                "/problem/{problemID}/submissions",
+                web::get().to(problem_submissions),
+            )
+            .route(
+                "/problem/{problemID}/collaborators",
+                web::get().to(list_collaborators),
+            )
+            .route(
+                "/problem/{problemID}/collaborators/{userID}",
+                web::put().to(set_collaborator),
+            )
+            .route(
+                "/problem/{problemID}/collaborators/{userID}",
+                web::delete().to(remove_collaborator),
+            )
             .route("/createProblem", web::post().to(create_problem))
             .route("/problems", web::get().to(list_problems))
             .route("/stats", web::get().to(stats))
+            .route("/tags", web::get().to(list_tags))
+            .route("/problem/{problemID}/tags", web::put().to(set_problem_tags))
+            .route(
+                "/problem/{problemID}/reference",
+                web::get().to(get_reference),
+            )
+            .route(
+                "/problem/{problemID}/reference",
+                web::post().to(submit_reference),
+            )
+            .route(
+                "/problem/{problemID}/validator",
+                web::get().to(get_validator),
+            )
+            .route(
+                "/problem/{problemID}/validator",
+                web::put().to(set_validator),
+            )
+            .route(
+                "/problem/{problemID}/validator",
+                web::delete().to(delete_validator),
+            )
+            .route(
+                "/problem/{problemID}/generators",
+                web::get().to(list_generators),
+            )
+            .route(
+                "/problem/{problemID}/generators/{name}",
+                web::put().to(set_generator),
+            )
+            .route(
+                "/problem/{problemID}/generators/{name}",
+                web::delete().to(delete_generator),
+            )
+            .route(
+                "/problem/{problemID}/subtasks",
+                web::get().to(list_subtasks),
+            )
+            .route("/problem/{problemID}/subtasks", web::put().to(set_subtasks))
+            .route(
+                "/problem/{problemID}/generate",
+                web::post().to(generate_testcases),
+            )
+            .route(
+                "/problem/{problemID}/package",
+                web::get().to(export_problem_package),
+            )
+            .service(
+                web::resource("/problems/import")
+                    .app_data(web::PayloadConfig::new(
+                        data_testcase_limits.max_archive_bytes,
+                    ))
+                    .route(web::post().to(import_problem_package)),
+            )
+            .route("/teams", web::get().to(my_teams))
+            .route("/teams", web::post().to(create_team))
+            .route("/team/{teamID}", web::get().to(get_team))
+            .route(
+                "/team/{teamID}/invitations/{userID}",
+                web::post().to(invite_to_team),
+            )
+            .route(
+                "/team/{teamID}/invitations/{userID}",
+                web::delete().to(withdraw_team_invitation),
+            )
+            .route("/team/{teamID}/join", web::post().to(join_team))
+            .route(
+                "/team/{teamID}/members/{userID}",
+                web::delete().to(remove_team_member),
+            )
+            .route("/contests", web::get().to(list_contests))
+            .route("/contests", web::post().to(create_contest))
+            .route("/contest/{contestID}", web::get().to(get_contest))
+            .route("/contest/{contestID}", web::put().to(update_contest))
+            .route(
+                "/contest/{contestID}/problems",
+                web::put().to(set_contest_problems),
+            )
+            .route(
+                "/contest/{contestID}/problems/{label}",
+                web::get().to(contest_problem),
+            )
+            .route(
+                "/contest/{contestID}/scoreboard",
+                web::get().to(contest_scoreboard),
+            )
+            .route(
+                "/contest/{contestID}/unfreeze",
+                web::post().to(unfreeze_scoreboard),
+            )
+            .route(
+                "/contest/{contestID}/resolve",
+                web::get().to(resolve_scoreboard),
+            )
+            .route(
+                "/contest/{contestID}/clarifications",
+                web::get().to(list_clarifications),
+            )
+            .route(
+                "/contest/{contestID}/clarifications",
+                web::post().to(ask_clarification),
+            )
+            .route(
+                "/contest/{contestID}/clarifications/{clarificationID}/answer",
+                web::post().to(answer_clarification),
+            )
+            .route(
+                "/contest/{contestID}/announcements",
+                web::get().to(list_announcements),
+            )
+            .route(
+                "/contest/{contestID}/announcements",
+                web::post().to(make_announcement),
+            )
+            .route(
+                "/contest/{contestID}/virtual",
+                web::get().to(get_virtual_participation),
+            )
+            .route(
+                "/contest/{contestID}/virtual",
+                web::post().to(start_virtual_participation),
+            )
+            .route(
+                "/contest/{contestID}/virtual/scoreboard",
+                web::get().to(virtual_scoreboard),
+            )
+            .route(
+                "/contest/{contestID}/export",
+                web::get().to(export_contest_grades),
+            )
+            .route(
+                "/contest/{contestID}/register",
+                web::post().to(register_for_contest),
+            )
+            .route(
+                "/contest/{contestID}/register",
+                web::delete().to(unregister_from_contest),
+            )
+            .route("/courses", web::get().to(my_courses))
+            .route("/courses", web::post().to(create_course))
+            .route("/course/{courseID}", web::get().to(get_course))
+            .route(
+                "/course/{courseID}/members",
+                web::get().to(list_course_members),
+            )
+            .route(
+                "/course/{courseID}/members/{userID}",
+                web::put().to(set_course_member),
+            )
+            .route(
+                "/course/{courseID}/members/{userID}",
+                web::delete().to(remove_course_member),
+            )
+            .route(
+                "/course/{courseID}/problems",
+                web::put().to(set_course_problems),
+            )
+            .route(
+                "/course/{courseID}/problems/{problemID}",
+                web::get().to(course_problem),
+            )
+            .route(
+                "/course/{courseID}/assignments",
+                web::post().to(create_assignment),
+            )
+            .route(
+                "/course/{courseID}/assignments/{assignmentID}",
+                web::get().to(get_assignment),
+            )
+            .route(
+                "/course/{courseID}/assignments/{assignmentID}",
+                web::put().to(update_assignment),
+            )
+            .route(
+                "/course/{courseID}/assignments/{assignmentID}/summary",
+                web::get().to(assignment_summary),
+            )
+            .route(
+                "/course/{courseID}/assignments/{assignmentID}/export",
+                web::get().to(export_assignment_grades),
+            )
+            .route("/admin/dlq", web::get().to(list_dead_letters))
+            .route("/admin/dlq/replay", web::post().to(replay_dead_letters))
+            .route("/admin/dlq/discard", web::post().to(discard_dead_letters))
+            .service(
+                web::scope("/problem/{problemID}/testcases")
+                    // a testcase and its output may each be up to max_testcase_bytes
+                    .app_data(
+                        web::JsonConfig::default()
+                            .limit(2 * data_testcase_limits.max_testcase_bytes + 4096),
+                    )
+                    .route("", web::get().to(list_testcases))
+                    .route("", web::post().to(add_testcase))
+                    .route("/reorder", web::post().to(reorder_testcases))
+                    .service(
+                        web::resource("/archive")
+                            .app_data(web::PayloadConfig::new(
+                                data_testcase_limits.max_archive_bytes,
+                            ))
+                            .route(web::post().to(upload_testcase_archive)),
+                    )
+                    .route("/{testcaseID}", web::put().to(replace_testcase))
+                    .route("/{testcaseID}", web::delete().to(delete_testcase))
+                    .route(
+                        "/{testcaseID}/{part}",
+                        web::get().to(download_testcase_file),
+                    )
+                    .route("/{testcaseID}/{part}", web::put().to(upload_testcase_file)),
+            )
     })
     .listen(listener)?
     .run();
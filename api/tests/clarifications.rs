@@ -0,0 +1,52 @@
+use api::routes::clarifications::{Clarification, visible_clarifications};
+use chrono::{TimeZone, Utc};
+
+fn clarification(clarification_id: i64, user_id: i64, broadcast: bool) -> Clarification {
+    Clarification {
+        clarification_id,
+        user_id: Some(user_id),
+        username: Some(format!("user{}", user_id)),
+        label: None,
+        question: "Is the input sorted?".into(),
+        answer: broadcast.then(|| "No".into()),
+        broadcast,
+        created_at: Utc.with_ymd_and_hms(2026, 10, 1, 12, 0, 0).unwrap(),
+        answered_at: None,
+    }
+}
+
+fn clarifications() -> Vec<Clarification> {
+    vec![
+        clarification(1, 1, false),
+        clarification(2, 2, false),
+        clarification(3, 2, true),
+    ]
+}
+
+#[test]
+fn judges_see_every_clarification_with_its_asker() {
+    assert_eq!(
+        visible_clarifications(clarifications(), 9, true),
+        clarifications()
+    );
+}
+
+#[test]
+fn contestants_see_their_own_and_broadcast_ones() {
+    let visible = visible_clarifications(clarifications(), 1, false);
+
+    let ids: Vec<_> = visible.iter().map(|c| c.clarification_id).collect();
+    assert_eq!(ids, [1, 3]);
+    assert_eq!(visible[0].username.as_deref(), Some("user1"));
+}
+
+#[test]
+fn broadcast_clarifications_hide_other_askers() {
+    let visible = visible_clarifications(clarifications(), 1, false);
+
+    assert_eq!(visible[1].user_id, None);
+    assert_eq!(visible[1].username, None);
+    // the asker still sees their own name on a broadcast
+    let own = visible_clarifications(clarifications(), 2, false);
+    assert_eq!(own[1].user_id, Some(2));
+}
@@ -0,0 +1,56 @@
+use api::routes::contests::{ContestError, ContestWindow, EntryRefused};
+
+fn window(started: bool, ended: bool) -> ContestWindow {
+    ContestWindow {
+        owner_id: Some(1),
+        started,
+        ended,
+    }
+}
+
+#[test]
+fn running_contest_admits_registered_users() {
+    let running = window(true, false);
+
+    assert_eq!(running.admits(true, false), Ok(false));
+    assert_eq!(running.admits(false, false), Err(EntryRefused::NotRegistered));
+}
+
+#[test]
+fn upcoming_contest_admits_no_one() {
+    let upcoming = window(false, false);
+
+    assert_eq!(upcoming.admits(true, false), Err(EntryRefused::NotRunning));
+    // registration is checked first, so the message tells what to fix
+    assert_eq!(upcoming.admits(false, false), Err(EntryRefused::NotRegistered));
+}
+
+#[test]
+fn ended_contest_admits_only_running_virtual_participations() {
+    let ended = window(true, true);
+
+    assert_eq!(ended.admits(false, true), Ok(true));
+    assert_eq!(ended.admits(true, false), Err(EntryRefused::NotRunning));
+}
+
+#[test]
+fn virtual_participation_starts_after_the_contest() {
+    let running = window(true, false);
+    let ended = window(true, true);
+
+    assert!(matches!(
+        running.can_start_virtual(false),
+        Err(ContestError::Conflict(message)) if message == "Contest has not ended"
+    ));
+    assert!(ended.can_start_virtual(false).is_ok());
+}
+
+#[test]
+fn contestants_cannot_take_the_contest_virtually() {
+    let ended = window(true, true);
+
+    assert!(matches!(
+        ended.can_start_virtual(true),
+        Err(ContestError::Conflict(message)) if message == "Already took part in the contest"
+    ));
+}
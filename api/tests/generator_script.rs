@@ -0,0 +1,48 @@
+use api::generator::{ScriptError, parse_generator_script, valid_generator_name};
+use models::GeneratorInvocation;
+
+#[test]
+fn script_lines_become_invocations_in_order() {
+    let script = "# small tests\ngen_random 10 1\n\n  gen_random   1000 2  \nmax-case\n";
+
+    let invocations = parse_generator_script(script).unwrap();
+
+    assert_eq!(
+        invocations,
+        vec![
+            GeneratorInvocation {
+                generator: "gen_random".to_string(),
+                args: vec!["10".to_string(), "1".to_string()],
+            },
+            GeneratorInvocation {
+                generator: "gen_random".to_string(),
+                args: vec!["1000".to_string(), "2".to_string()],
+            },
+            GeneratorInvocation {
+                generator: "max-case".to_string(),
+                args: vec![],
+            },
+        ]
+    );
+}
+
+#[test]
+fn invalid_generator_name_reports_its_line() {
+    let res = parse_generator_script("gen 1\n\n../gen 2\n");
+
+    assert_eq!(
+        res,
+        Err(ScriptError {
+            line: 3,
+            message: "Invalid generator name ../gen".to_string(),
+        })
+    );
+}
+
+#[test]
+fn generator_names_are_restricted() {
+    assert!(valid_generator_name("gen_tree-2"));
+    assert!(!valid_generator_name(""));
+    assert!(!valid_generator_name("gen.py"));
+    assert!(!valid_generator_name("a/b"));
+}
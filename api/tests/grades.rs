@@ -0,0 +1,159 @@
+use api::grades::{
+    Gradebook, GradebookProblem, GradedSubmission, Student, grade_students, late_score,
+};
+use chrono::{DateTime, TimeZone, Utc};
+
+fn students() -> Vec<Student> {
+    ["alice", "bob"]
+        .iter()
+        .enumerate()
+        .map(|(i, username)| Student {
+            user_id: i as i64 + 1,
+            username: username.to_string(),
+        })
+        .collect()
+}
+
+fn at(minute: u32) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2026, 10, 1, 12, minute, 0).unwrap()
+}
+
+fn submission(
+    user_id: i64,
+    problem_id: i64,
+    status: &str,
+    score: i32,
+    late: bool,
+    minute: u32,
+) -> GradedSubmission {
+    GradedSubmission {
+        user_id,
+        problem_id,
+        status: status.into(),
+        score,
+        late,
+        created_at: at(minute),
+    }
+}
+
+#[test]
+fn best_submission_counts_per_problem() {
+    let submissions = vec![
+        submission(1, 10, "WRONG ANSWER", 40, false, 1),
+        submission(1, 10, "PASSED", 100, false, 5),
+        // a worse later attempt doesn't lower the grade
+        submission(1, 10, "WRONG ANSWER", 0, false, 9),
+        submission(1, 20, "WRONG ANSWER", 30, false, 3),
+        submission(1, 20, "WRONG ANSWER", 30, false, 7),
+    ];
+
+    let grades = grade_students(0, &[10, 20], &students(), &submissions);
+
+    let alice = &grades[0];
+    assert_eq!(alice.total, 130);
+    assert_eq!(alice.problems[0].best_score, 100);
+    assert_eq!(alice.problems[0].attempts, 3);
+    assert!(alice.problems[0].solved);
+    assert_eq!(alice.problems[0].first_submitted_at, Some(at(1)));
+    assert_eq!(alice.problems[0].best_at, Some(at(5)));
+    // ties keep the earliest submission
+    assert_eq!(alice.problems[1].best_at, Some(at(3)));
+    assert!(!alice.problems[1].solved);
+
+    let bob = &grades[1];
+    assert_eq!(bob.total, 0);
+    assert!(bob.problems.iter().all(|grade| grade.attempts == 0));
+    assert!(bob.problems.iter().all(|grade| grade.best_at.is_none()));
+}
+
+#[test]
+fn late_submissions_lose_the_penalty() {
+    assert_eq!(late_score(100, 30), 70);
+    assert_eq!(late_score(100, 150), 0);
+
+    let submissions = vec![
+        submission(1, 10, "WRONG ANSWER", 60, false, 1),
+        // 100 late with a 30% penalty beats 60 on time
+        submission(1, 10, "PASSED", 100, true, 20),
+        submission(2, 10, "WRONG ANSWER", 80, false, 2),
+        // 100 late is only worth 70, below 80 on time
+        submission(2, 10, "PASSED", 100, true, 30),
+    ];
+
+    let grades = grade_students(30, &[10], &students(), &submissions);
+
+    assert_eq!(grades[0].problems[0].best_score, 70);
+    assert!(grades[0].problems[0].late);
+    assert_eq!(grades[1].problems[0].best_score, 80);
+    assert!(!grades[1].problems[0].late);
+    assert!(grades[1].problems[0].solved);
+}
+
+#[test]
+fn pending_judge_errors_and_strangers_are_skipped() {
+    let submissions = vec![
+        submission(1, 10, "PENDING", 0, false, 1),
+        submission(1, 10, "JUDGE ERROR", 0, false, 2),
+        // not a student of the course
+        submission(3, 10, "PASSED", 100, false, 3),
+        // not a problem of the assignment
+        submission(2, 30, "PASSED", 100, false, 4),
+    ];
+
+    let grades = grade_students(0, &[10], &students(), &submissions);
+
+    assert_eq!(grades.len(), 2);
+    assert!(grades.iter().all(|row| row.total == 0));
+    assert!(grades.iter().all(|row| row.problems[0].attempts == 0));
+}
+
+#[test]
+fn gradebook_csv_has_a_row_per_student() {
+    let mut students = students();
+    students[1].username = "bob, \"the builder\"".into();
+    let submissions = vec![
+        submission(1, 10, "WRONG ANSWER", 0, false, 1),
+        submission(1, 10, "PASSED", 100, false, 5),
+    ];
+    let gradebook = Gradebook {
+        students: grade_students(0, &[10], &students, &submissions),
+        problems: vec![GradebookProblem {
+            problem_id: 10,
+            label: "A".into(),
+        }],
+    };
+
+    let csv = gradebook.to_csv();
+    let lines: Vec<&str> = csv.split_terminator("\r\n").collect();
+    assert_eq!(
+        lines,
+        vec![
+            "user_id,username,total,A score,A attempts,A solved,A late,A first_submitted_at,A best_at",
+            "1,alice,100,100,2,true,false,2026-10-01T12:01:00+00:00,2026-10-01T12:05:00+00:00",
+            "2,\"bob, \"\"the builder\"\"\",0,0,0,false,false,,",
+        ]
+    );
+}
+
+#[test]
+fn gradebook_csv_neutralizes_formulas() {
+    let mut students = students();
+    students[0].username = "=HYPERLINK(\"http://evil\")".into();
+    students[1].username = "@SUM(A1)".into();
+    let gradebook = Gradebook {
+        students: grade_students(0, &[10], &students, &[]),
+        problems: vec![GradebookProblem {
+            problem_id: 10,
+            label: "+A".into(),
+        }],
+    };
+
+    let csv = gradebook.to_csv();
+    let lines: Vec<&str> = csv.split_terminator("\r\n").collect();
+    assert!(lines[0].starts_with("user_id,username,total,'+A score,"));
+    assert_eq!(
+        lines[1],
+        "1,\"'=HYPERLINK(\"\"http://evil\"\")\",0,0,0,false,false,,"
+    );
+    assert_eq!(lines[2], "2,'@SUM(A1),0,0,0,false,false,,");
+}
@@ -0,0 +1,177 @@
+use std::io::{Cursor, Write};
+
+use api::package::{
+    PackageError, PackageFormat, PackageLimits, PackageTestcase, ProblemPackage, read_package,
+    write_package,
+};
+use models::{Checker, TestcaseLimits};
+use zip::{ZipWriter, write::SimpleFileOptions};
+
+fn build_archive(files: &[(&str, impl AsRef<[u8]>)]) -> Vec<u8> {
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    for (name, content) in files {
+        zip.start_file(*name, SimpleFileOptions::default()).unwrap();
+        zip.write_all(content.as_ref()).unwrap();
+    }
+    zip.finish().unwrap().into_inner()
+}
+
+fn testcase(input: &str, output: &str, sample: bool) -> PackageTestcase {
+    PackageTestcase {
+        input: input.into(),
+        output: output.into(),
+        sample,
+    }
+}
+
+#[test]
+fn exported_package_imports_unchanged() {
+    let package = ProblemPackage {
+        title: "EVEN/ODD".into(),
+        difficulty: Some("easy".into()),
+        statement: "Print EVEN or ODD".into(),
+        limits: PackageLimits {
+            time_limit_ms: Some(2000),
+            memory_limit_mb: None,
+        },
+        checker: Checker::Lines,
+        testcases: vec![testcase("1 4", "EVEN", true), testcase("1 7", "ODD", false)],
+    };
+
+    let archive = write_package(&package).unwrap();
+    let (format, imported) = read_package(&archive, &TestcaseLimits::default()).unwrap();
+
+    assert_eq!(format, PackageFormat::Crabjudge);
+    assert_eq!(imported, package);
+}
+
+#[test]
+fn kattis_package_in_subdirectory_is_imported() {
+    let archive = build_archive(&[
+        (
+            "hello/problem.yaml",
+            "name: Hello\nlimits:\n  time_limit: 1.5\n  memory: 512\n",
+        ),
+        ("hello/problem_statement/problem.en.md", "Say hello"),
+        ("hello/data/sample/1.in", ""),
+        ("hello/data/sample/1.ans", "hello"),
+        ("hello/data/secret/a.in", "x"),
+        ("hello/data/secret/a.ans", "hello x"),
+    ]);
+
+    let (format, package) = read_package(&archive, &TestcaseLimits::default()).unwrap();
+
+    assert_eq!(format, PackageFormat::Kattis);
+    assert_eq!(package.title, "Hello");
+    assert_eq!(package.statement, "Say hello");
+    assert_eq!(package.limits.time_limit_ms, Some(1500));
+    assert_eq!(package.limits.memory_limit_mb, Some(512));
+    assert_eq!(
+        package.testcases,
+        vec![testcase("", "hello", true), testcase("x", "hello x", false)]
+    );
+}
+
+#[test]
+fn kattis_package_with_custom_validator_is_rejected() {
+    let archive = build_archive(&[
+        ("problem.yaml", "name: Hello\nvalidation: custom\n"),
+        ("problem_statement/problem.md", "Say hello"),
+        ("data/secret/1.in", ""),
+        ("data/secret/1.ans", "hello"),
+    ]);
+
+    let res = read_package(&archive, &TestcaseLimits::default());
+
+    assert!(matches!(res, Err(PackageError::Unsupported(_))));
+}
+
+#[test]
+fn kattis_package_with_binary_testcase_is_rejected() {
+    let archive = build_archive(&[
+        ("problem.yaml", b"name: Hello\n".as_slice()),
+        ("problem_statement/problem.md", b"Say hello"),
+        ("problem_statement/logo.png", b"\x89PNG\r\n\x1a\n\xff"),
+        ("data/secret/1.in", b"\xff\xfe"),
+        ("data/secret/1.ans", b"hello"),
+    ]);
+
+    let res = read_package(&archive, &TestcaseLimits::default());
+
+    assert!(
+        matches!(res, Err(PackageError::Invalid(message)) if message == "data/secret/1.in is not a text file")
+    );
+}
+
+#[test]
+fn polygon_package_is_imported() {
+    let problem_xml = r#"<?xml version="1.0" encoding="utf-8"?>
+<problem short-name="sum">
+  <names>
+    <name language="english" value="Sum"/>
+  </names>
+  <judging>
+    <testset name="tests">
+      <time-limit>1000</time-limit>
+      <memory-limit>268435456</memory-limit>
+      <test-count>2</test-count>
+      <input-path-pattern>tests/%02d</input-path-pattern>
+      <answer-path-pattern>tests/%02d.a</answer-path-pattern>
+      <tests>
+        <test method="manual" sample="true"/>
+        <test method="generated"/>
+      </tests>
+    </testset>
+  </judging>
+  <assets>
+    <checker name="std::lcmp.cpp" type="testlib"/>
+  </assets>
+</problem>"#;
+    let archive = build_archive(&[
+        ("problem.xml", problem_xml),
+        ("statement-sections/english/legend.tex", "Add two numbers."),
+        ("statement-sections/english/input.tex", "Two integers."),
+        ("tests/01", "1 2"),
+        ("tests/01.a", "3"),
+        ("tests/02", "5 5"),
+        ("tests/02.a", "10"),
+    ]);
+
+    let (format, package) = read_package(&archive, &TestcaseLimits::default()).unwrap();
+
+    assert_eq!(format, PackageFormat::Polygon);
+    assert_eq!(package.title, "Sum");
+    assert_eq!(package.statement, "Add two numbers.\n\nTwo integers.");
+    assert_eq!(package.checker, Checker::Lines);
+    assert_eq!(
+        package.limits,
+        PackageLimits {
+            time_limit_ms: Some(1000),
+            memory_limit_mb: Some(256),
+        }
+    );
+    assert_eq!(
+        package.testcases,
+        vec![testcase("1 2", "3", true), testcase("5 5", "10", false)]
+    );
+}
+
+#[test]
+fn package_unpacking_past_the_budget_is_rejected() {
+    let big = "0".repeat(1000);
+    let archive = build_archive(&[
+        ("problem.yaml", "name: Hello\n"),
+        ("data/secret/1.in", &big),
+        ("data/secret/1.ans", &big),
+        ("data/secret/2.in", &big),
+        ("data/secret/2.ans", &big),
+    ]);
+    let limits = TestcaseLimits {
+        max_unpacked_bytes: 3000,
+        ..TestcaseLimits::default()
+    };
+
+    let res = read_package(&archive, &limits);
+
+    assert!(matches!(res, Err(PackageError::TooLarge(_))));
+}
@@ -0,0 +1,247 @@
+use api::scoreboard::{
+    ContestRules, ContestSubmission, Participant, ScoreboardProblem, build_scoreboard,
+    resolve_order,
+};
+
+fn problems() -> Vec<ScoreboardProblem> {
+    vec![
+        ScoreboardProblem {
+            problem_id: 10,
+            label: "A".into(),
+        },
+        ScoreboardProblem {
+            problem_id: 20,
+            label: "B".into(),
+        },
+    ]
+}
+
+fn participants() -> Vec<Participant> {
+    ["alice", "bob", "carol"]
+        .iter()
+        .enumerate()
+        .map(|(i, name)| Participant {
+            user_id: Some(i as i64 + 1),
+            team_id: None,
+            name: name.to_string(),
+        })
+        .collect()
+}
+
+fn submission(user_id: i64, problem_id: i64, status: &str, minute: i64) -> ContestSubmission {
+    ContestSubmission {
+        user_id,
+        team_id: None,
+        problem_id,
+        status: status.into(),
+        minute,
+        score: if status == "PASSED" { 100 } else { 0 },
+        frozen: false,
+    }
+}
+
+#[test]
+fn icpc_ranks_by_solved_then_penalty() {
+    let submissions = vec![
+        submission(2, 10, "WRONG ANSWER", 5),
+        submission(1, 10, "PASSED", 12),
+        submission(2, 10, "PASSED", 20),
+        submission(2, 20, "PASSED", 30),
+        submission(1, 20, "TIME LIMIT EXCEEDED", 40),
+        submission(1, 20, "PASSED", 50),
+        // after the first accepted submission nothing counts
+        submission(1, 20, "WRONG ANSWER", 55),
+    ];
+
+    let board = build_scoreboard(
+        ContestRules::Icpc,
+        20,
+        &problems(),
+        &participants(),
+        &submissions,
+        false,
+    );
+
+    let ranking: Vec<_> = board
+        .rows
+        .iter()
+        .map(|row| (row.rank, row.name.as_str(), row.solved, row.penalty))
+        .collect();
+    assert_eq!(
+        ranking,
+        vec![(1, "bob", 2, 70), (2, "alice", 2, 82), (3, "carol", 0, 0)]
+    );
+    let alice = &board.rows[1];
+    assert_eq!(alice.problems[1].attempts, 2);
+    assert!(alice.problems[0].first_to_solve);
+    assert!(!board.rows[0].problems[0].first_to_solve);
+    assert!(board.rows[0].problems[1].first_to_solve);
+}
+
+#[test]
+fn icpc_ties_share_a_rank_and_skip_judge_errors() {
+    let submissions = vec![
+        submission(1, 10, "JUDGE ERROR", 1),
+        submission(1, 10, "PASSED", 10),
+        submission(3, 10, "PASSED", 10),
+        submission(2, 20, "PENDING", 11),
+    ];
+
+    let board = build_scoreboard(
+        ContestRules::Icpc,
+        20,
+        &problems(),
+        &participants(),
+        &submissions,
+        false,
+    );
+
+    let ranks: Vec<_> = board
+        .rows
+        .iter()
+        .map(|row| (row.rank, row.name.as_str()))
+        .collect();
+    assert_eq!(ranks, vec![(1, "alice"), (1, "carol"), (3, "bob")]);
+    assert_eq!(board.rows[0].problems[0].attempts, 1);
+    assert_eq!(board.rows[2].problems[1].pending, 1);
+}
+
+#[test]
+fn ioi_sums_best_scores() {
+    let mut partial = submission(2, 20, "WRONG ANSWER", 8);
+    partial.score = 40;
+    let submissions = vec![
+        submission(1, 10, "PASSED", 3),
+        submission(1, 10, "WRONG ANSWER", 4),
+        partial,
+        submission(2, 20, "WRONG ANSWER", 9),
+        submission(3, 99, "PASSED", 9),
+    ];
+
+    let board = build_scoreboard(
+        ContestRules::Ioi,
+        20,
+        &problems(),
+        &participants(),
+        &submissions,
+        false,
+    );
+
+    let ranking: Vec<_> = board
+        .rows
+        .iter()
+        .map(|row| (row.rank, row.name.as_str(), row.score))
+        .collect();
+    assert_eq!(
+        ranking,
+        vec![(1, "alice", 100), (2, "bob", 40), (3, "carol", 0)]
+    );
+    assert_eq!(board.rows[0].problems[0].attempts, 2);
+    assert_eq!(board.rows[1].problems[1].minute, Some(8));
+    assert_eq!(board.rows[0].penalty, 0);
+}
+
+fn frozen(user_id: i64, problem_id: i64, status: &str, minute: i64) -> ContestSubmission {
+    ContestSubmission {
+        frozen: true,
+        ..submission(user_id, problem_id, status, minute)
+    }
+}
+
+#[test]
+fn frozen_submissions_show_as_pending() {
+    let submissions = vec![
+        submission(1, 10, "PASSED", 10),
+        frozen(2, 10, "PASSED", 250),
+        // solved before the freeze, so nothing is hidden
+        frozen(1, 10, "WRONG ANSWER", 260),
+    ];
+
+    let board = build_scoreboard(
+        ContestRules::Icpc,
+        20,
+        &problems(),
+        &participants(),
+        &submissions,
+        true,
+    );
+
+    assert!(board.frozen);
+    let bob = board.rows.iter().find(|row| row.name == "bob").unwrap();
+    assert_eq!(bob.solved, 0);
+    assert_eq!(bob.problems[0].pending, 1);
+    assert_eq!(board.rows[0].name, "alice");
+    assert_eq!(board.rows[0].problems[0].pending, 0);
+}
+
+#[test]
+fn resolve_reveals_from_the_bottom_up() {
+    let submissions = vec![
+        submission(1, 10, "PASSED", 10),
+        submission(2, 10, "PASSED", 20),
+        frozen(2, 20, "PASSED", 250),
+        frozen(3, 10, "WRONG ANSWER", 255),
+        frozen(1, 20, "WRONG ANSWER", 270),
+    ];
+
+    let steps = resolve_order(20, &problems(), &participants(), &submissions);
+
+    let order: Vec<_> = steps
+        .iter()
+        .map(|step| {
+            (
+                step.name.as_str(),
+                step.label.as_str(),
+                step.result.solved,
+                step.rank_before,
+                step.rank_after,
+            )
+        })
+        .collect();
+    assert_eq!(
+        order,
+        vec![
+            ("carol", "A", false, 3, 3),
+            ("bob", "B", true, 2, 1),
+            ("alice", "B", false, 2, 2),
+        ]
+    );
+}
+
+#[test]
+fn team_members_share_a_row() {
+    let mut participants = participants();
+    participants.push(Participant {
+        user_id: None,
+        team_id: Some(7),
+        name: "crabs".into(),
+    });
+    let for_team = |user_id, problem_id, status, minute| ContestSubmission {
+        team_id: Some(7),
+        ..submission(user_id, problem_id, status, minute)
+    };
+    let submissions = vec![
+        for_team(4, 10, "WRONG ANSWER", 3),
+        for_team(5, 10, "PASSED", 6),
+        for_team(4, 20, "PASSED", 9),
+        submission(1, 10, "PASSED", 5),
+    ];
+
+    let board = build_scoreboard(
+        ContestRules::Icpc,
+        20,
+        &problems(),
+        &participants,
+        &submissions,
+        false,
+    );
+
+    let team = &board.rows[0];
+    assert_eq!(
+        (team.name.as_str(), team.team_id, team.user_id),
+        ("crabs", Some(7), None)
+    );
+    assert_eq!((team.solved, team.penalty), (2, 35));
+    assert_eq!(team.problems[0].attempts, 2);
+    assert_eq!(board.rows[1].name, "alice");
+}
@@ -0,0 +1,94 @@
+use std::io::{Cursor, Write};
+
+use api::routes::{TestcaseError, parse_testcase_archive};
+use models::TestcaseLimits;
+use zip::{ZipWriter, write::SimpleFileOptions};
+
+fn build_archive(files: &[(&str, &str)]) -> Vec<u8> {
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    for (name, content) in files {
+        zip.start_file(*name, SimpleFileOptions::default()).unwrap();
+        zip.write_all(content.as_bytes()).unwrap();
+    }
+    zip.finish().unwrap().into_inner()
+}
+
+#[test]
+fn archive_pairs_are_ordered_numerically() {
+    let archive = build_archive(&[
+        ("10.in", "ten"),
+        ("10.ans", "10"),
+        ("tests/2.in", "two"),
+        ("tests/2.ans", "2"),
+    ]);
+
+    let testcases = parse_testcase_archive(&archive, &TestcaseLimits::default()).unwrap();
+
+    assert_eq!(
+        testcases,
+        vec![
+            ("two".to_string(), "2".to_string()),
+            ("ten".to_string(), "10".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn archive_with_unpaired_input_is_rejected() {
+    let archive = build_archive(&[("1.in", "one"), ("1.ans", "1"), ("2.in", "two")]);
+
+    let res = parse_testcase_archive(&archive, &TestcaseLimits::default());
+
+    assert!(matches!(res, Err(TestcaseError::InvalidArchive(_))));
+}
+
+#[test]
+fn archive_with_oversized_testcase_is_rejected() {
+    let archive = build_archive(&[("1.in", "0123456789"), ("1.ans", "1")]);
+    let limits = TestcaseLimits {
+        max_testcase_bytes: 4,
+        ..TestcaseLimits::default()
+    };
+
+    let res = parse_testcase_archive(&archive, &limits);
+
+    assert!(matches!(res, Err(TestcaseError::TooLarge(_))));
+}
+
+#[test]
+fn archive_with_too_many_files_is_rejected() {
+    let archive = build_archive(&[
+        ("1.in", "one"),
+        ("1.ans", "1"),
+        ("2.in", "two"),
+        ("2.ans", "2"),
+        ("3.in", "three"),
+    ]);
+    let limits = TestcaseLimits {
+        max_testcases: 2,
+        ..TestcaseLimits::default()
+    };
+
+    let res = parse_testcase_archive(&archive, &limits);
+
+    assert!(matches!(res, Err(TestcaseError::TooLarge(_))));
+}
+
+#[test]
+fn archive_unpacking_past_the_budget_is_rejected() {
+    let big = "0".repeat(1000);
+    let archive = build_archive(&[
+        ("1.in", &big),
+        ("1.ans", &big),
+        ("2.in", &big),
+        ("2.ans", &big),
+    ]);
+    let limits = TestcaseLimits {
+        max_unpacked_bytes: 3000,
+        ..TestcaseLimits::default()
+    };
+
+    let res = parse_testcase_archive(&archive, &limits);
+
+    assert!(matches!(res, Err(TestcaseError::TooLarge(_))));
+}
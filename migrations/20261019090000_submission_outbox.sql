@@ -0,0 +1,14 @@
+-- Add migration script here
+ALTER TABLE submit_status ADD created_at TIMESTAMPTZ NOT NULL DEFAULT NOW();
+
+CREATE TABLE submission_outbox (
+    id BIGSERIAL PRIMARY KEY,
+    submission_id BIGINT references submit_status(submission_id) NOT NULL,
+    routing_key TEXT NOT NULL,
+    payload BYTEA NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    published_at TIMESTAMPTZ,
+    republish_count INT NOT NULL DEFAULT 0
+);
+
+CREATE INDEX submission_outbox_unpublished ON submission_outbox (id) WHERE published_at IS NULL;
@@ -0,0 +1,5 @@
+-- Add migration script here
+ALTER TABLE submit_status
+ADD judge_worker TEXT,
+ADD judge_attempts INT NOT NULL DEFAULT 0,
+ADD lease_expires_at TIMESTAMPTZ;
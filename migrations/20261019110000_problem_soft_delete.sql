@@ -0,0 +1,2 @@
+-- Add migration script here
+ALTER TABLE problems ADD deleted_at TIMESTAMPTZ;
@@ -0,0 +1,14 @@
+-- Add migration script here
+ALTER TABLE problem_testcases
+ADD testcase_id BIGSERIAL PRIMARY KEY,
+ADD position INT NOT NULL DEFAULT 0,
+ADD is_sample BOOLEAN NOT NULL DEFAULT FALSE;
+
+UPDATE problem_testcases t SET position = n.position
+FROM (
+    SELECT testcase_id, (ROW_NUMBER() OVER (PARTITION BY problem_id ORDER BY testcase_id) - 1)::INT AS position
+    FROM problem_testcases
+) n
+WHERE t.testcase_id = n.testcase_id;
+
+CREATE INDEX problem_testcases_problem_position ON problem_testcases (problem_id, position);
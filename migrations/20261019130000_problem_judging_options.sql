@@ -0,0 +1,5 @@
+-- Add migration script here
+ALTER TABLE problems
+ADD time_limit_ms INT,
+ADD memory_limit_mb INT,
+ADD checker TEXT NOT NULL DEFAULT 'tokens' CHECK (checker IN ('tokens', 'lines', 'exact'));
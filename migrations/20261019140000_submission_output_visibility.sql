@@ -0,0 +1,4 @@
+-- Add migration script here
+ALTER TABLE submit_status
+ADD testcase_number INT,
+ADD output_hidden BOOLEAN NOT NULL DEFAULT FALSE;
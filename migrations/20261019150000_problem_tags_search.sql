@@ -0,0 +1,22 @@
+-- Add migration script here
+CREATE TABLE tags (
+    tag_id BIGSERIAL PRIMARY KEY,
+    name TEXT NOT NULL UNIQUE
+);
+
+CREATE TABLE problem_tags (
+    problem_id BIGINT references problems(problem_id) NOT NULL,
+    tag_id BIGINT references tags(tag_id) ON DELETE CASCADE NOT NULL,
+    PRIMARY KEY (problem_id, tag_id)
+);
+
+CREATE INDEX problem_tags_tag ON problem_tags (tag_id);
+
+ALTER TABLE problems
+ADD created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+ADD search_vector TSVECTOR GENERATED ALWAYS AS (
+    setweight(to_tsvector('english', title), 'A') || setweight(to_tsvector('english', statement), 'B')
+) STORED;
+
+CREATE INDEX problems_search ON problems USING GIN (search_vector);
+CREATE INDEX submit_status_problem_status ON submit_status (problem_id, status);
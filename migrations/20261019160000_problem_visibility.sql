@@ -0,0 +1,11 @@
+-- Add migration script here
+ALTER TABLE problems
+ADD visibility TEXT NOT NULL DEFAULT 'published' CHECK (visibility IN ('draft', 'review', 'published', 'archived')),
+ADD published_at TIMESTAMPTZ;
+
+UPDATE problems SET published_at = created_at;
+
+-- existing problems stay public, new ones start as drafts
+ALTER TABLE problems ALTER COLUMN visibility SET DEFAULT 'draft';
+
+CREATE INDEX problems_visibility ON problems (visibility) WHERE deleted_at IS NULL;
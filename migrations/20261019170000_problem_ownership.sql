@@ -0,0 +1,14 @@
+-- Add migration script here
+-- problems created before ownership existed have no owner and are managed by Admins
+ALTER TABLE problems ADD owner_id BIGINT references users(user_id);
+
+CREATE TABLE problem_collaborators (
+    problem_id BIGINT references problems(problem_id) NOT NULL,
+    user_id BIGINT references users(user_id) NOT NULL,
+    role TEXT NOT NULL CHECK (role IN ('coauthor', 'tester')),
+    added_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (problem_id, user_id)
+);
+
+CREATE INDEX problem_collaborators_user ON problem_collaborators (user_id);
+CREATE INDEX problems_owner ON problems (owner_id);
@@ -0,0 +1,13 @@
+-- Add migration script here
+-- reference runs go through the judging pipeline as submissions, kept out of solve counts
+ALTER TABLE submit_status ADD is_reference BOOLEAN NOT NULL DEFAULT FALSE;
+
+ALTER TABLE problems
+    ADD reference_code TEXT,
+    ADD reference_env TEXT,
+    ADD reference_submission_id BIGINT references submit_status(submission_id),
+    -- bumped when the time limit or checker changes, older reference runs no longer count
+    ADD judging_updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW();
+
+-- bumped when a testcase is added or replaced, filling outputs from the reference does not
+ALTER TABLE problem_testcases ADD updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW();
@@ -0,0 +1,14 @@
+-- Add migration script here
+-- validator runs use the judging pipeline too, so submissions get a kind instead of a reference flag
+ALTER TABLE submit_status ADD kind TEXT NOT NULL DEFAULT 'submission' CHECK (kind IN ('submission', 'reference', 'validation'));
+UPDATE submit_status SET kind = 'reference' WHERE is_reference;
+ALTER TABLE submit_status DROP COLUMN is_reference;
+
+ALTER TABLE problems
+    ADD validator_code TEXT,
+    ADD validator_env TEXT;
+
+-- only valid testcases are judged or shown as samples
+ALTER TABLE problem_testcases
+    ADD validation TEXT NOT NULL DEFAULT 'valid' CHECK (validation IN ('pending', 'valid', 'invalid')),
+    ADD validation_message TEXT;
@@ -0,0 +1,16 @@
+-- Add migration script here
+CREATE TABLE problem_generators (
+    problem_id BIGINT references problems(problem_id) NOT NULL,
+    name TEXT NOT NULL,
+    code TEXT NOT NULL,
+    env TEXT NOT NULL,
+    PRIMARY KEY (problem_id, name)
+);
+
+ALTER TABLE problems ADD generator_script TEXT;
+
+-- generated testcases are replaced as a whole each time the script runs
+ALTER TABLE problem_testcases ADD generated BOOLEAN NOT NULL DEFAULT FALSE;
+
+ALTER TABLE submit_status DROP CONSTRAINT submit_status_kind_check;
+ALTER TABLE submit_status ADD CONSTRAINT submit_status_kind_check CHECK (kind IN ('submission', 'reference', 'validation', 'generation'));
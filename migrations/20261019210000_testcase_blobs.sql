@@ -0,0 +1,11 @@
+-- Add migration script here
+CREATE TABLE blobs (
+    hash TEXT PRIMARY KEY,
+    size BIGINT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- a testcase part lives either inline in its TEXT column or in blob storage
+ALTER TABLE problem_testcases
+    ADD input_blob TEXT REFERENCES blobs(hash),
+    ADD output_blob TEXT REFERENCES blobs(hash);
@@ -0,0 +1,34 @@
+-- Add migration script here
+CREATE TABLE contests (
+    contest_id BIGSERIAL PRIMARY KEY,
+    title TEXT NOT NULL,
+    description TEXT NOT NULL DEFAULT '',
+    starts_at TIMESTAMPTZ NOT NULL,
+    ends_at TIMESTAMPTZ NOT NULL,
+    owner_id BIGINT references users(user_id),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CHECK (ends_at > starts_at)
+);
+
+CREATE TABLE contest_problems (
+    contest_id BIGINT references contests(contest_id) ON DELETE CASCADE NOT NULL,
+    problem_id BIGINT references problems(problem_id) NOT NULL,
+    label TEXT NOT NULL,
+    position INT NOT NULL,
+    PRIMARY KEY (contest_id, problem_id),
+    UNIQUE (contest_id, label)
+);
+
+CREATE INDEX contest_problems_problem ON contest_problems (problem_id);
+
+CREATE TABLE contest_registrations (
+    contest_id BIGINT references contests(contest_id) ON DELETE CASCADE NOT NULL,
+    user_id BIGINT references users(user_id) NOT NULL,
+    registered_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (contest_id, user_id)
+);
+
+-- practice submissions have no contest
+ALTER TABLE submit_status ADD contest_id BIGINT references contests(contest_id);
+
+CREATE INDEX submit_status_contest ON submit_status (contest_id, created_at) WHERE contest_id IS NOT NULL;
@@ -0,0 +1,4 @@
+-- Add migration script here
+ALTER TABLE contests
+    ADD rules TEXT NOT NULL DEFAULT 'icpc' CHECK (rules IN ('icpc', 'ioi')),
+    ADD penalty_minutes INT NOT NULL DEFAULT 20 CHECK (penalty_minutes >= 0);
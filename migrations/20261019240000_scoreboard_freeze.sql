@@ -0,0 +1,4 @@
+-- Add migration script here
+ALTER TABLE contests
+    ADD freeze_minutes INT NOT NULL DEFAULT 0 CHECK (freeze_minutes >= 0),
+    ADD unfrozen_at TIMESTAMPTZ;
@@ -0,0 +1,25 @@
+-- Add migration script here
+CREATE TABLE problem_subtasks (
+    problem_id BIGINT references problems(problem_id) NOT NULL,
+    name TEXT NOT NULL,
+    position INT NOT NULL,
+    points INT NOT NULL CHECK (points >= 0),
+    depends_on TEXT[] NOT NULL DEFAULT '{}',
+    skip_on_failure BOOLEAN NOT NULL DEFAULT TRUE,
+    PRIMARY KEY (problem_id, name)
+);
+
+-- testcases outside every subtask are judged but worth no points
+ALTER TABLE problem_testcases ADD subtask TEXT;
+ALTER TABLE problem_testcases ADD FOREIGN KEY (problem_id, subtask) REFERENCES problem_subtasks(problem_id, name);
+
+ALTER TABLE submit_status ADD score INT;
+
+CREATE TABLE submission_subtasks (
+    submission_id BIGINT references submit_status(submission_id) ON DELETE CASCADE NOT NULL,
+    subtask TEXT NOT NULL,
+    points INT NOT NULL,
+    max_points INT NOT NULL,
+    passed BOOLEAN NOT NULL,
+    PRIMARY KEY (submission_id, subtask)
+);
@@ -0,0 +1,10 @@
+-- Add migration script here
+CREATE TABLE virtual_participations (
+    contest_id BIGINT references contests(contest_id) ON DELETE CASCADE NOT NULL,
+    user_id BIGINT references users(user_id) NOT NULL,
+    started_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (contest_id, user_id)
+);
+
+-- virtual submissions count from the participant's own start, not the contest's
+ALTER TABLE submit_status ADD is_virtual BOOLEAN NOT NULL DEFAULT FALSE;
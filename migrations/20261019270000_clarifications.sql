@@ -0,0 +1,29 @@
+-- Add migration script here
+CREATE TABLE contest_clarifications (
+    clarification_id BIGSERIAL PRIMARY KEY,
+    contest_id BIGINT references contests(contest_id) ON DELETE CASCADE NOT NULL,
+    user_id BIGINT references users(user_id) NOT NULL,
+    -- NULL for questions about the contest as a whole
+    problem_id BIGINT references problems(problem_id),
+    question TEXT NOT NULL,
+    answer TEXT,
+    answered_by BIGINT references users(user_id),
+    answered_at TIMESTAMPTZ,
+    broadcast BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX contest_clarifications_contest ON contest_clarifications (contest_id, clarification_id);
+
+CREATE TABLE contest_announcements (
+    announcement_id BIGSERIAL PRIMARY KEY,
+    contest_id BIGINT references contests(contest_id) ON DELETE CASCADE NOT NULL,
+    author_id BIGINT references users(user_id),
+    problem_id BIGINT references problems(problem_id),
+    body TEXT NOT NULL,
+    -- set when the announcement broadcasts the answer to a clarification
+    clarification_id BIGINT references contest_clarifications(clarification_id) ON DELETE CASCADE UNIQUE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX contest_announcements_contest ON contest_announcements (contest_id, announcement_id);
@@ -0,0 +1,34 @@
+-- Add migration script here
+CREATE TABLE teams (
+    team_id BIGSERIAL PRIMARY KEY,
+    name TEXT NOT NULL UNIQUE,
+    captain_id BIGINT references users(user_id) NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE team_members (
+    team_id BIGINT references teams(team_id) ON DELETE CASCADE NOT NULL,
+    user_id BIGINT references users(user_id) NOT NULL,
+    joined_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (team_id, user_id)
+);
+
+CREATE INDEX team_members_user ON team_members (user_id);
+
+CREATE TABLE team_invitations (
+    team_id BIGINT references teams(team_id) ON DELETE CASCADE NOT NULL,
+    user_id BIGINT references users(user_id) NOT NULL,
+    invited_by BIGINT references users(user_id) NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (team_id, user_id)
+);
+
+CREATE TABLE contest_team_registrations (
+    contest_id BIGINT references contests(contest_id) ON DELETE CASCADE NOT NULL,
+    team_id BIGINT references teams(team_id) NOT NULL,
+    registered_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (contest_id, team_id)
+);
+
+-- the submitting user stays in user_id
+ALTER TABLE submit_status ADD team_id BIGINT references teams(team_id);
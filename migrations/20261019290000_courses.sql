@@ -0,0 +1,52 @@
+-- Add migration script here
+CREATE TABLE courses (
+    course_id BIGSERIAL PRIMARY KEY,
+    name TEXT NOT NULL,
+    description TEXT NOT NULL DEFAULT '',
+    owner_id BIGINT references users(user_id) NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE course_members (
+    course_id BIGINT references courses(course_id) ON DELETE CASCADE NOT NULL,
+    user_id BIGINT references users(user_id) NOT NULL,
+    role TEXT NOT NULL CHECK (role IN ('instructor', 'student')),
+    joined_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (course_id, user_id)
+);
+
+CREATE INDEX course_members_user ON course_members (user_id);
+
+-- the course's private problem set, shown to its members whatever the problems' visibility
+CREATE TABLE course_problems (
+    course_id BIGINT references courses(course_id) ON DELETE CASCADE NOT NULL,
+    problem_id BIGINT references problems(problem_id) NOT NULL,
+    position INT NOT NULL,
+    PRIMARY KEY (course_id, problem_id)
+);
+
+CREATE TABLE assignments (
+    assignment_id BIGSERIAL PRIMARY KEY,
+    course_id BIGINT references courses(course_id) ON DELETE CASCADE NOT NULL,
+    title TEXT NOT NULL,
+    description TEXT NOT NULL DEFAULT '',
+    opens_at TIMESTAMPTZ,
+    due_at TIMESTAMPTZ NOT NULL,
+    -- late submissions are taken until then, NULL to take none
+    late_until TIMESTAMPTZ,
+    late_penalty_percent INT NOT NULL DEFAULT 0 CHECK (late_penalty_percent BETWEEN 0 AND 100),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CHECK (opens_at IS NULL OR opens_at < due_at),
+    CHECK (late_until IS NULL OR late_until > due_at)
+);
+
+CREATE TABLE assignment_problems (
+    assignment_id BIGINT references assignments(assignment_id) ON DELETE CASCADE NOT NULL,
+    problem_id BIGINT references problems(problem_id) NOT NULL,
+    position INT NOT NULL,
+    PRIMARY KEY (assignment_id, problem_id)
+);
+
+ALTER TABLE submit_status ADD assignment_id BIGINT references assignments(assignment_id);
+
+CREATE INDEX submit_status_assignment ON submit_status (assignment_id, user_id) WHERE assignment_id IS NOT NULL;
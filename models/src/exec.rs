@@ -1,18 +1,88 @@
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ExecStatus {
+    Pending,
     Passed,
     WrongAnswer,
     MemoryLimitExceeded,
     SegmentationFault,
     TimeLimitExceeded,
+    JudgeError,
 }
 impl From<ExecStatus> for &str {
     fn from(value: ExecStatus) -> Self {
         match value {
+            ExecStatus::Pending => "PENDING",
             ExecStatus::Passed => "PASSED",
             ExecStatus::WrongAnswer => "WRONG ANSWER",
             ExecStatus::MemoryLimitExceeded => "MEMORY LIMIT EXCEEDED",
             ExecStatus::SegmentationFault => "SEGMENTATION FAULT",
             ExecStatus::TimeLimitExceeded => "TIME LIMIT EXCEEDED",
+            ExecStatus::JudgeError => "JUDGE ERROR",
         }
     }
 }
+
+/// How a testcase's output is compared with the expected answer.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Checker {
+    /// Whitespace is ignored entirely.
+    #[default]
+    Tokens,
+    /// Lines must match after trimming trailing whitespace and blank lines at the end.
+    Lines,
+    /// Byte for byte.
+    Exact,
+}
+impl From<Checker> for &str {
+    fn from(value: Checker) -> Self {
+        match value {
+            Checker::Tokens => "tokens",
+            Checker::Lines => "lines",
+            Checker::Exact => "exact",
+        }
+    }
+}
+impl TryFrom<&str> for Checker {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "tokens" => Ok(Checker::Tokens),
+            "lines" => Ok(Checker::Lines),
+            "exact" => Ok(Checker::Exact),
+            _ => Err(format!("Unknown checker {}", value)),
+        }
+    }
+}
+
+/// One line of a generator script: a generator and the arguments it is run with.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct GeneratorInvocation {
+    pub generator: String,
+    pub args: Vec<String>,
+}
+
+/// What a worker task judges.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum TaskKind {
+    /// A user's submission.
+    #[default]
+    Submission,
+    /// A problem's reference solution. With `fill_outputs` its output becomes the
+    /// expected output of every testcase, provided it runs cleanly on all of them.
+    Reference { fill_outputs: bool },
+    /// A problem's input validator, run on every testcase awaiting validation.
+    Validation,
+    /// A problem's generators. Each invocation's output becomes the input of a
+    /// generated testcase, replacing the previously generated ones.
+    Generation {
+        /// Generator code by name.
+        generators: BTreeMap<String, String>,
+        invocations: Vec<GeneratorInvocation>,
+        max_input_bytes: usize,
+    },
+}
@@ -1,8 +1,13 @@
 pub mod email;
 pub mod exec;
+pub mod outbox;
+pub mod scoring;
+pub mod storage;
 pub mod utils;
 
 pub use exec::*;
+pub use scoring::{FULL_SCORE, Subtask, SubtaskScore};
+pub use storage::{BlobStore, StorageConfig};
 
 use std::{collections::HashMap};
 use urlencoding::encode;
@@ -17,16 +22,25 @@ pub struct RuntimeConfig {
     pub compile: Option<String>,
     pub image: String,
     pub timeout: u8,
+    /// Seconds the compile step may take, once per submission.
+    #[serde(default = "default_compile_timeout")]
+    pub compile_timeout: u8,
     pub memory: i64,
     pub env: String,
 }
 
+fn default_compile_timeout() -> u8 {
+    30
+}
+
 #[derive(serde::Serialize, serde::Deserialize, Debug)]
 pub struct WorkerTask {
     pub code: String,
     pub problem_id: i64,
     pub user_id: i64,
     pub submission_id: i64,
+    #[serde(default)]
+    pub kind: TaskKind,
 }
 
 #[derive(serde::Deserialize, PartialEq, Debug)]
@@ -36,6 +50,97 @@ pub struct ApiConfig {
     pub base_url: String,
 }
 
+#[derive(serde::Deserialize, PartialEq, Debug, Clone)]
+#[serde(default)]
+pub struct OutboxConfig {
+    pub poll_interval_ms: u64,
+    pub relay_after_ms: u64,
+    pub batch_size: i64,
+    pub sweep_interval_secs: u64,
+    pub stuck_after_secs: u64,
+    pub max_republish: i32,
+}
+
+impl Default for OutboxConfig {
+    fn default() -> Self {
+        Self {
+            poll_interval_ms: 500,
+            relay_after_ms: 5000,
+            batch_size: 50,
+            sweep_interval_secs: 60,
+            stuck_after_secs: 300,
+            max_republish: 3,
+        }
+    }
+}
+
+#[derive(serde::Deserialize, PartialEq, Debug, Clone)]
+#[serde(default)]
+pub struct ScoreboardConfig {
+    /// How long a rendered scoreboard is served from Redis, 0 to always recompute.
+    pub cache_secs: u64,
+}
+
+impl Default for ScoreboardConfig {
+    fn default() -> Self {
+        Self { cache_secs: 5 }
+    }
+}
+
+#[derive(serde::Deserialize, PartialEq, Debug, Clone)]
+#[serde(default)]
+pub struct LeaseConfig {
+    pub worker_id: Option<String>,
+    pub lease_secs: u64,
+}
+
+impl Default for LeaseConfig {
+    fn default() -> Self {
+        Self {
+            worker_id: None,
+            lease_secs: 120,
+        }
+    }
+}
+
+#[derive(serde::Deserialize, PartialEq, Debug, Clone)]
+#[serde(default)]
+pub struct RetryConfig {
+    pub delays_ms: Vec<u64>,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            delays_ms: vec![1000, 5000, 30000],
+        }
+    }
+}
+
+#[derive(serde::Deserialize, PartialEq, Debug, Clone)]
+#[serde(default)]
+pub struct TestcaseLimits {
+    pub max_testcase_bytes: usize,
+    pub max_archive_bytes: usize,
+    pub max_testcases: usize,
+    /// Total bytes an uploaded archive may unpack to, whatever its compressed size.
+    pub max_unpacked_bytes: usize,
+    /// Upper bound for a testcase input or output kept in blob storage.
+    pub max_blob_bytes: u64,
+}
+
+impl Default for TestcaseLimits {
+    fn default() -> Self {
+        Self {
+            max_testcase_bytes: 8 * 1024 * 1024,
+            max_archive_bytes: 64 * 1024 * 1024,
+            max_testcases: 200,
+            max_unpacked_bytes: 256 * 1024 * 1024,
+            max_blob_bytes: 1024 * 1024 * 1024,
+        }
+    }
+}
+
 #[derive(serde::Deserialize, PartialEq, Debug)]
 pub struct DatabaseConfig {
     pub user: String,
@@ -72,6 +177,12 @@ pub struct RabbitMQConfig {
     pub host: String,
     pub port: u16,
     pub vhost: String,
+    #[serde(default = "default_channel_pool_size")]
+    pub channel_pool_size: usize,
+}
+
+fn default_channel_pool_size() -> usize {
+    8
 }
 
 impl RabbitMQConfig {
@@ -0,0 +1,20 @@
+use sqlx::PgConnection;
+
+/// Records a task in the outbox. Must run inside the transaction that creates
+/// the submission so the two are committed together.
+pub async fn enqueue(
+    conn: &mut PgConnection,
+    submission_id: i64,
+    routing_key: &str,
+    payload: &[u8],
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        "INSERT INTO submission_outbox (submission_id, routing_key, payload) VALUES ($1, $2, $3)",
+        submission_id,
+        routing_key,
+        payload
+    )
+    .execute(conn)
+    .await?;
+    Ok(())
+}
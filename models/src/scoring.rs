@@ -0,0 +1,124 @@
+use std::collections::HashMap;
+
+/// Score of a passing submission to a problem without subtasks.
+pub const FULL_SCORE: i32 = 100;
+
+/// A group of testcases worth `points` when all of them pass.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Subtask {
+    pub name: String,
+    pub points: i32,
+    /// Subtasks that must score for this one to score.
+    pub depends_on: Vec<String>,
+    /// Skip the rest of the subtask's testcases after one fails.
+    pub skip_on_failure: bool,
+}
+
+/// What a submission earned on one subtask.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct SubtaskScore {
+    pub name: String,
+    pub points: i32,
+    pub max_points: i32,
+    /// Whether every testcase of the subtask itself passed.
+    pub passed: bool,
+}
+
+/// Checks that subtask dependencies name other subtasks of the problem and
+/// do not form a cycle.
+pub fn check_subtask_dependencies(subtasks: &[Subtask]) -> Result<(), String> {
+    let index: HashMap<&str, usize> = subtasks
+        .iter()
+        .enumerate()
+        .map(|(i, subtask)| (subtask.name.as_str(), i))
+        .collect();
+    for subtask in subtasks {
+        if let Some(missing) = subtask
+            .depends_on
+            .iter()
+            .find(|name| !index.contains_key(name.as_str()))
+        {
+            return Err(format!(
+                "Subtask {} depends on unknown subtask {}",
+                subtask.name, missing
+            ));
+        }
+    }
+
+    // 0 unvisited, 1 on the current path, 2 done
+    fn visit(
+        i: usize,
+        subtasks: &[Subtask],
+        index: &HashMap<&str, usize>,
+        state: &mut [u8],
+    ) -> Result<(), String> {
+        match state[i] {
+            1 => {
+                return Err(format!(
+                    "Subtask {} is part of a dependency cycle",
+                    subtasks[i].name
+                ));
+            }
+            2 => return Ok(()),
+            _ => {}
+        }
+        state[i] = 1;
+        for name in &subtasks[i].depends_on {
+            visit(index[name.as_str()], subtasks, index, state)?;
+        }
+        state[i] = 2;
+        Ok(())
+    }
+    let mut state = vec![0u8; subtasks.len()];
+    for i in 0..subtasks.len() {
+        visit(i, subtasks, &index, &mut state)?;
+    }
+    Ok(())
+}
+
+/// Scores every subtask, all or nothing: a subtask earns its points when each of its
+/// testcases passed and every subtask it depends on, directly or not, earned theirs.
+/// `passed` tells whether a subtask's own testcases all passed; subtasks missing
+/// from it have no testcases and pass.
+pub fn score_subtasks(subtasks: &[Subtask], passed: &HashMap<String, bool>) -> Vec<SubtaskScore> {
+    fn scores(
+        name: &str,
+        subtasks: &HashMap<&str, &Subtask>,
+        passed: &HashMap<String, bool>,
+        memo: &mut HashMap<String, bool>,
+    ) -> bool {
+        if let Some(&scored) = memo.get(name) {
+            return scored;
+        }
+        // a dependency cycle never scores
+        memo.insert(name.to_string(), false);
+        let scored = passed.get(name).copied().unwrap_or(true)
+            && subtasks.get(name).is_some_and(|subtask| {
+                subtask
+                    .depends_on
+                    .iter()
+                    .all(|dependency| scores(dependency, subtasks, passed, memo))
+            });
+        memo.insert(name.to_string(), scored);
+        scored
+    }
+
+    let by_name: HashMap<&str, &Subtask> = subtasks
+        .iter()
+        .map(|subtask| (subtask.name.as_str(), subtask))
+        .collect();
+    let mut memo = HashMap::new();
+    subtasks
+        .iter()
+        .map(|subtask| SubtaskScore {
+            name: subtask.name.clone(),
+            points: if scores(&subtask.name, &by_name, passed, &mut memo) {
+                subtask.points
+            } else {
+                0
+            },
+            max_points: subtask.points,
+            passed: passed.get(&subtask.name).copied().unwrap_or(true),
+        })
+        .collect()
+}
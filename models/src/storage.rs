@@ -0,0 +1,341 @@
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+
+use futures_util::TryStreamExt;
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
+use tokio_util::io::{ReaderStream, StreamReader};
+
+/// Where testcase blobs are kept.
+#[derive(serde::Deserialize, PartialEq, Debug, Clone)]
+#[serde(tag = "backend", rename_all = "lowercase")]
+pub enum StorageConfig {
+    /// A directory on the local filesystem, shared by the api and the workers.
+    Local { root: PathBuf },
+    /// An S3-compatible bucket, addressed path-style so MinIO works too.
+    S3(S3Config),
+}
+
+impl Default for StorageConfig {
+    fn default() -> Self {
+        Self::Local {
+            root: PathBuf::from("./blobs"),
+        }
+    }
+}
+
+#[derive(serde::Deserialize, PartialEq, Debug, Clone)]
+pub struct S3Config {
+    pub endpoint: String,
+    pub bucket: String,
+    #[serde(default = "default_region")]
+    pub region: String,
+    pub access_key: String,
+    pub secret_key: String,
+}
+
+fn default_region() -> String {
+    "us-east-1".into()
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum StorageError {
+    #[error("Storage io error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Storage request failed: {0}")]
+    Request(#[from] reqwest::Error),
+
+    #[error("Storage responded with {0}")]
+    Status(reqwest::StatusCode),
+
+    #[error("No such blob {0}")]
+    NotFound(String),
+
+    #[error("Invalid storage endpoint {0}")]
+    InvalidEndpoint(String),
+
+    #[error("Invalid blob hash {0}")]
+    InvalidHash(String),
+
+    #[error("Blob exceeds {0} bytes")]
+    TooLarge(u64),
+}
+
+/// A blob as stored: its SHA-256 in lowercase hex and its size in bytes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StoredBlob {
+    pub hash: String,
+    pub size: u64,
+}
+
+pub type BlobReader = Pin<Box<dyn AsyncRead + Send>>;
+
+/// Whether `hash` can name a blob. Hashes become paths and object keys, so
+/// anything but a SHA-256 in lowercase hex is refused.
+pub fn is_blob_hash(hash: &str) -> bool {
+    hash.len() == 64 && hash.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+}
+
+fn blob_key(hash: &str) -> Result<String, StorageError> {
+    if !is_blob_hash(hash) {
+        return Err(StorageError::InvalidHash(hash.to_string()));
+    }
+    Ok(format!("{}/{}", &hash[..2], hash))
+}
+
+/// Content-addressed blob storage. Blobs are written once under their hash,
+/// so storing the same content twice keeps a single copy.
+#[derive(Clone)]
+pub enum BlobStore {
+    Local(LocalStore),
+    S3(S3Store),
+}
+
+impl BlobStore {
+    pub fn new(config: &StorageConfig) -> Self {
+        match config {
+            StorageConfig::Local { root } => Self::Local(LocalStore { root: root.clone() }),
+            StorageConfig::S3(config) => Self::S3(S3Store {
+                config: config.clone(),
+                client: reqwest::Client::new(),
+            }),
+        }
+    }
+
+    /// Streams `reader` into the store and returns the blob it became.
+    /// Reading stops with [`StorageError::TooLarge`] past `max_bytes`.
+    pub async fn put(
+        &self,
+        reader: impl AsyncRead + Unpin,
+        max_bytes: u64,
+    ) -> Result<StoredBlob, StorageError> {
+        let staging = match self {
+            Self::Local(store) => store.root.join("tmp"),
+            Self::S3(_) => std::env::temp_dir(),
+        };
+        let spooled = spool(reader, &staging, max_bytes).await?;
+        let res = match self {
+            Self::Local(store) => store.commit(&spooled).await,
+            Self::S3(store) => store.commit(&spooled).await,
+        };
+        // whatever the backend did not move into place is left to remove
+        let _ = tokio::fs::remove_file(&spooled.path).await;
+        res.map(|()| spooled.blob)
+    }
+
+    /// Opens a blob for streaming.
+    pub async fn open(&self, hash: &str) -> Result<BlobReader, StorageError> {
+        let key = blob_key(hash)?;
+        match self {
+            Self::Local(store) => match tokio::fs::File::open(store.root.join(key)).await {
+                Ok(file) => Ok(Box::pin(file)),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                    Err(StorageError::NotFound(hash.to_string()))
+                }
+                Err(e) => Err(e.into()),
+            },
+            Self::S3(store) => store.get(hash, &key).await,
+        }
+    }
+
+    /// Reads a whole blob into memory, for contents that are needed at once anyway.
+    pub async fn read(&self, hash: &str) -> Result<Vec<u8>, StorageError> {
+        let mut content = Vec::new();
+        self.open(hash).await?.read_to_end(&mut content).await?;
+        Ok(content)
+    }
+}
+
+struct Spooled {
+    path: PathBuf,
+    blob: StoredBlob,
+}
+
+/// Copies `reader` to a temporary file under `dir`, hashing it on the way.
+async fn spool(
+    mut reader: impl AsyncRead + Unpin,
+    dir: &Path,
+    max_bytes: u64,
+) -> Result<Spooled, StorageError> {
+    tokio::fs::create_dir_all(dir).await?;
+    let path = dir.join(format!("upload-{}", uuid::Uuid::new_v4()));
+    let mut file = tokio::fs::File::create(&path).await?;
+    let mut hasher = Sha256::new();
+    let mut size = 0u64;
+    let mut buf = vec![0u8; 64 * 1024];
+    let res = loop {
+        let n = match reader.read(&mut buf).await {
+            Ok(0) => break file.flush().await.map_err(StorageError::from),
+            Ok(n) => n,
+            Err(e) => break Err(e.into()),
+        };
+        size += n as u64;
+        if size > max_bytes {
+            break Err(StorageError::TooLarge(max_bytes));
+        }
+        hasher.update(&buf[..n]);
+        if let Err(e) = file.write_all(&buf[..n]).await {
+            break Err(e.into());
+        }
+    };
+    if let Err(e) = res {
+        let _ = tokio::fs::remove_file(&path).await;
+        return Err(e);
+    }
+    Ok(Spooled {
+        path,
+        blob: StoredBlob {
+            hash: format!("{:x}", hasher.finalize()),
+            size,
+        },
+    })
+}
+
+#[derive(Clone)]
+pub struct LocalStore {
+    root: PathBuf,
+}
+
+impl LocalStore {
+    async fn commit(&self, spooled: &Spooled) -> Result<(), StorageError> {
+        let path = self.root.join(blob_key(&spooled.blob.hash)?);
+        if tokio::fs::try_exists(&path).await? {
+            return Ok(());
+        }
+        if let Some(dir) = path.parent() {
+            tokio::fs::create_dir_all(dir).await?;
+        }
+        // the staging directory lives under the root, so this is a rename on one filesystem
+        tokio::fs::rename(&spooled.path, &path).await?;
+        Ok(())
+    }
+}
+
+#[derive(Clone)]
+pub struct S3Store {
+    config: S3Config,
+    client: reqwest::Client,
+}
+
+const UNSIGNED_PAYLOAD: &str = "UNSIGNED-PAYLOAD";
+
+impl S3Store {
+    fn url(&self, key: &str) -> String {
+        format!(
+            "{}/{}/{}",
+            self.config.endpoint.trim_end_matches('/'),
+            self.config.bucket,
+            key
+        )
+    }
+
+    /// A request signed with AWS Signature Version 4. `payload_hash` is the
+    /// body's SHA-256, which for a blob is its own name.
+    fn request(
+        &self,
+        method: reqwest::Method,
+        key: &str,
+        payload_hash: &str,
+    ) -> Result<reqwest::RequestBuilder, StorageError> {
+        let url = reqwest::Url::parse(&self.url(key))
+            .map_err(|_| StorageError::InvalidEndpoint(self.config.endpoint.clone()))?;
+        let host = match (url.host_str(), url.port()) {
+            (Some(host), Some(port)) => format!("{}:{}", host, port),
+            (Some(host), None) => host.to_string(),
+            (None, _) => return Err(StorageError::InvalidEndpoint(self.config.endpoint.clone())),
+        };
+        let now = chrono::Utc::now();
+        let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+        let date = now.format("%Y%m%d").to_string();
+        let scope = format!("{}/{}/s3/aws4_request", date, self.config.region);
+        let signed_headers = "host;x-amz-content-sha256;x-amz-date";
+
+        let canonical_request = format!(
+            "{}\n{}\n\nhost:{}\nx-amz-content-sha256:{}\nx-amz-date:{}\n\n{}\n{}",
+            method,
+            url.path(),
+            host,
+            payload_hash,
+            amz_date,
+            signed_headers,
+            payload_hash
+        );
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{}\n{}\n{:x}",
+            amz_date,
+            scope,
+            Sha256::digest(canonical_request.as_bytes())
+        );
+        let mut signing_key = format!("AWS4{}", self.config.secret_key).into_bytes();
+        for part in [
+            date.as_str(),
+            self.config.region.as_str(),
+            "s3",
+            "aws4_request",
+        ] {
+            signing_key = hmac(&signing_key, part.as_bytes());
+        }
+        let signature = hmac(&signing_key, string_to_sign.as_bytes())
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect::<String>();
+
+        Ok(self
+            .client
+            .request(method, url)
+            .header("x-amz-content-sha256", payload_hash)
+            .header("x-amz-date", amz_date)
+            .header(
+                reqwest::header::AUTHORIZATION,
+                format!(
+                    "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+                    self.config.access_key, scope, signed_headers, signature
+                ),
+            ))
+    }
+
+    async fn commit(&self, spooled: &Spooled) -> Result<(), StorageError> {
+        let key = blob_key(&spooled.blob.hash)?;
+        let head = self
+            .request(reqwest::Method::HEAD, &key, UNSIGNED_PAYLOAD)?
+            .send()
+            .await?;
+        if head.status().is_success() {
+            return Ok(());
+        }
+
+        let file = tokio::fs::File::open(&spooled.path).await?;
+        let res = self
+            .request(reqwest::Method::PUT, &key, &spooled.blob.hash)?
+            .header(reqwest::header::CONTENT_LENGTH, spooled.blob.size)
+            .body(reqwest::Body::wrap_stream(ReaderStream::new(file)))
+            .send()
+            .await?;
+        if !res.status().is_success() {
+            return Err(StorageError::Status(res.status()));
+        }
+        Ok(())
+    }
+
+    async fn get(&self, hash: &str, key: &str) -> Result<BlobReader, StorageError> {
+        let res = self
+            .request(reqwest::Method::GET, key, UNSIGNED_PAYLOAD)?
+            .send()
+            .await?;
+        match res.status() {
+            reqwest::StatusCode::NOT_FOUND => Err(StorageError::NotFound(hash.to_string())),
+            status if !status.is_success() => Err(StorageError::Status(status)),
+            _ => Ok(Box::pin(StreamReader::new(
+                res.bytes_stream().map_err(std::io::Error::other),
+            ))),
+        }
+    }
+}
+
+fn hmac(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts any key length");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
@@ -0,0 +1,153 @@
+use std::collections::HashMap;
+use std::net::TcpListener;
+use std::sync::Mutex;
+
+use actix_web::{App, HttpRequest, HttpResponse, HttpServer, web};
+use models::storage::{S3Config, StorageError};
+use models::{BlobStore, StorageConfig};
+
+const HELLO_HASH: &str = "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824";
+
+fn local_store() -> (BlobStore, std::path::PathBuf) {
+    let root = std::env::temp_dir().join(format!("blobs-{}", uuid::Uuid::new_v4()));
+    (
+        BlobStore::new(&StorageConfig::Local { root: root.clone() }),
+        root,
+    )
+}
+
+#[tokio::test]
+async fn local_store_is_content_addressed() {
+    let (store, root) = local_store();
+
+    let first = store.put(&b"hello"[..], 1024).await.unwrap();
+    let second = store.put(&b"hello"[..], 1024).await.unwrap();
+
+    assert_eq!(first.hash, HELLO_HASH);
+    assert_eq!(first.size, 5);
+    assert_eq!(first, second);
+    assert!(root.join("2c").join(HELLO_HASH).is_file());
+    assert_eq!(store.read(HELLO_HASH).await.unwrap(), b"hello");
+    assert_eq!(std::fs::read_dir(root.join("tmp")).unwrap().count(), 0);
+}
+
+#[tokio::test]
+async fn local_store_rejects_oversized_blobs() {
+    let (store, root) = local_store();
+
+    let res = store.put(&b"0123456789"[..], 4).await;
+
+    assert!(matches!(res, Err(StorageError::TooLarge(4))));
+    assert_eq!(std::fs::read_dir(root.join("tmp")).unwrap().count(), 0);
+}
+
+#[tokio::test]
+async fn blob_hashes_cannot_escape_the_store() {
+    let (store, _) = local_store();
+
+    let res = store.open("../../etc/passwd").await;
+
+    assert!(matches!(res, Err(StorageError::InvalidHash(_))));
+}
+
+/// Objects of a stand-in for an S3-compatible server, by `bucket/key`.
+#[derive(Default)]
+struct Bucket(Mutex<HashMap<String, Vec<u8>>>);
+
+fn signed(req: &HttpRequest) -> bool {
+    req.headers()
+        .get("authorization")
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("AWS4-HMAC-SHA256 Credential=minio/"))
+}
+
+async fn put_object(
+    req: HttpRequest,
+    path: web::Path<(String, String, String)>,
+    body: web::Bytes,
+    bucket: web::Data<Bucket>,
+) -> HttpResponse {
+    let (name, prefix, hash) = path.into_inner();
+    // a blob's payload hash is its own name
+    let claimed = req
+        .headers()
+        .get("x-amz-content-sha256")
+        .and_then(|value| value.to_str().ok());
+    if !signed(&req) || claimed != Some(hash.as_str()) {
+        return HttpResponse::Forbidden().finish();
+    }
+    bucket
+        .0
+        .lock()
+        .unwrap()
+        .insert(format!("{}/{}/{}", name, prefix, hash), body.to_vec());
+    HttpResponse::Ok().finish()
+}
+
+async fn get_object(
+    req: HttpRequest,
+    path: web::Path<(String, String, String)>,
+    bucket: web::Data<Bucket>,
+) -> HttpResponse {
+    if !signed(&req) {
+        return HttpResponse::Forbidden().finish();
+    }
+    let (name, prefix, hash) = path.into_inner();
+    match bucket
+        .0
+        .lock()
+        .unwrap()
+        .get(&format!("{}/{}/{}", name, prefix, hash))
+    {
+        Some(content) => HttpResponse::Ok().body(content.clone()),
+        None => HttpResponse::NotFound().finish(),
+    }
+}
+
+fn spawn_bucket() -> (String, web::Data<Bucket>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let bucket = web::Data::new(Bucket::default());
+    let data = bucket.clone();
+    let server = HttpServer::new(move || {
+        App::new().app_data(data.clone()).service(
+            web::resource("/{bucket}/{prefix}/{hash}")
+                .route(web::put().to(put_object))
+                .route(web::get().to(get_object))
+                .route(web::head().to(get_object)),
+        )
+    })
+    .listen(listener)
+    .unwrap()
+    .run();
+    tokio::spawn(server);
+    (format!("http://127.0.0.1:{}", port), bucket)
+}
+
+#[tokio::test]
+async fn s3_store_round_trips_through_the_bucket() {
+    let (endpoint, bucket) = spawn_bucket();
+    let store = BlobStore::new(&StorageConfig::S3(S3Config {
+        endpoint,
+        bucket: "judge".into(),
+        region: "us-east-1".into(),
+        access_key: "minio".into(),
+        secret_key: "minio123".into(),
+    }));
+
+    let blob = store.put(&b"hello"[..], 1024).await.unwrap();
+
+    assert_eq!(blob.hash, HELLO_HASH);
+    assert!(
+        bucket
+            .0
+            .lock()
+            .unwrap()
+            .contains_key(&format!("judge/2c/{}", HELLO_HASH))
+    );
+    assert_eq!(store.read(HELLO_HASH).await.unwrap(), b"hello");
+    assert!(matches!(
+        store.open(&"0".repeat(64)).await,
+        Err(StorageError::NotFound(_))
+    ));
+}
@@ -0,0 +1,91 @@
+use std::collections::HashMap;
+
+use models::Subtask;
+use models::scoring::{check_subtask_dependencies, score_subtasks};
+
+fn subtask(name: &str, points: i32, depends_on: &[&str]) -> Subtask {
+    Subtask {
+        name: name.into(),
+        points,
+        depends_on: depends_on.iter().map(|name| name.to_string()).collect(),
+        skip_on_failure: true,
+    }
+}
+
+fn passed(results: &[(&str, bool)]) -> HashMap<String, bool> {
+    results
+        .iter()
+        .map(|(name, passed)| (name.to_string(), *passed))
+        .collect()
+}
+
+#[test]
+fn subtasks_score_all_or_nothing() {
+    let subtasks = vec![
+        subtask("small", 20, &[]),
+        subtask("medium", 30, &[]),
+        subtask("large", 50, &[]),
+    ];
+
+    let scores = score_subtasks(
+        &subtasks,
+        &passed(&[("small", true), ("medium", false), ("large", true)]),
+    );
+
+    let points: Vec<_> = scores
+        .iter()
+        .map(|score| (score.name.as_str(), score.points, score.max_points))
+        .collect();
+    assert_eq!(
+        points,
+        vec![("small", 20, 20), ("medium", 0, 30), ("large", 50, 50)]
+    );
+}
+
+#[test]
+fn failed_dependencies_cancel_points_transitively() {
+    let subtasks = vec![
+        subtask("small", 20, &[]),
+        subtask("medium", 30, &["small"]),
+        subtask("large", 50, &["medium"]),
+        // no testcases of its own
+        subtask("bonus", 10, &[]),
+    ];
+
+    let scores = score_subtasks(
+        &subtasks,
+        &passed(&[("small", false), ("medium", true), ("large", true)]),
+    );
+
+    let points: Vec<_> = scores
+        .iter()
+        .map(|score| (score.name.as_str(), score.points, score.passed))
+        .collect();
+    assert_eq!(
+        points,
+        vec![
+            ("small", 0, false),
+            ("medium", 0, true),
+            ("large", 0, true),
+            ("bonus", 10, true),
+        ]
+    );
+}
+
+#[test]
+fn dependencies_must_exist_and_not_form_a_cycle() {
+    assert!(check_subtask_dependencies(&[subtask("a", 10, &[]), subtask("b", 10, &["a"])]).is_ok());
+    assert_eq!(
+        check_subtask_dependencies(&[subtask("a", 10, &["missing"])]),
+        Err("Subtask a depends on unknown subtask missing".to_string())
+    );
+    assert!(
+        check_subtask_dependencies(&[
+            subtask("a", 10, &["c"]),
+            subtask("b", 10, &["a"]),
+            subtask("c", 10, &["b"]),
+        ])
+        .unwrap_err()
+        .contains("dependency cycle")
+    );
+}
@@ -1,10 +1,10 @@
 use bollard::{
     Docker,
     exec::StartExecResults,
-    secret::{ContainerCreateBody, HostConfig},
+    secret::{ContainerCreateBody, ContainerUpdateBody, HostConfig},
 };
 use futures::StreamExt;
-use tokio::io::AsyncWriteExt;
+use tokio::io::{AsyncRead, AsyncWriteExt};
 
 pub async fn create_container(
     docker: &Docker,
@@ -45,22 +45,44 @@ pub async fn create_container(
     Ok(id)
 }
 
+/// Caps the memory of a running container, swap included.
+pub async fn set_memory_limit(
+    docker: &Docker,
+    id: &str,
+    memory: i64,
+) -> Result<(), bollard::errors::Error> {
+    docker
+        .update_container(
+            id,
+            ContainerUpdateBody {
+                memory: Some(memory),
+                memory_swap: Some(memory),
+                ..Default::default()
+            },
+        )
+        .await
+}
+
+#[derive(Clone)]
 pub struct ExecOutput {
     pub output: String,
     pub exit_code: i64,
 }
+/// Runs `cmd` in the container with `testcase` streamed to its stdin. Its stderr is
+/// interleaved into the output only when `stderr` is set.
 pub async fn run_exec(
     docker: &Docker,
     id: &str,
     cmd: Vec<String>,
-    testcase: &str,
+    mut testcase: impl AsyncRead + Unpin,
+    stderr: bool,
 ) -> Result<ExecOutput, bollard::errors::Error> {
     let exec_id = docker
         .create_exec(
             id,
             bollard::models::ExecConfig {
                 attach_stdout: Some(true),
-                attach_stderr: Some(true),
+                attach_stderr: Some(stderr),
                 attach_stdin: Some(true),
                 cmd: Some(cmd),
                 ..Default::default()
@@ -76,12 +98,21 @@ pub async fn run_exec(
     {
         let mut input_stream = input;
 
-        input_stream.write_all(testcase.as_bytes()).await?;
-
-        input_stream.shutdown().await?;
-
-        while let Some(Ok(msg)) = output.next().await {
-            exec_output.push_str(&msg.to_string());
+        // stdin is written while output is read, a program may answer before it read everything
+        let write = async {
+            tokio::io::copy(&mut testcase, &mut input_stream).await?;
+            input_stream.shutdown().await
+        };
+        let read = async {
+            while let Some(Ok(msg)) = output.next().await {
+                exec_output.push_str(&msg.to_string());
+            }
+        };
+        let (written, ()) = tokio::join!(write, read);
+        match written {
+            // the program exited without reading all of its input
+            Err(e) if e.kind() == std::io::ErrorKind::BrokenPipe => {}
+            res => res?,
         }
     } else {
         // TODO handle detach case
@@ -16,6 +16,13 @@ pub enum ExecError {
         bollard::errors::Error,
     ),
 
+    #[error("Storage error :{0}")]
+    StorageError(
+        #[from]
+        #[source]
+        models::storage::StorageError,
+    ),
+
     #[error("RabbitMQ error :{0}")]
     QueueError(
         #[from]
@@ -25,6 +32,9 @@ pub enum ExecError {
 
     #[error("Error parsing rabbitmq message")]
     ParseError,
+
+    #[error("Broker refused retry message")]
+    RetryNacked,
     #[error("")]
     PoolError(#[from] deadpool::managed::PoolError<bollard::errors::Error>),
 }
@@ -1,15 +1,25 @@
+use std::collections::HashMap;
 use std::time::Duration;
 
-use crate::docker::ExecOutput;
+use crate::docker::{ExecOutput, set_memory_limit};
 use crate::error::ExecError;
 use crate::pool::ContainerGroup;
 use backoff::{ExponentialBackoff, ExponentialBackoffBuilder};
 use bollard::Docker;
 use deadpool::managed::{self, Object, Pool};
 use futures_util::StreamExt;
-use lapin::{Channel, Consumer, ExchangeKind, options::*, types::FieldTable};
-use models::{ExecStatus, RuntimeConfig, WorkerTask};
+use lapin::{
+    BasicProperties, Channel, Consumer, ExchangeKind,
+    options::*,
+    types::{AMQPValue, FieldTable},
+};
+use models::{
+    BlobStore, Checker, ExecStatus, FULL_SCORE, LeaseConfig, RetryConfig, RuntimeConfig, Subtask,
+    SubtaskScore, TaskKind, WorkerTask, outbox::enqueue, scoring::score_subtasks,
+    storage::StorageError,
+};
 use sqlx::PgPool;
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
 use tokio::signal::unix::{SignalKind, signal};
 use tokio_util::future::FutureExt;
 use tokio_util::sync::CancellationToken;
@@ -22,6 +32,89 @@ fn are_equal_ignore_whitespace(s1: &str, s2: &str) -> bool {
     s1_filtered == s2_filtered
 }
 
+fn are_equal_by_lines(s1: &str, s2: &str) -> bool {
+    let lines = |s: &str| -> Vec<String> {
+        s.trim_end()
+            .lines()
+            .map(|line| line.trim_end().to_string())
+            .collect()
+    };
+    lines(s1) == lines(s2)
+}
+
+/// Verdict implied by the exit code alone, `None` when the program ran to completion.
+fn exit_status(exit_code: i64) -> Option<ExecStatus> {
+    match exit_code {
+        137 => Some(ExecStatus::MemoryLimitExceeded),
+        139 => Some(ExecStatus::SegmentationFault),
+        124 => Some(ExecStatus::TimeLimitExceeded),
+        _ => None,
+    }
+}
+
+fn outputs_match(checker: Checker, output: &str, expected: &str) -> bool {
+    match checker {
+        Checker::Tokens => are_equal_ignore_whitespace(output, expected),
+        Checker::Lines => are_equal_by_lines(output, expected),
+        Checker::Exact => output == expected,
+    }
+}
+
+/// [`outputs_match`] against an expected output streamed line by line, so a blob
+/// is never held in memory as a whole.
+pub async fn output_matches_stream(
+    checker: Checker,
+    output: &str,
+    expected: impl AsyncRead + Unpin,
+) -> std::io::Result<bool> {
+    let mut expected = BufReader::new(expected);
+    let mut buf = Vec::new();
+    let mut tokens = output.chars().filter(|c| !c.is_whitespace());
+    let mut lines = output.trim_end().lines().map(str::trim_end);
+    let mut rest = output;
+    // blank lines only count once something follows them
+    let mut blank_lines = 0;
+    loop {
+        buf.clear();
+        if expected.read_until(b'\n', &mut buf).await? == 0 {
+            break;
+        }
+        // lines end at an ASCII byte, so decoding each one equals decoding the whole
+        let line = String::from_utf8_lossy(&buf);
+        let matched = match checker {
+            Checker::Tokens => line
+                .chars()
+                .filter(|c| !c.is_whitespace())
+                .all(|c| tokens.next() == Some(c)),
+            Checker::Lines => {
+                let line = line.trim_end();
+                if line.is_empty() {
+                    blank_lines += 1;
+                    true
+                } else {
+                    (0..std::mem::take(&mut blank_lines)).all(|_| lines.next() == Some(""))
+                        && lines.next() == Some(line)
+                }
+            }
+            Checker::Exact => match rest.strip_prefix(line.as_ref()) {
+                Some(tail) => {
+                    rest = tail;
+                    true
+                }
+                None => false,
+            },
+        };
+        if !matched {
+            return Ok(false);
+        }
+    }
+    Ok(match checker {
+        Checker::Tokens => tokens.next().is_none(),
+        Checker::Lines => lines.next().is_none(),
+        Checker::Exact => rest.is_empty(),
+    })
+}
+
 pub async fn declare_queue_exchange(
     channel: &Channel,
     queue: &str,
@@ -87,6 +180,55 @@ pub async fn declare_queue_exchange(
 
     Ok(())
 }
+
+pub const RETRY_COUNT_HEADER: &str = "x-retry-count";
+
+const MIB: i64 = 1024 * 1024;
+/// Docker refuses container memory limits below 6 MiB.
+const MIN_CONTAINER_MEMORY: i64 = 6 * MIB;
+
+/// Name of the holding queue for retries of `queue` delayed by `delay_ms`.
+pub fn retry_queue_name(queue: &str, delay_ms: u64) -> String {
+    format!("{}.retry.{}", queue, delay_ms)
+}
+
+/// Declares one holding queue per retry delay. Messages sit there until their TTL
+/// expires and are then dead-lettered back to `queue` through `exchange`.
+pub async fn declare_retry_queues(
+    channel: &Channel,
+    queue: &str,
+    exchange: &str,
+    delays_ms: &[u64],
+) -> lapin::Result<()> {
+    for &delay_ms in delays_ms {
+        debug!(
+            "Declaring retry queue for {} with delay {}ms",
+            queue, delay_ms
+        );
+        let mut args = FieldTable::default();
+        args.insert(
+            "x-message-ttl".into(),
+            AMQPValue::LongLongInt(delay_ms as i64),
+        );
+        args.insert(
+            "x-dead-letter-exchange".into(),
+            AMQPValue::LongString(exchange.into()),
+        );
+        args.insert(
+            "x-dead-letter-routing-key".into(),
+            AMQPValue::LongString(queue.into()),
+        );
+
+        channel
+            .queue_declare(
+                &retry_queue_name(queue, delay_ms),
+                QueueDeclareOptions::default(),
+                args,
+            )
+            .await?;
+    }
+    Ok(())
+}
 /*
 async fn declare_queue_exchange(
     channel: &Channel,
@@ -119,34 +261,71 @@ async fn declare_queue_exchange(
     Ok(())
 }
 */
-pub async fn exec_testcase(
-    docker_task: Docker,
+/// Writes `code` into the container and compiles it, within `compile_timeout` seconds.
+/// Returns the compiler's output when compilation fails. Runtimes without a compile
+/// step only get the file written.
+pub async fn compile_program(
+    docker_task: &Docker,
     container_id: &str,
     code: &str,
-    testcase: &str,
     compile: &Option<String>,
-    run: &str,
-    timeout: u8,
-) -> Result<ExecOutput, ExecError> {
-    let command = if let Some(compile) = compile {
-        format!("{} && timeout {timeout}s {}", compile, run)
-    } else {
-        format!("timeout {timeout}s {}", run)
-    };
+    compile_timeout: u8,
+) -> Result<Option<ExecOutput>, ExecError> {
+    let mut command = "printf '%s' \"$1\" > /tmp/file".to_string();
+    if let Some(compile) = compile {
+        command = format!("{} && timeout {}s {}", command, compile_timeout, compile);
+    }
     let cmd = vec![
         "sh".into(),
         "-c".into(),
-        format!("printf '%s' \"$1\" > /tmp/file && {}", command),
+        command,
         "--".into(),
         // DO NOT log or record `code` - keep payloads out of logs
         code.into(),
     ];
 
+    info!(
+        "Compiling in container {} with timeout {}s",
+        container_id, compile_timeout
+    );
+    let output = exec(docker_task, container_id, cmd, &b""[..], true).await?;
+    Ok((output.exit_code != 0).then_some(output))
+}
+
+/// Runs the program written by [`compile_program`] with `args` on its command line
+/// and `stdin` as input. Its stderr is part of the output only when `stderr` is set.
+pub async fn run_program(
+    docker_task: &Docker,
+    container_id: &str,
+    stdin: impl AsyncRead + Unpin,
+    args: &[String],
+    run: &str,
+    timeout: u8,
+    stderr: bool,
+) -> Result<ExecOutput, ExecError> {
+    let mut cmd = vec![
+        "sh".into(),
+        "-c".into(),
+        format!("timeout {timeout}s {} \"$@\"", run),
+        "--".into(),
+    ];
+    cmd.extend(args.iter().cloned());
+
     info!(
         "Starting exec in container {} with timeout {}s",
         container_id, timeout
     );
-    match crate::docker::run_exec(&docker_task, container_id, cmd, testcase).await {
+    exec(docker_task, container_id, cmd, stdin, stderr).await
+}
+
+async fn exec(
+    docker_task: &Docker,
+    container_id: &str,
+    cmd: Vec<String>,
+    stdin: impl AsyncRead + Unpin,
+    stderr: bool,
+) -> Result<ExecOutput, ExecError> {
+    match crate::docker::run_exec(docker_task, container_id, cmd, stdin, stderr).await {
         Ok(out) => {
             info!("Exec finished with exit_code={}", out.exit_code);
             Ok(out)
@@ -157,12 +336,15 @@ pub async fn exec_testcase(
         }
     }
 }
+
 async fn get_consumer(
     queue: &str,
     exchange: &str,
+    delays_ms: &[u64],
     channel: Channel,
 ) -> Result<Consumer, lapin::Error> {
     declare_queue_exchange(&channel, queue, exchange).await?;
+    declare_retry_queues(&channel, queue, exchange, delays_ms).await?;
 
     channel
         .basic_consume(
@@ -177,16 +359,207 @@ async fn get_consumer(
 #[derive(Clone)]
 pub struct CompileConfig {
     compile: Option<String>,
+    compile_timeout: u8,
     run: String,
     timeout: u8,
+    memory: i64,
 }
 
+#[derive(Clone)]
+pub struct RetryPolicy {
+    publisher: Channel,
+    queue: String,
+    delays_ms: Vec<u64>,
+}
+
+impl RetryPolicy {
+    /// Retries a delivery already went through, from its retry count header.
+    pub fn attempts(headers: &Option<FieldTable>) -> usize {
+        headers
+            .as_ref()
+            .and_then(|headers| headers.inner().get(RETRY_COUNT_HEADER))
+            .and_then(AMQPValue::as_long_long_int)
+            .unwrap_or_default() as usize
+    }
+
+    /// Schedules the next attempt of a failed delivery and acks it. Once every retry
+    /// is spent, `dead_lettered` runs and the delivery is dead-lettered; it is too if
+    /// the retry can't be scheduled, and the outbox sweeper picks the task up later.
+    async fn retry_or_dead_letter(
+        &self,
+        delivery: &lapin::message::Delivery,
+        submission_id: i64,
+        error: &(dyn std::fmt::Display + Sync),
+        dead_lettered: impl Future<Output = ()>,
+    ) -> Result<bool, lapin::Error> {
+        let attempts = Self::attempts(delivery.properties.headers());
+        match self.delays_ms.get(attempts) {
+            Some(&delay_ms) => match self.schedule(delay_ms, attempts + 1, &delivery.data).await {
+                Ok(()) => {
+                    warn!(
+                        "Submission_id={} failed ({}), retry {} scheduled in {}ms",
+                        submission_id,
+                        error,
+                        attempts + 1,
+                        delay_ms
+                    );
+                    return delivery.ack(BasicAckOptions::default()).await;
+                }
+                Err(retry_err) => error!(
+                    "Failed to schedule retry for submission_id={}: {}",
+                    submission_id, retry_err
+                ),
+            },
+            None => {
+                error!(
+                    "Submission_id={} failed after {} attempts, dead-lettering: {}",
+                    submission_id,
+                    attempts + 1,
+                    error
+                );
+                dead_lettered.await;
+            }
+        }
+        delivery
+            .nack(BasicNackOptions {
+                multiple: false,
+                requeue: false,
+            })
+            .await
+    }
+
+    /// Parks the task in the retry queue for its next attempt, waiting for the
+    /// broker to confirm before the original delivery may be acked.
+    async fn schedule(
+        &self,
+        delay_ms: u64,
+        attempt: usize,
+        payload: &[u8],
+    ) -> Result<(), ExecError> {
+        let mut headers = FieldTable::default();
+        headers.insert(
+            RETRY_COUNT_HEADER.into(),
+            AMQPValue::LongLongInt(attempt as i64),
+        );
+
+        let confirmation = self
+            .publisher
+            .basic_publish(
+                "",
+                &retry_queue_name(&self.queue, delay_ms),
+                BasicPublishOptions::default(),
+                payload,
+                BasicProperties::default().with_headers(headers),
+            )
+            .await?
+            .await?;
+
+        if confirmation.is_nack() {
+            return Err(ExecError::RetryNacked);
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone)]
+pub struct Lease {
+    pub worker_id: String,
+    pub lease_secs: u64,
+}
+
+/// Proof that this worker holds the judging lease for a submission. Only the
+/// holder of the latest attempt may write a verdict.
+#[derive(Clone, Debug)]
+pub struct JudgeClaim {
+    pub worker_id: String,
+    pub attempt: i32,
+    pub lease_secs: u64,
+}
+
+impl JudgeClaim {
+    /// Pushes the lease past one more run of up to `timeout` seconds, so a problem
+    /// with many testcases outlives the lease it was claimed with. Returns `false`
+    /// once a newer attempt took the submission over.
+    pub async fn renew(
+        &self,
+        pgpool: &PgPool,
+        submission_id: i64,
+        timeout: u8,
+    ) -> Result<bool, sqlx::Error> {
+        let renewed = sqlx::query!(
+            "UPDATE submit_status SET lease_expires_at = NOW() + make_interval(secs => $4) WHERE submission_id = $1 AND judge_worker = $2 AND judge_attempts = $3 AND lease_expires_at IS NOT NULL",
+            submission_id,
+            self.worker_id,
+            self.attempt,
+            (self.lease_secs + timeout as u64) as f64
+        )
+        .execute(pgpool)
+        .await?
+        .rows_affected();
+        if renewed == 0 {
+            warn!(
+                "Attempt {} lost the lease on submission_id={}, abandoning it",
+                self.attempt, submission_id
+            );
+        }
+        Ok(renewed > 0)
+    }
+}
+
+/// Atomically takes the judging lease for a pending submission. Returns `None`
+/// when the submission already has a verdict or another worker's lease is live.
+pub async fn claim_submission(
+    pgpool: &PgPool,
+    submission_id: i64,
+    lease: &Lease,
+) -> Result<Option<JudgeClaim>, sqlx::Error> {
+    let pending: &str = ExecStatus::Pending.into();
+    let row = sqlx::query!(
+        "UPDATE submit_status SET judge_worker = $2, judge_attempts = judge_attempts + 1, lease_expires_at = NOW() + make_interval(secs => $3) WHERE submission_id = $1 AND status = $4 AND (lease_expires_at IS NULL OR lease_expires_at < NOW()) RETURNING judge_attempts",
+        submission_id,
+        lease.worker_id,
+        lease.lease_secs as f64,
+        pending
+    )
+    .fetch_optional(pgpool)
+    .await?;
+
+    Ok(row.map(|row| JudgeClaim {
+        worker_id: lease.worker_id.clone(),
+        attempt: row.judge_attempts,
+        lease_secs: lease.lease_secs,
+    }))
+}
+
+/// Gives the lease back so a redelivery can be picked up without waiting for expiry.
+async fn release_submission(pgpool: &PgPool, submission_id: i64, claim: &JudgeClaim) {
+    let res = sqlx::query!(
+        "UPDATE submit_status SET lease_expires_at = NULL WHERE submission_id = $1 AND judge_worker = $2 AND judge_attempts = $3",
+        submission_id,
+        claim.worker_id,
+        claim.attempt
+    )
+    .execute(pgpool)
+    .await;
+
+    if let Err(e) = res {
+        warn!(
+            "Failed to release lease for submission_id={}: {}",
+            submission_id, e
+        );
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 async fn listen<T: TestcaseHandler>(
     task_tracker: TaskTracker,
     docker: &Docker,
     pool: &managed::Pool<ContainerGroup>,
     pgpool: PgPool,
+    storage: BlobStore,
     compile_config: CompileConfig,
+    lease: Lease,
+    retry: RetryPolicy,
     mut consumer: Consumer,
 
     token: CancellationToken,
@@ -201,7 +574,10 @@ async fn listen<T: TestcaseHandler>(
         };
         let docker_task = docker.clone();
         let pgpool = pgpool.clone();
+        let storage = storage.clone();
         let compile_config = compile_config.clone();
+        let lease = lease.clone();
+        let retry = retry.clone();
         let conn: Object<ContainerGroup> = pool.get().await?;
         let task = serde_json::from_slice(&delivery.data);
         match task {
@@ -221,7 +597,10 @@ async fn listen<T: TestcaseHandler>(
                         delivery,
                         docker_task,
                         pgpool,
+                        storage,
                         compile_config,
+                        lease,
+                        retry,
                         conn,
                         task,
                         token,
@@ -235,31 +614,80 @@ async fn listen<T: TestcaseHandler>(
     Ok(())
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn handle_delivery<T: TestcaseHandler>(
     delivery: lapin::message::Delivery,
     docker_task: Docker,
     pgpool: sqlx::Pool<sqlx::Postgres>,
+    storage: BlobStore,
     compile_config: CompileConfig,
+    lease: Lease,
+    retry: RetryPolicy,
     conn: Object<ContainerGroup>,
     task: WorkerTask,
     token: CancellationToken,
 ) -> Result<bool, lapin::Error> {
-    match handle_message::<T>(docker_task, compile_config, pgpool, conn, task)
-        .with_cancellation_token_owned(token)
-        .await
+    let submission_id = task.submission_id;
+    let claim = match claim_submission(&pgpool, submission_id, &lease).await {
+        Ok(Some(claim)) => claim,
+        Ok(None) => {
+            info!(
+                "Skipping delivery for submission_id={}, already judged or claimed (redelivered={})",
+                submission_id, delivery.redelivered
+            );
+            return delivery.ack(BasicAckOptions::default()).await;
+        }
+        Err(e) => {
+            // nothing is claimed yet, so the task goes back through the retry ladder as is
+            return retry
+                .retry_or_dead_letter(
+                    &delivery,
+                    submission_id,
+                    &format!("claim failed: {}", e),
+                    async {},
+                )
+                .await;
+        }
+    };
+
+    match handle_message::<T>(
+        docker_task,
+        compile_config,
+        pgpool.clone(),
+        storage,
+        conn,
+        task,
+        claim.clone(),
+    )
+    .with_cancellation_token_owned(token)
+    .await
     {
         Some(output) => match output {
             Ok(()) => delivery.ack(BasicAckOptions::default()).await,
-            Err(_) => {
-                delivery
-                    .nack(BasicNackOptions {
-                        multiple: false,
-                        requeue: false,
-                    })
+            Err(e) => {
+                release_submission(&pgpool, submission_id, &claim).await;
+                let judge_error = async {
+                    // the error itself is logged by update_submit_status
+                    let _ = update_submit_status(
+                        &pgpool,
+                        submission_id,
+                        &claim,
+                        e.to_string(),
+                        ExecStatus::JudgeError.into(),
+                        None,
+                        false,
+                        None,
+                        &[],
+                    )
+                    .await;
+                };
+                retry
+                    .retry_or_dead_letter(&delivery, submission_id, &e, judge_error)
                     .await
             }
         },
         None => {
+            release_submission(&pgpool, submission_id, &claim).await;
             delivery
                 .nack(BasicNackOptions {
                     multiple: true,
@@ -271,12 +699,126 @@ async fn handle_delivery<T: TestcaseHandler>(
 }
 
 pub struct Testcase {
+    testcase_id: i64,
     testcase: String,
     output: String,
+    is_sample: bool,
+    /// Generated testcases take their expected output from the reference solution.
+    generated: bool,
+    input_blob: Option<String>,
+    output_blob: Option<String>,
+    subtask: Option<String>,
 }
 
-async fn fetch_testcase(pgpool: &PgPool, problem_id: i64) -> Result<Testcase, sqlx::Error> {
-    info!("Fetching testcase for problem_id {}", problem_id);
+/// Points of a judged submission and the scores of its subtasks, if it has any.
+/// `passed` tells whether each subtask's testcases passed, as for [`score_subtasks`].
+pub fn submission_score(
+    status: ExecStatus,
+    compiled: bool,
+    subtasks: &[Subtask],
+    passed: &HashMap<String, bool>,
+) -> (i32, Vec<SubtaskScore>) {
+    if subtasks.is_empty() {
+        let score = if status == ExecStatus::Passed {
+            FULL_SCORE
+        } else {
+            0
+        };
+        return (score, Vec::new());
+    }
+    let scores = if compiled {
+        score_subtasks(subtasks, passed)
+    } else {
+        // no testcase ran, which would otherwise pass every subtask
+        let failed = subtasks
+            .iter()
+            .map(|subtask| (subtask.name.clone(), false))
+            .collect();
+        score_subtasks(subtasks, &failed)
+    };
+    (scores.iter().map(|subtask| subtask.points).sum(), scores)
+}
+
+/// The verdict of a submission and the testcase that decided it.
+pub struct JudgeResult {
+    pub status: ExecStatus,
+    pub exec_output: ExecOutput,
+    /// 1-based position of the last testcase run, `None` when nothing ran.
+    pub testcase_number: Option<i32>,
+    /// Output on hidden testcases is only shown to problem setters.
+    pub output_hidden: bool,
+    /// Points earned by a submission, `None` for other tasks.
+    pub score: Option<i32>,
+    pub subtasks: Vec<SubtaskScore>,
+}
+
+pub struct JudgingOptions {
+    time_limit_ms: Option<i32>,
+    memory_limit_mb: Option<i32>,
+    checker: String,
+}
+
+async fn fetch_judging_options(
+    pgpool: &PgPool,
+    problem_id: i64,
+) -> Result<JudgingOptions, sqlx::Error> {
+    sqlx::query_as!(
+        JudgingOptions,
+        "SELECT time_limit_ms, memory_limit_mb, checker FROM problems WHERE problem_id = $1",
+        problem_id
+    )
+    .fetch_one(pgpool)
+    .await
+}
+
+async fn fetch_subtasks(pgpool: &PgPool, problem_id: i64) -> Result<Vec<Subtask>, sqlx::Error> {
+    sqlx::query_as!(
+        Subtask,
+        "SELECT name, points, depends_on, skip_on_failure FROM problem_subtasks WHERE problem_id = $1 ORDER BY position",
+        problem_id
+    )
+    .fetch_all(pgpool)
+    .await
+}
+
+/// Stores a reference solution's output as the expected output of each testcase.
+async fn fill_expected_outputs(
+    pgpool: &PgPool,
+    outputs: &[(i64, String)],
+) -> Result<(), sqlx::Error> {
+    let mut transaction = pgpool.begin().await?;
+    for (testcase_id, output) in outputs {
+        sqlx::query!(
+            "UPDATE problem_testcases t SET output = $2, output_blob = NULL, validation = CASE WHEN t.generated AND t.validation = 'pending' AND p.validator_code IS NULL THEN 'valid' ELSE t.validation END FROM problems p WHERE t.testcase_id = $1 AND p.problem_id = t.problem_id",
+            testcase_id,
+            output
+        )
+        .execute(transaction.as_mut())
+        .await?;
+    }
+    transaction.commit().await
+}
+
+/// Opens a testcase input, streamed from blob storage when it is kept there.
+async fn open_input<'a>(
+    storage: &BlobStore,
+    inline: &'a str,
+    blob: &Option<String>,
+) -> Result<Box<dyn AsyncRead + Send + Unpin + 'a>, ExecError> {
+    match blob {
+        Some(hash) => Ok(Box::new(storage.open(hash).await?)),
+        None => Ok(Box::new(inline.as_bytes())),
+    }
+}
+
+/// Fetches the testcases a task runs on. Submissions only see validated inputs, while
+/// reference runs also cover the ones still awaiting validation.
+async fn fetch_testcases(
+    pgpool: &PgPool,
+    problem_id: i64,
+    include_pending: bool,
+) -> Result<Vec<Testcase>, sqlx::Error> {
+    info!("Fetching testcases for problem_id {}", problem_id);
     let backoff = ExponentialBackoffBuilder::new()
         .with_max_elapsed_time(Some(Duration::from_secs(10)))
         .build();
@@ -284,22 +826,27 @@ async fn fetch_testcase(pgpool: &PgPool, problem_id: i64) -> Result<Testcase, sq
     let res = backoff::future::retry(backoff, || async {
         Ok(sqlx::query_as!(
             Testcase,
-            "SELECT testcase,output from problem_testcases WHERE problem_id=$1",
-            problem_id
+            "SELECT testcase_id,testcase,output,is_sample,generated,input_blob,output_blob,subtask from problem_testcases WHERE problem_id=$1 AND (validation = 'valid' OR ($2 AND validation = 'pending')) ORDER BY position, testcase_id",
+            problem_id,
+            include_pending
         )
-        .fetch_one(pgpool)
+        .fetch_all(pgpool)
         .await?)
     })
     .await;
 
     match res {
-        Ok(tc) => {
-            info!("Fetched testcase for problem_id {}", problem_id);
-            Ok(tc)
+        Ok(tcs) => {
+            info!(
+                "Fetched {} testcases for problem_id {}",
+                tcs.len(),
+                problem_id
+            );
+            Ok(tcs)
         }
         Err(e) => {
             error!(
-                "Failed to fetch testcase for problem_id {}: {}",
+                "Failed to fetch testcases for problem_id {}: {}",
                 problem_id, e
             );
             Err(e)
@@ -307,13 +854,365 @@ async fn fetch_testcase(pgpool: &PgPool, problem_id: i64) -> Result<Testcase, sq
     }
 }
 
-#[instrument(skip(docker_task, compile_config, pgpool, container, task), fields(user_id = tracing::field::Empty, submission_id = tracing::field::Empty))]
+pub struct PendingInput {
+    testcase_id: i64,
+    testcase: String,
+    input_blob: Option<String>,
+}
+
+/// Runs the problem's validator on every testcase awaiting validation. A testcase is
+/// valid when the validator exits with 0, otherwise its output is kept as the reason.
+async fn handle_validation<T: TestcaseHandler>(
+    docker_task: Docker,
+    compile_config: CompileConfig,
+    pgpool: sqlx::Pool<sqlx::Postgres>,
+    storage: BlobStore,
+    container: Object<ContainerGroup>,
+    task: WorkerTask,
+    claim: JudgeClaim,
+) -> Result<(), ExecError> {
+    let inputs = sqlx::query_as!(
+        PendingInput,
+        "SELECT testcase_id, testcase, input_blob FROM problem_testcases WHERE problem_id = $1 AND validation = 'pending' ORDER BY position, testcase_id",
+        task.problem_id
+    )
+    .fetch_all(&pgpool)
+    .await?;
+
+    // a validator that doesn't compile rejects every input with the compiler's output
+    let compile_failure = compile_program(
+        &docker_task,
+        &container.id,
+        &task.code,
+        &compile_config.compile,
+        compile_config.compile_timeout,
+    )
+    .await?;
+    let mut rejected = Vec::new();
+    for input in &inputs {
+        if !claim
+            .renew(&pgpool, task.submission_id, compile_config.timeout)
+            .await?
+        {
+            return Ok(());
+        }
+        let exec_output = match &compile_failure {
+            Some(exec_output) => exec_output.clone(),
+            None => {
+                run_program(
+                    &docker_task,
+                    &container.id,
+                    open_input(&storage, &input.testcase, &input.input_blob).await?,
+                    &[],
+                    &compile_config.run,
+                    compile_config.timeout,
+                    true,
+                )
+                .await?
+            }
+        };
+        let message = match exit_status(exec_output.exit_code) {
+            Some(status) => Some(format!("Validator failed with {}", <&str>::from(status))),
+            None if exec_output.exit_code != 0 => match exec_output.output.trim() {
+                "" => Some(format!(
+                    "Validator exited with code {}",
+                    exec_output.exit_code
+                )),
+                output => Some(output.to_string()),
+            },
+            None => None,
+        };
+        let validation = if message.is_some() {
+            "invalid"
+        } else {
+            "valid"
+        };
+
+        // the input may have been replaced meanwhile, the newer validation run covers it.
+        // Reference runs already covered the pending testcase, so updated_at stays put.
+        sqlx::query!(
+            "UPDATE problem_testcases SET validation = $2, validation_message = $3 WHERE testcase_id = $1 AND testcase = $4 AND input_blob IS NOT DISTINCT FROM $5 AND validation = 'pending'",
+            input.testcase_id,
+            validation,
+            message,
+            input.testcase,
+            input.input_blob
+        )
+        .execute(&pgpool)
+        .await?;
+        if let Some(message) = message {
+            rejected.push((input.testcase_id, message));
+        }
+    }
+
+    info!(
+        "Validated {} testcases of problem {}, {} rejected",
+        inputs.len(),
+        task.problem_id,
+        rejected.len()
+    );
+    let (status, output) = match rejected.first() {
+        None => (
+            ExecStatus::Passed,
+            format!("{} testcases valid", inputs.len()),
+        ),
+        Some((testcase_id, message)) => (
+            ExecStatus::WrongAnswer,
+            format!(
+                "{} of {} testcases rejected, testcase {}: {}",
+                rejected.len(),
+                inputs.len(),
+                testcase_id,
+                message
+            ),
+        ),
+    };
+    let result = JudgeResult {
+        status,
+        exec_output: ExecOutput {
+            output,
+            exit_code: 0,
+        },
+        testcase_number: None,
+        output_hidden: false,
+        score: None,
+        subtasks: Vec::new(),
+    };
+    T::handle_result(pgpool, task, claim, result).await
+}
+
+pub struct ChainedPrograms {
+    validator_code: Option<String>,
+    validator_env: Option<String>,
+    reference_code: Option<String>,
+    reference_env: Option<String>,
+}
+
+/// Writes a follow-up task to the outbox under a new submission, for the relay to publish.
+async fn chain_task(
+    conn: &mut sqlx::PgConnection,
+    task: &WorkerTask,
+    kind: TaskKind,
+    code: String,
+    env: &str,
+) -> Result<i64, sqlx::Error> {
+    let kind_name = match kind {
+        TaskKind::Validation => "validation",
+        _ => "reference",
+    };
+    let submission_id = sqlx::query!(
+        "INSERT INTO submit_status (user_id, problem_id, kind) VALUES ($1, $2, $3) RETURNING submission_id",
+        task.user_id,
+        task.problem_id,
+        kind_name
+    )
+    .fetch_one(&mut *conn)
+    .await?
+    .submission_id;
+    let chained = WorkerTask {
+        code,
+        problem_id: task.problem_id,
+        user_id: task.user_id,
+        submission_id,
+        kind,
+    };
+    let payload = serde_json::to_vec(&chained).unwrap();
+    enqueue(conn, submission_id, env, &payload).await?;
+    Ok(submission_id)
+}
+
+/// Replaces a problem's generated testcases with the outputs of its generator script.
+/// Expected outputs come from a reference run queued right after, and the inputs are
+/// validated first when the problem has a validator.
+async fn store_generated(
+    pgpool: &PgPool,
+    task: &WorkerTask,
+    inputs: &[String],
+) -> Result<(), sqlx::Error> {
+    let mut transaction = pgpool.begin().await?;
+    let programs = sqlx::query_as!(
+        ChainedPrograms,
+        "SELECT validator_code, validator_env, reference_code, reference_env FROM problems WHERE problem_id = $1 FOR UPDATE",
+        task.problem_id
+    )
+    .fetch_one(transaction.as_mut())
+    .await?;
+    sqlx::query!(
+        "DELETE FROM problem_testcases WHERE problem_id = $1 AND generated",
+        task.problem_id
+    )
+    .execute(transaction.as_mut())
+    .await?;
+    sqlx::query!(
+        "INSERT INTO problem_testcases (problem_id, testcase, output, position, generated, validation) SELECT $1, g.input, '', m.start + g.n::INT, TRUE, 'pending' FROM UNNEST($2::TEXT[]) WITH ORDINALITY AS g(input, n) CROSS JOIN (SELECT COALESCE(MAX(position), -1) AS start FROM problem_testcases WHERE problem_id = $1) m",
+        task.problem_id,
+        inputs
+    )
+    .execute(transaction.as_mut())
+    .await?;
+
+    if let (Some(code), Some(env)) = (programs.validator_code, &programs.validator_env) {
+        chain_task(&mut transaction, task, TaskKind::Validation, code, env).await?;
+    }
+    if let (Some(code), Some(env)) = (programs.reference_code, &programs.reference_env) {
+        let submission_id = chain_task(
+            &mut transaction,
+            task,
+            TaskKind::Reference {
+                fill_outputs: false,
+            },
+            code,
+            env,
+        )
+        .await?;
+        sqlx::query!(
+            "UPDATE problems SET reference_submission_id = $2 WHERE problem_id = $1",
+            task.problem_id,
+            submission_id
+        )
+        .execute(transaction.as_mut())
+        .await?;
+    }
+    transaction.commit().await
+}
+
+/// Runs each invocation of a generator script with no input. Every output becomes the
+/// input of a generated testcase, and nothing is stored unless all of them succeed.
+async fn handle_generation<T: TestcaseHandler>(
+    docker_task: Docker,
+    compile_config: CompileConfig,
+    pgpool: sqlx::Pool<sqlx::Postgres>,
+    container: Object<ContainerGroup>,
+    task: WorkerTask,
+    claim: JudgeClaim,
+) -> Result<(), ExecError> {
+    let TaskKind::Generation {
+        generators,
+        invocations,
+        max_input_bytes,
+    } = &task.kind
+    else {
+        unreachable!("handle_generation called for a {:?} task", task.kind);
+    };
+
+    let mut inputs = Vec::with_capacity(invocations.len());
+    let mut failure = None;
+    // generators are compiled when the script switches to them, not per invocation
+    let mut compiled = None;
+    for (index, invocation) in invocations.iter().enumerate() {
+        let Some(code) = generators.get(&invocation.generator) else {
+            failure = Some((
+                index,
+                ExecStatus::JudgeError,
+                ExecOutput {
+                    output: format!("No such generator {}", invocation.generator),
+                    exit_code: 0,
+                },
+            ));
+            break;
+        };
+        if compiled != Some(&invocation.generator) {
+            if let Some(exec_output) = compile_program(
+                &docker_task,
+                &container.id,
+                code,
+                &compile_config.compile,
+                compile_config.compile_timeout,
+            )
+            .await?
+            {
+                let status = exit_status(exec_output.exit_code).unwrap_or(ExecStatus::WrongAnswer);
+                failure = Some((index, status, exec_output));
+                break;
+            }
+            compiled = Some(&invocation.generator);
+        }
+        if !claim
+            .renew(&pgpool, task.submission_id, compile_config.timeout)
+            .await?
+        {
+            return Ok(());
+        }
+        let exec_output = run_program(
+            &docker_task,
+            &container.id,
+            &b""[..],
+            &invocation.args,
+            &compile_config.run,
+            compile_config.timeout,
+            // diagnostics a generator prints must not end up in the testcase
+            false,
+        )
+        .await?;
+        let failed = match exit_status(exec_output.exit_code) {
+            Some(status) => Some((status, exec_output)),
+            None if exec_output.exit_code != 0 => Some((ExecStatus::WrongAnswer, exec_output)),
+            None if exec_output.output.len() > *max_input_bytes => Some((
+                ExecStatus::WrongAnswer,
+                ExecOutput {
+                    output: format!("Generated input exceeds {} bytes", max_input_bytes),
+                    exit_code: 0,
+                },
+            )),
+            None => {
+                inputs.push(exec_output.output);
+                None
+            }
+        };
+        if let Some((status, exec_output)) = failed {
+            failure = Some((index, status, exec_output));
+            break;
+        }
+    }
+
+    let result = match failure {
+        Some((index, status, exec_output)) => {
+            info!(
+                "Generator script of problem {} failed on invocation {}",
+                task.problem_id,
+                index + 1
+            );
+            JudgeResult {
+                status,
+                exec_output,
+                testcase_number: Some(index as i32 + 1),
+                output_hidden: false,
+                score: None,
+                subtasks: Vec::new(),
+            }
+        }
+        None => {
+            store_generated(&pgpool, &task, &inputs).await?;
+            info!(
+                "Generated {} testcases for problem {}",
+                inputs.len(),
+                task.problem_id
+            );
+            JudgeResult {
+                status: ExecStatus::Passed,
+                exec_output: ExecOutput {
+                    output: format!("{} testcases generated", inputs.len()),
+                    exit_code: 0,
+                },
+                testcase_number: None,
+                output_hidden: false,
+                score: None,
+                subtasks: Vec::new(),
+            }
+        }
+    };
+    T::handle_result(pgpool, task, claim, result).await
+}
+
+#[instrument(skip(docker_task, compile_config, pgpool, storage, container, task), fields(user_id = tracing::field::Empty, submission_id = tracing::field::Empty))]
 async fn handle_message<T: TestcaseHandler>(
     docker_task: Docker,
     compile_config: CompileConfig,
     pgpool: sqlx::Pool<sqlx::Postgres>,
+    storage: BlobStore,
     container: Object<ContainerGroup>,
     task: WorkerTask,
+    claim: JudgeClaim,
 ) -> Result<(), ExecError> {
     tracing::Span::current().record("user_id", &task.user_id);
     tracing::Span::current().record("submission_id", &task.submission_id);
@@ -322,40 +1221,227 @@ async fn handle_message<T: TestcaseHandler>(
         "Handling task for submission_id={} problem_id={} user_id={}",
         task.submission_id, task.problem_id, task.user_id
     );
+    match task.kind {
+        TaskKind::Validation => {
+            return handle_validation::<T>(
+                docker_task,
+                compile_config,
+                pgpool,
+                storage,
+                container,
+                task,
+                claim,
+            )
+            .await;
+        }
+        TaskKind::Generation { .. } => {
+            return handle_generation::<T>(
+                docker_task,
+                compile_config,
+                pgpool,
+                container,
+                task,
+                claim,
+            )
+            .await;
+        }
+        _ => {}
+    }
 
-    let testcase = match fetch_testcase(&pgpool, task.problem_id).await {
-        Ok(tc) => tc,
+    let reference = matches!(task.kind, TaskKind::Reference { .. });
+    let testcases = match fetch_testcases(&pgpool, task.problem_id, reference).await {
+        Ok(tcs) => tcs,
         Err(e) => {
             error!(
-                "Failed to fetch testcase for submission {}: {}",
+                "Failed to fetch testcases for submission {}: {}",
                 task.submission_id, e
             );
             return Err(ExecError::DatabaseError(e));
         }
     };
+    let options = fetch_judging_options(&pgpool, task.problem_id).await?;
+    let checker = Checker::try_from(options.checker.as_str()).unwrap_or_else(|e| {
+        warn!("{} on problem {}, comparing tokens", e, task.problem_id);
+        Checker::Tokens
+    });
+    // the problem's own time limit replaces the runtime default, rounded up to whole seconds
+    let timeout = options
+        .time_limit_ms
+        .map(|ms| (ms.max(1) as u32).div_ceil(1000).min(u8::MAX as u32) as u8)
+        .unwrap_or(compile_config.timeout);
+    // likewise its memory limit tightens the container's, the pool restores it on recycle
+    if let Some(mb) = options.memory_limit_mb {
+        let memory = (mb as i64 * MIB).clamp(MIN_CONTAINER_MEMORY, compile_config.memory);
+        set_memory_limit(&docker_task, &container.id, memory).await?;
+    }
 
-    let exec_output = match exec_testcase(
-        docker_task,
+    // a reference run that fills outputs only has to finish cleanly within the limits
+    let fill_outputs = matches!(task.kind, TaskKind::Reference { fill_outputs: true });
+    let mut filled = Vec::new();
+
+    // only submissions are scored, reference runs are judged as a whole
+    let subtasks = match task.kind {
+        TaskKind::Submission => fetch_subtasks(&pgpool, task.problem_id).await?,
+        _ => Vec::new(),
+    };
+    let skip_on_failure: HashMap<&str, bool> = subtasks
+        .iter()
+        .map(|subtask| (subtask.name.as_str(), subtask.skip_on_failure))
+        .collect();
+    let mut subtask_passed: HashMap<String, bool> = HashMap::new();
+
+    // run testcases in order and stop at the first one that does not pass, unless
+    // the problem has subtasks: then later subtasks can still earn their points
+    let mut result = None;
+    // a program that doesn't compile fails as a whole, before any testcase runs
+    let mut failure = compile_program(
+        &docker_task,
         &container.id,
         &task.code,
-        &testcase.testcase,
         &compile_config.compile,
-        &compile_config.run,
-        compile_config.timeout,
+        compile_config.compile_timeout,
     )
-    .await
-    {
-        Ok(out) => out,
-        Err(e) => {
-            error!(
-                "Execution failed for submission {}: {}",
-                task.submission_id, e
-            );
-            return Err(e);
-        }
+    .await?
+    .map(|exec_output| JudgeResult {
+        status: exit_status(exec_output.exit_code).unwrap_or(ExecStatus::WrongAnswer),
+        exec_output,
+        testcase_number: None,
+        output_hidden: false,
+        score: None,
+        subtasks: Vec::new(),
+    });
+    let compiled = failure.is_none();
+    let runnable = if compiled {
+        &testcases[..]
+    } else {
+        &testcases[..0]
     };
+    for (index, testcase) in runnable.iter().enumerate() {
+        if let Some(subtask) = &testcase.subtask
+            && subtask_passed.get(subtask) == Some(&false)
+            && skip_on_failure
+                .get(subtask.as_str())
+                .copied()
+                .unwrap_or(true)
+        {
+            continue;
+        }
+        if !claim.renew(&pgpool, task.submission_id, timeout).await? {
+            return Ok(());
+        }
+        // a filled output becomes the expected one, so diagnostics must stay out of it
+        let fills = fill_outputs || (reference && testcase.generated);
+        let exec_output = match run_program(
+            &docker_task,
+            &container.id,
+            open_input(&storage, &testcase.testcase, &testcase.input_blob).await?,
+            &[],
+            &compile_config.run,
+            timeout,
+            !fills,
+        )
+        .await
+        {
+            Ok(out) => out,
+            Err(e) => {
+                error!(
+                    "Execution failed for submission {} on testcase {}: {}",
+                    task.submission_id,
+                    index + 1,
+                    e
+                );
+                return Err(e);
+            }
+        };
+        let status = if fills {
+            match exit_status(exec_output.exit_code) {
+                Some(status) => status,
+                None if exec_output.exit_code != 0 => ExecStatus::WrongAnswer,
+                None => {
+                    filled.push((testcase.testcase_id, exec_output.output.clone()));
+                    ExecStatus::Passed
+                }
+            }
+        } else if let Some(hash) = &testcase.output_blob {
+            // outputs kept in blob storage are streamed against the program's output
+            match exit_status(exec_output.exit_code) {
+                Some(status) => status,
+                None => {
+                    let expected = storage.open(hash).await?;
+                    let matched = output_matches_stream(checker, &exec_output.output, expected)
+                        .await
+                        .map_err(StorageError::from)?;
+                    if matched {
+                        ExecStatus::Passed
+                    } else {
+                        ExecStatus::WrongAnswer
+                    }
+                }
+            }
+        } else {
+            T::verdict(checker, &testcase.output, &exec_output)
+        };
+        if let Some(subtask) = &testcase.subtask {
+            *subtask_passed.entry(subtask.clone()).or_insert(true) &= status == ExecStatus::Passed;
+        }
+        let judged = JudgeResult {
+            status,
+            exec_output,
+            testcase_number: Some(index as i32 + 1),
+            output_hidden: !testcase.is_sample,
+            score: None,
+            subtasks: Vec::new(),
+        };
+        if status == ExecStatus::Passed {
+            result = Some(judged);
+            continue;
+        }
+        info!(
+            "Submission {} failed on testcase {} of {}",
+            task.submission_id,
+            index + 1,
+            testcases.len()
+        );
+        // the verdict is the first failure
+        if failure.is_none() {
+            failure = Some(judged);
+        }
+        if subtasks.is_empty() {
+            break;
+        }
+    }
+
+    let mut result = failure.or(result).unwrap_or_else(|| {
+        warn!("Problem {} has no testcases", task.problem_id);
+        JudgeResult {
+            status: ExecStatus::JudgeError,
+            exec_output: ExecOutput {
+                output: "Problem has no testcases".into(),
+                exit_code: 0,
+            },
+            testcase_number: None,
+            output_hidden: false,
+            score: None,
+            subtasks: Vec::new(),
+        }
+    });
+    if task.kind == TaskKind::Submission && result.status != ExecStatus::JudgeError {
+        let (score, scores) = submission_score(result.status, compiled, &subtasks, &subtask_passed);
+        result.score = Some(score);
+        result.subtasks = scores;
+    }
+
+    if !filled.is_empty() && result.status == ExecStatus::Passed {
+        fill_expected_outputs(&pgpool, &filled).await?;
+        info!(
+            "Filled {} expected outputs of problem {} from its reference solution",
+            filled.len(),
+            task.problem_id
+        );
+    }
+
     let submission_id = task.submission_id;
-    match T::handle_testcase(pgpool, task, testcase.output, exec_output).await {
+    match T::handle_result(pgpool, task, claim, result).await {
         Ok(()) => info!(
             "Finished evaluating and updating database for submission_id={}",
             submission_id
@@ -371,35 +1457,76 @@ async fn handle_message<T: TestcaseHandler>(
     Ok(())
 }
 
-async fn update_submit_status(
+#[allow(clippy::too_many_arguments)]
+pub async fn update_submit_status(
     pgpool: &PgPool,
     submission_id: i64,
+    claim: &JudgeClaim,
     output: String,
     status: &str,
+    testcase_number: Option<i32>,
+    output_hidden: bool,
+    score: Option<i32>,
+    subtasks: &[SubtaskScore],
 ) -> Result<(), sqlx::Error> {
     info!(
         "Updating submit_status submission_id={} status={}",
         submission_id, status
     );
+    let pending: &str = ExecStatus::Pending.into();
     let backoff: ExponentialBackoff = ExponentialBackoffBuilder::new()
         .with_max_elapsed_time(Some(Duration::from_secs(10)))
         .build();
 
+    let names: Vec<String> = subtasks.iter().map(|s| s.name.clone()).collect();
+    let points: Vec<i32> = subtasks.iter().map(|s| s.points).collect();
+    let max_points: Vec<i32> = subtasks.iter().map(|s| s.max_points).collect();
+    let passed: Vec<bool> = subtasks.iter().map(|s| s.passed).collect();
+
     let res = backoff::future::retry(backoff, || async {
-        sqlx::query!(
-            "UPDATE submit_status SET output=$1, status=$2 WHERE submission_id=$3",
+        let mut transaction = pgpool.begin().await?;
+        let updated = sqlx::query!(
+            "UPDATE submit_status SET output=$1, status=$2, testcase_number=$7, output_hidden=$8, score=$9, lease_expires_at=NULL WHERE submission_id=$3 AND judge_worker=$4 AND judge_attempts=$5 AND status=$6",
             output,
             status,
             submission_id,
+            claim.worker_id,
+            claim.attempt,
+            pending,
+            testcase_number,
+            output_hidden,
+            score,
         )
-        .execute(pgpool)
-        .await?;
-        Ok(())
+        .execute(transaction.as_mut())
+        .await?
+        .rows_affected();
+        // per subtask results are only written along with the verdict they belong to
+        if updated > 0 && !subtasks.is_empty() {
+            sqlx::query!(
+                "INSERT INTO submission_subtasks (submission_id, subtask, points, max_points, passed) SELECT $1, * FROM UNNEST($2::TEXT[], $3::INT[], $4::INT[], $5::BOOL[])",
+                submission_id,
+                &names,
+                &points,
+                &max_points,
+                &passed
+            )
+            .execute(transaction.as_mut())
+            .await?;
+        }
+        transaction.commit().await?;
+        Ok(updated)
     })
     .await;
 
     match res {
-        Ok(()) => {
+        Ok(0) => {
+            warn!(
+                "Discarded stale result for submission_id={}, attempt {} no longer holds the lease",
+                submission_id, claim.attempt
+            );
+            Ok(())
+        }
+        Ok(_) => {
             info!("Updated submit_status for submission_id={}", submission_id);
             Ok(())
         }
@@ -413,37 +1540,53 @@ async fn update_submit_status(
     }
 }
 pub trait TestcaseHandler {
-    fn handle_testcase(
+    /// Verdict for a single testcase run against its expected output.
+    fn verdict(checker: Checker, expected: &str, exec_output: &ExecOutput) -> ExecStatus {
+        match exit_status(exec_output.exit_code) {
+            Some(status) => status,
+            None => {
+                if outputs_match(checker, &exec_output.output, expected) {
+                    ExecStatus::Passed
+                } else {
+                    ExecStatus::WrongAnswer
+                }
+            }
+        }
+    }
+
+    /// Records the final verdict of a submission.
+    fn handle_result(
         pgpool: sqlx::Pool<sqlx::Postgres>,
         task: WorkerTask,
-        output: String,
-        exec_output: ExecOutput,
+        claim: JudgeClaim,
+        result: JudgeResult,
     ) -> impl std::future::Future<Output = Result<(), ExecError>> + std::marker::Send {
         async move {
-            let status: &str = match exec_output.exit_code {
-                137 => ExecStatus::MemoryLimitExceeded,
-                139 => ExecStatus::SegmentationFault,
-                124 => ExecStatus::TimeLimitExceeded,
-                _ => {
-                    if are_equal_ignore_whitespace(&exec_output.output, &output) {
-                        ExecStatus::Passed
-                    } else {
-                        ExecStatus::WrongAnswer
-                    }
-                }
-            }
-            .into();
-            update_submit_status(&pgpool, task.submission_id, exec_output.output, status).await?;
+            update_submit_status(
+                &pgpool,
+                task.submission_id,
+                &claim,
+                result.exec_output.output,
+                result.status.into(),
+                result.testcase_number,
+                result.output_hidden,
+                result.score,
+                &result.subtasks,
+            )
+            .await?;
             Ok(())
         }
     }
 }
 
-#[instrument(skip(conn, pgpool, docker), fields(env = %runtime.env))]
+#[instrument(skip(lease_config, retry_config, conn, pgpool, docker, storage), fields(env = %runtime.env))]
 pub async fn execute<T: TestcaseHandler>(
     runtime: RuntimeConfig,
+    lease_config: LeaseConfig,
+    retry_config: RetryConfig,
     conn: lapin::Connection,
     pgpool: PgPool,
+    storage: BlobStore,
     docker: Docker,
 ) {
     info!("Worker started for environment: {}", runtime.env);
@@ -464,10 +1607,24 @@ pub async fn execute<T: TestcaseHandler>(
 
     info!("Docker pool created with max_size: 2");
 
-    let consumer = get_consumer(&runtime.env, "code", channel)
+    let consumer = get_consumer(&runtime.env, "code", &retry_config.delays_ms, channel)
         .await
         .expect("Unable to get consumer");
 
+    let publisher = conn
+        .create_channel()
+        .await
+        .expect("Error creating retry channel");
+    publisher
+        .confirm_select(ConfirmSelectOptions::default())
+        .await
+        .expect("Error enabling publisher confirms");
+    let retry = RetryPolicy {
+        publisher,
+        queue: runtime.env.clone(),
+        delays_ms: retry_config.delays_ms,
+    };
+
     info!("Consumer started for environment: {}", runtime.env);
 
     let token = CancellationToken::new();
@@ -475,12 +1632,27 @@ pub async fn execute<T: TestcaseHandler>(
 
     let compile_config = CompileConfig {
         compile: runtime.compile,
+        compile_timeout: runtime.compile_timeout,
         run: runtime.run,
         timeout: runtime.timeout,
+        memory: runtime.memory,
     };
+    let lease = Lease {
+        worker_id: lease_config.worker_id.unwrap_or_else(|| {
+            format!(
+                "{}:{}:{}",
+                std::env::var("HOSTNAME").unwrap_or_else(|_| "worker".into()),
+                runtime.env,
+                std::process::id()
+            )
+        }),
+        lease_secs: lease_config.lease_secs,
+    };
+    info!("Judging as worker_id: {}", lease.worker_id);
+
     let task_tracker = TaskTracker::new();
     tokio::select! {
-        _ = listen::<T>(task_tracker.clone(),&docker, &docker_pool, pgpool, compile_config, consumer, token.clone()) => {
+        _ = listen::<T>(task_tracker.clone(),&docker, &docker_pool, pgpool, storage, compile_config, lease, retry, consumer, token.clone()) => {
             info!("Listen loop ended");
             docker_pool.manager().close().await;
         },
@@ -1,5 +1,6 @@
 use bollard::Docker;
 use lapin::{Connection, ConnectionProperties};
+use models::BlobStore;
 use sqlx::PgPool;
 use worker::{
     executer::{TestcaseHandler, execute},
@@ -26,8 +27,19 @@ async fn main() -> anyhow::Result<()> {
 
     let pgpool = PgPool::connect_lazy(&settings.database.url()).unwrap();
 
+    let storage = BlobStore::new(&settings.storage);
+
     let docker = Docker::connect_with_local_defaults()?;
 
-    execute::<DefaultTestcaseHandler>(settings.runtimeconfig, conn, pgpool, docker).await;
+    execute::<DefaultTestcaseHandler>(
+        settings.runtimeconfig,
+        settings.lease,
+        settings.retry,
+        conn,
+        pgpool,
+        storage,
+        docker,
+    )
+    .await;
     Ok(())
 }
@@ -3,7 +3,7 @@ use deadpool::managed::{self, Manager};
 use futures::StreamExt as _;
 use tokio::sync::Mutex;
 
-use crate::docker::create_container;
+use crate::docker::{create_container, set_memory_limit};
 
 pub struct ContainerConn {
     pub id: String,
@@ -86,8 +86,10 @@ impl Manager for ContainerGroup {
                 &conn.id,
                 None::<bollard::query_parameters::InspectContainerOptions>,
             )
+            .await?;
+        // a judged problem may have lowered the limit, the next task starts from the runtime's
+        set_memory_limit(&self.docker, &conn.id, self.memory)
             .await
-            .map(|_| ())
             .map_err(managed::RecycleError::from)
     }
 }
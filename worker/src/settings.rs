@@ -1,12 +1,21 @@
 use std::path::Path;
 
-use models::{DatabaseConfig, RabbitMQConfig, RuntimeConfig, utils::get_configuration};
+use models::{
+    DatabaseConfig, LeaseConfig, RabbitMQConfig, RetryConfig, RuntimeConfig, StorageConfig,
+    utils::get_configuration,
+};
 
 #[derive(serde::Deserialize)]
 pub struct WorkerSettings {
     pub database: DatabaseConfig,
     pub rabbitmq: RabbitMQConfig,
     pub runtimeconfig: RuntimeConfig,
+    #[serde(default)]
+    pub lease: LeaseConfig,
+    #[serde(default)]
+    pub retry: RetryConfig,
+    #[serde(default)]
+    pub storage: StorageConfig,
 }
 
 impl WorkerSettings {
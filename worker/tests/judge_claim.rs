@@ -0,0 +1,161 @@
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use models::{DatabaseConfig, ExecStatus, utils::get_configuration};
+use sqlx::{Connection, Executor, PgConnection, PgPool, postgres::PgConnectOptions};
+use worker::executer::{Lease, claim_submission, update_submit_status};
+
+#[derive(serde::Deserialize)]
+struct TestSettings {
+    database: DatabaseConfig,
+}
+
+async fn test_pool() -> PgPool {
+    let settings = get_configuration::<TestSettings>(Path::new("../configuration"))
+        .expect("Unable to read configuration files");
+    let options = PgConnectOptions::new()
+        .host(&settings.database.host)
+        .port(settings.database.port)
+        .username(&settings.database.user)
+        .password(&settings.database.password);
+    let mut conn = PgConnection::connect_with(&options.clone().database("postgres"))
+        .await
+        .expect("Unable to connect to postgres");
+
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    let dbname = format!("judge_claim_{}_{}", std::process::id(), nanos);
+    conn.execute(format!(r#"CREATE DATABASE "{}";"#, dbname).as_str())
+        .await
+        .expect("failed to create database");
+
+    let pg_pool = PgPool::connect_with(options.database(&dbname))
+        .await
+        .expect("Unable to connect to new database");
+    sqlx::migrate!("../migrations")
+        .run(&pg_pool)
+        .await
+        .expect("Failed to run migrations on test database");
+    pg_pool
+}
+
+async fn pending_submission(pg_pool: &PgPool) -> i64 {
+    let user_id: i64 = sqlx::query_scalar(
+        "INSERT INTO users (username, password, email) VALUES ('judge', 'x', 'judge@example.com') RETURNING user_id",
+    )
+    .fetch_one(pg_pool)
+    .await
+    .unwrap();
+    sqlx::query_scalar(
+        "INSERT INTO submit_status (user_id, problem_id) VALUES ($1, 1) RETURNING submission_id",
+    )
+    .bind(user_id)
+    .fetch_one(pg_pool)
+    .await
+    .unwrap()
+}
+
+fn lease(worker_id: &str) -> Lease {
+    Lease {
+        worker_id: worker_id.into(),
+        lease_secs: 120,
+    }
+}
+
+async fn expire_lease(pg_pool: &PgPool, submission_id: i64) {
+    sqlx::query(
+        "UPDATE submit_status SET lease_expires_at = NOW() - INTERVAL '1 second' WHERE submission_id = $1",
+    )
+    .bind(submission_id)
+    .execute(pg_pool)
+    .await
+    .unwrap();
+}
+
+async fn status(pg_pool: &PgPool, submission_id: i64) -> String {
+    sqlx::query_scalar("SELECT status FROM submit_status WHERE submission_id = $1")
+        .bind(submission_id)
+        .fetch_one(pg_pool)
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn live_lease_is_not_claimed_twice() {
+    let pg_pool = test_pool().await;
+    let submission_id = pending_submission(&pg_pool).await;
+
+    let first = claim_submission(&pg_pool, submission_id, &lease("a"))
+        .await
+        .unwrap();
+    let second = claim_submission(&pg_pool, submission_id, &lease("b"))
+        .await
+        .unwrap();
+
+    assert_eq!(first.unwrap().attempt, 1);
+    assert!(second.is_none());
+}
+
+#[tokio::test]
+async fn stale_attempt_cannot_renew_or_write_a_verdict() {
+    let pg_pool = test_pool().await;
+    let submission_id = pending_submission(&pg_pool).await;
+
+    let stale = claim_submission(&pg_pool, submission_id, &lease("a"))
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(stale.renew(&pg_pool, submission_id, 5).await.unwrap());
+    expire_lease(&pg_pool, submission_id).await;
+    let current = claim_submission(&pg_pool, submission_id, &lease("b"))
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(current.attempt, 2);
+
+    assert!(!stale.renew(&pg_pool, submission_id, 5).await.unwrap());
+    update_submit_status(
+        &pg_pool,
+        submission_id,
+        &stale,
+        String::new(),
+        ExecStatus::WrongAnswer.into(),
+        Some(1),
+        false,
+        Some(0),
+        &[],
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        status(&pg_pool, submission_id).await,
+        <&str>::from(ExecStatus::Pending)
+    );
+
+    update_submit_status(
+        &pg_pool,
+        submission_id,
+        &current,
+        String::new(),
+        ExecStatus::Passed.into(),
+        Some(1),
+        false,
+        Some(100),
+        &[],
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        status(&pg_pool, submission_id).await,
+        <&str>::from(ExecStatus::Passed)
+    );
+    // a verdict ends the lease, so redeliveries are skipped
+    assert!(
+        claim_submission(&pg_pool, submission_id, &lease("c"))
+            .await
+            .unwrap()
+            .is_none()
+    );
+}
@@ -0,0 +1,35 @@
+use lapin::types::{AMQPValue, FieldTable};
+use worker::executer::{RETRY_COUNT_HEADER, RetryPolicy, retry_queue_name};
+
+fn headers(value: AMQPValue) -> Option<FieldTable> {
+    let mut headers = FieldTable::default();
+    headers.insert(RETRY_COUNT_HEADER.into(), value);
+    Some(headers)
+}
+
+#[test]
+fn attempts_come_from_the_retry_count_header() {
+    assert_eq!(
+        RetryPolicy::attempts(&headers(AMQPValue::LongLongInt(2))),
+        2
+    );
+    // first deliveries carry no headers at all
+    assert_eq!(RetryPolicy::attempts(&None), 0);
+    assert_eq!(RetryPolicy::attempts(&Some(FieldTable::default())), 0);
+}
+
+#[test]
+fn malformed_retry_count_counts_as_first_attempt() {
+    assert_eq!(
+        RetryPolicy::attempts(&headers(AMQPValue::LongString("2".into()))),
+        0
+    );
+}
+
+#[test]
+fn retry_queues_are_named_after_queue_and_delay() {
+    assert_eq!(
+        retry_queue_name("python:3.12", 5000),
+        "python:3.12.retry.5000"
+    );
+}
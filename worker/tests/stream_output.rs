@@ -0,0 +1,31 @@
+use models::Checker;
+use worker::executer::output_matches_stream;
+
+async fn matches(checker: Checker, output: &str, expected: &str) -> bool {
+    output_matches_stream(checker, output, expected.as_bytes())
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn tokens_ignore_whitespace_across_lines() {
+    assert!(matches(Checker::Tokens, "1 2\n3", "1\n2 3\n\n").await);
+    assert!(!matches(Checker::Tokens, "1 2 3", "1 2").await);
+    assert!(!matches(Checker::Tokens, "1 2", "1 2 3").await);
+}
+
+#[tokio::test]
+async fn lines_ignore_trailing_whitespace_and_blank_lines() {
+    assert!(matches(Checker::Lines, "a  \r\nb\n", "a\nb\n\n \n").await);
+    assert!(matches(Checker::Lines, "a\n\nb", "a\n\nb\n").await);
+    assert!(!matches(Checker::Lines, "a\nb", "a\n\nb").await);
+    assert!(!matches(Checker::Lines, "a b", "a\nb").await);
+    assert!(!matches(Checker::Lines, "a\nb", "a").await);
+}
+
+#[tokio::test]
+async fn exact_compares_every_byte() {
+    assert!(matches(Checker::Exact, "a\nb\n", "a\nb\n").await);
+    assert!(!matches(Checker::Exact, "a\nb", "a\nb\n").await);
+    assert!(!matches(Checker::Exact, "a\nb\n", "a\nb").await);
+}
@@ -0,0 +1,53 @@
+use std::collections::HashMap;
+
+use models::{ExecStatus, FULL_SCORE, Subtask};
+use worker::executer::submission_score;
+
+fn subtask(name: &str, points: i32) -> Subtask {
+    Subtask {
+        name: name.into(),
+        points,
+        depends_on: Vec::new(),
+        skip_on_failure: true,
+    }
+}
+
+#[test]
+fn problems_without_subtasks_score_all_or_nothing() {
+    let none = HashMap::new();
+
+    assert_eq!(
+        submission_score(ExecStatus::Passed, true, &[], &none),
+        (FULL_SCORE, Vec::new())
+    );
+    assert_eq!(
+        submission_score(ExecStatus::WrongAnswer, true, &[], &none),
+        (0, Vec::new())
+    );
+}
+
+#[test]
+fn subtasks_score_their_passed_testcases() {
+    let subtasks = [subtask("small", 40), subtask("large", 60)];
+    let passed = HashMap::from([("small".to_string(), true), ("large".to_string(), false)]);
+
+    let (score, scores) = submission_score(ExecStatus::WrongAnswer, true, &subtasks, &passed);
+
+    assert_eq!(score, 40);
+    assert_eq!(
+        scores.iter().map(|s| s.passed).collect::<Vec<_>>(),
+        [true, false]
+    );
+}
+
+#[test]
+fn compile_failure_scores_no_subtask() {
+    let subtasks = [subtask("small", 40), subtask("large", 60)];
+
+    // nothing ran, so no subtask has a recorded result
+    let (score, scores) =
+        submission_score(ExecStatus::WrongAnswer, false, &subtasks, &HashMap::new());
+
+    assert_eq!(score, 0);
+    assert!(scores.iter().all(|s| !s.passed && s.points == 0));
+}